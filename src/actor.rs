@@ -0,0 +1,108 @@
+//!
+//! Actors
+//!
+//! An actor owns some state on a dedicated thread and runs the jobs sent to it, in order,
+//! over a channel.  The base station keeps the radio and the sockets to the Field Computer
+//! in separate actors so a stall on one (e.g. re-resolving the Field Computer's hostname)
+//! never delays the others.
+//!
+//! The state is created on the actor's thread and never leaves it, so it doesn't have to
+//! be Send (the radio's SPI bus and GPIO pins only ever live on the radio thread).
+//!
+
+use std::sync::mpsc::{self, Sender};
+use std::thread::{self, JoinHandle};
+
+type Job<T> = Box<dyn FnOnce(&mut T) + Send>;
+
+pub struct Actor<T> {
+    name: String,
+    jobs: Option<Sender<Job<T>>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl<T: 'static> Actor<T> {
+    /// Start an actor whose state is created by `open` on the actor's thread, returning
+    /// the error from `open` if the state couldn't be created
+    pub fn spawn<F>(name: &str, open: F) -> Result<Self, String>
+    where
+        F: FnOnce() -> Result<T, String> + Send + 'static,
+    {
+        let (jobs_tx, jobs_rx) = mpsc::channel::<Job<T>>();
+        let (started_tx, started_rx) = mpsc::channel();
+        let handle = thread::Builder::new()
+            .name(name.to_string())
+            .spawn(move || {
+                let mut state = match open() {
+                    Ok(state) => state,
+                    Err(err) => {
+                        let _ = started_tx.send(Err(err));
+                        return;
+                    },
+                };
+                let _ = started_tx.send(Ok(()));
+
+                for job in jobs_rx {
+                    job(&mut state);
+                }
+            })
+            .map_err(|err| format!("Unable to spawn the {} thread: {}", name, err))?;
+
+        match started_rx.recv() {
+            Ok(Ok(())) => Ok(Self {
+                name: name.to_string(),
+                jobs: Some(jobs_tx),
+                handle: Some(handle),
+            }),
+            Ok(Err(err)) => {
+                let _ = handle.join();
+                Err(err)
+            },
+            Err(_) => {
+                let _ = handle.join();
+                Err(format!("The {} thread exited while starting", name))
+            },
+        }
+    }
+
+    /// Run a job on the actor without waiting for it, returning false if the actor has
+    /// stopped
+    pub fn cast<F>(&self, job: F) -> bool
+    where
+        F: FnOnce(&mut T) + Send + 'static,
+    {
+        match self.jobs.as_ref() {
+            Some(jobs) => jobs.send(Box::new(job)).is_ok(),
+            None => false,
+        }
+    }
+
+    /// Run a job on the actor and wait for its result (None if the actor has stopped)
+    pub fn call<R, F>(&self, job: F) -> Option<R>
+    where
+        R: Send + 'static,
+        F: FnOnce(&mut T) -> R + Send + 'static,
+    {
+        let (result_tx, result_rx) = mpsc::channel();
+        if !self.cast(move |state| { let _ = result_tx.send(job(state)); }) {
+            return None;
+        }
+        result_rx.recv().ok()
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl<T> Drop for Actor<T> {
+    fn drop(&mut self) {
+        // Closing the channel stops the thread once it has run every job already sent
+        self.jobs.take();
+        if let Some(handle) = self.handle.take() {
+            if handle.join().is_err() {
+                println!("The {} thread panicked", self.name);
+            }
+        }
+    }
+}
@@ -0,0 +1,112 @@
+//!
+//! The set of robots the Timeout Checker currently considers alive.
+//!
+//! Alive robots can be sent to the Field Computer in one of two encodings:
+//!
+//! Legacy: [bitmask: u16] (robot i is alive if bit i is set), so at most 16 robots.
+//! Variable: [num_robots: u8][bitmask: ceil(num_robots / 8) bytes] where robot i is
+//!     alive if bit (i % 8) of byte (i / 8) is set.
+//!
+
+use clap::ValueEnum;
+
+/// The maximum number of robots the legacy (u16 bitmask) encoding can represent
+pub const LEGACY_MAX_ROBOTS: usize = 16;
+
+/// The encoding used to send alive robots to the Field Computer
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum AliveRobotsEncoding {
+    /// A 16 bit bitmask (at most 16 robots)
+    Legacy,
+    /// A robot count followed by a variable length bitmask (at most 255 robots)
+    Variable,
+}
+
+impl AliveRobotsEncoding {
+    /// The maximum number of robots this encoding can represent
+    pub fn max_robots(&self) -> usize {
+        match self {
+            AliveRobotsEncoding::Legacy => LEGACY_MAX_ROBOTS,
+            AliveRobotsEncoding::Variable => u8::MAX as usize,
+        }
+    }
+}
+
+/// The alive status of each robot in play
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AliveRobots {
+    num_robots: u8,
+    bitmask: Vec<u8>,
+}
+
+impl AliveRobots {
+    /// Create a set of `num_robots` robots that are all dead
+    pub fn new(num_robots: u8) -> Self {
+        Self {
+            num_robots,
+            bitmask: vec![0u8; (num_robots as usize).div_ceil(8)],
+        }
+    }
+
+    pub fn num_robots(&self) -> u8 {
+        self.num_robots
+    }
+
+    pub fn set_alive(&mut self, robot_id: u8, alive: bool) {
+        if robot_id >= self.num_robots {
+            return;
+        }
+
+        let (byte, bit) = (robot_id as usize / 8, robot_id % 8);
+        if alive {
+            self.bitmask[byte] |= 1 << bit;
+        } else {
+            self.bitmask[byte] &= !(1 << bit);
+        }
+    }
+
+    pub fn is_alive(&self, robot_id: u8) -> bool {
+        if robot_id >= self.num_robots {
+            return false;
+        }
+
+        self.bitmask[robot_id as usize / 8] & (1 << (robot_id % 8)) != 0
+    }
+
+    /// The ids of every robot that is alive
+    pub fn alive_ids(&self) -> Vec<u8> {
+        (0..self.num_robots).filter(|robot_id| self.is_alive(*robot_id)).collect()
+    }
+
+    /// Encode the alive robots for the Field Computer
+    pub fn encode(&self, encoding: AliveRobotsEncoding) -> Vec<u8> {
+        match encoding {
+            AliveRobotsEncoding::Legacy => {
+                let mut legacy = 0u16;
+                for robot_id in self.alive_ids().into_iter().filter(|id| (*id as usize) < LEGACY_MAX_ROBOTS) {
+                    legacy |= 1 << robot_id;
+                }
+                legacy.to_le_bytes().to_vec()
+            },
+            AliveRobotsEncoding::Variable => {
+                let mut buffer = Vec::with_capacity(1 + self.bitmask.len());
+                buffer.push(self.num_robots);
+                buffer.extend_from_slice(&self.bitmask);
+                buffer
+            },
+        }
+    }
+
+    /// Decode alive robots sent using the variable length encoding
+    pub fn decode(bytes: &[u8]) -> Option<Self> {
+        let (num_robots, bitmask) = bytes.split_first()?;
+        if bitmask.len() != (*num_robots as usize).div_ceil(8) {
+            return None;
+        }
+
+        Some(Self {
+            num_robots: *num_robots,
+            bitmask: bitmask.to_vec(),
+        })
+    }
+}
@@ -0,0 +1,590 @@
+//!
+//! Base station tools for checking the radio and the field computer link on the Pi.
+//!
+//! bs-tools ping-pong                        Bounce a counter off a robot running ping-pong
+//! bs-tools drive-line --robot-id 0          Drive a robot forward in a straight line
+//! bs-tools bench-send --robot-id 5          Count the acknowledged Control Messages
+//! bs-tools bench-receive                    Print every Robot Status Message received
+//! bs-tools bench-matrix --robot-id 5 --report venue.csv
+//! bs-tools send-status --address 10.42.0.1:8001
+//! bs-tools send-alive --address 10.42.0.1:8002
+//! bs-tools robot-alive --robot-id 0         Check a robot answers stop commands
+//! bs-tools fake-robots                      Answer the field computer as the robots would
+//!
+//! The radio tools share the radio configuration (--channel, --pa-level and --robot-config)
+//! and pin definitions with the base station.  Every tool runs until it's done or ctrl-c is
+//! pressed.
+//!
+
+use std::{convert::Infallible, error::Error, sync::mpsc::{self, Receiver}, thread, time::{Duration, Instant}};
+
+use embedded_hal::blocking::delay::DelayMs;
+
+use ncomm::publisher_subscriber::{Publish, Receive};
+
+use packed_struct::{PackedStruct, PackedStructSlice};
+
+use rand::random;
+
+use robocup_base_station::alive_robots::{AliveRobots, AliveRobotsEncoding};
+use robocup_base_station::config::{pa_level_name, parse_pa_level, AddressSet, RadioConfig, RobotTable};
+use robocup_base_station::publishers::control_subscriber::ControlMessageSubscriber;
+use robocup_base_station::publishers::datagram::DatagramPublisher;
+use robocup_base_station::radio_bench::{BenchMatrix, BenchReport, RadioBench};
+use robocup_base_station::{RADIO_CE, RADIO_CSN};
+
+use robojackets_robocup_rtp::control_message::{ControlMessageBuilder, CONTROL_MESSAGE_SIZE};
+use robojackets_robocup_rtp::robot_status_message::{RobotStatusMessage, RobotStatusMessageBuilder, ROBOT_STATUS_SIZE};
+use robojackets_robocup_rtp::TEAM;
+
+use rppal::{spi::{self, Spi, Bus, SlaveSelect, Mode}, gpio::{Gpio, OutputPin}, hal::Delay};
+
+use rtic_nrf24l01::Radio;
+
+use clap::{Args, Parser, Subcommand};
+
+/// The Arguments passed to the base station tools.
+#[derive(Parser)]
+#[command(author, version, about, long_about = None)]
+struct Cli {
+    #[command(subcommand)]
+    pub tool: Tool,
+}
+
+#[derive(Subcommand)]
+enum Tool {
+    /// Bounce a counter back and forth with a robot running ping-pong
+    PingPong {
+        #[command(flatten)]
+        radio: RadioArgs,
+
+        // The robot to play ping-pong with
+        #[arg(long, default_value_t = 0)]
+        robot_id: u8,
+
+        // The delay between each ping
+        #[arg(long, default_value_t = 1_000)]
+        interval_ms: u64,
+    },
+    /// Drive a robot forward in a straight line and then stop it
+    DriveLine {
+        #[command(flatten)]
+        radio: RadioArgs,
+
+        #[arg(long, default_value_t = 0)]
+        robot_id: u8,
+
+        // How long to drive for
+        #[arg(long, default_value_t = 2_000)]
+        duration_ms: u64,
+
+        // The forward velocity of the robot
+        #[arg(long, default_value_t = 1.0)]
+        speed: f32,
+
+        // The delay between each Control Message
+        #[arg(long, default_value_t = 50)]
+        interval_ms: u64,
+    },
+    /// Send random Control Messages to a robot and count how many are acknowledged
+    BenchSend {
+        #[command(flatten)]
+        radio: RadioArgs,
+
+        #[arg(long, default_value_t = 0)]
+        robot_id: u8,
+
+        // The number of Control Messages to send
+        #[arg(long, default_value_t = 100)]
+        messages: usize,
+
+        // The delay between each Control Message
+        #[arg(long, default_value_t = 50)]
+        interval_ms: u64,
+    },
+    /// Benchmark every combination of PA level and retransmit settings against a robot on
+    /// the robot's channel (--channel) and data rate
+    BenchMatrix {
+        #[command(flatten)]
+        radio: RadioArgs,
+
+        #[arg(long, default_value_t = 0)]
+        robot_id: u8,
+
+        // The PA levels to sweep (e.g. min,low,high,max, defaults to --pa-level)
+        #[arg(long, conflicts_with = "pa_level")]
+        pa_levels: Option<String>,
+
+        // The robot's data rate (250kbps, 1mbps or 2mbps)
+        #[arg(long, default_value_t = String::from("1mbps"))]
+        data_rate: String,
+
+        // The retransmit settings to sweep (delay_us:count)
+        #[arg(long, default_value_t = String::from("500:15"))]
+        retransmits: String,
+
+        // The number of Control Messages to send with each combination
+        #[arg(long, default_value_t = 100)]
+        messages: usize,
+
+        // The delay between each Control Message
+        #[arg(long, default_value_t = 10)]
+        interval_ms: u64,
+
+        // How long to wait for the robot's status after each Control Message
+        #[arg(long, default_value_t = 20)]
+        response_timeout_ms: u64,
+
+        // Where to write the report (JSON for .json files, CSV otherwise)
+        #[arg(long, default_value_t = String::from("bench-matrix.csv"))]
+        report: String,
+    },
+    /// Listen for Robot Status Messages and print them
+    BenchReceive {
+        #[command(flatten)]
+        radio: RadioArgs,
+
+        // The delay between each check for Robot Status Messages
+        #[arg(long, default_value_t = 50)]
+        interval_ms: u64,
+    },
+    /// Send fake Robot Status Messages to the field computer
+    SendStatus {
+        #[arg(long, default_value_t = String::from("0.0.0.0:8001"))]
+        bind: String,
+
+        #[arg(long, default_value_t = String::from("10.42.0.1:8001"))]
+        address: String,
+
+        #[arg(long, default_value_t = 0)]
+        robot_id: u8,
+
+        #[arg(long, default_value_t = 100)]
+        interval_ms: u64,
+    },
+    /// Send a rotating alive robots bitmask to the field computer
+    SendAlive {
+        #[arg(long, default_value_t = String::from("0.0.0.0:8002"))]
+        bind: String,
+
+        #[arg(long, default_value_t = String::from("10.42.0.1:8002"))]
+        address: String,
+
+        #[arg(long, default_value_t = 500)]
+        interval_ms: u64,
+    },
+    /// Send stop commands to a robot and print its acknowledgements and statuses
+    RobotAlive {
+        #[command(flatten)]
+        radio: RadioArgs,
+
+        #[arg(long, default_value_t = 0)]
+        robot_id: u8,
+
+        // The delay between each stop command
+        #[arg(long, default_value_t = 100)]
+        interval_ms: u64,
+    },
+    /// Answer every Control Message from the field computer with a Robot Status Message
+    /// and an alive robots bitmask, as the robots would
+    FakeRobots {
+        #[arg(long, default_value_t = String::from("0.0.0.0:8000"))]
+        control_bind: String,
+
+        #[arg(long, default_value_t = String::from("0.0.0.0:8001"))]
+        status_bind: String,
+
+        #[arg(long, default_value_t = String::from("10.42.0.1:8001"))]
+        status_address: String,
+
+        #[arg(long, default_value_t = String::from("0.0.0.0:8002"))]
+        alive_bind: String,
+
+        #[arg(long, default_value_t = String::from("10.42.0.1:8002"))]
+        alive_address: String,
+
+        // The delay between each check for Control Messages
+        #[arg(long, default_value_t = 250)]
+        interval_ms: u64,
+    },
+}
+
+/// The radio configuration shared by the radio tools
+#[derive(Args)]
+struct RadioArgs {
+    // The radio channel (f = 2400 + CHANNEL (MHz))
+    #[arg(long, default_value_t = robocup_base_station::CHANNEL)]
+    pub channel: u8,
+
+    // The power amplifier level of the radio (min, low, high or max)
+    #[arg(long, default_value_t = String::from("low"))]
+    pub pa_level: String,
+
+    // Path to a TOML robot table (defaults to the robot radio addresses of the site)
+    #[arg(long)]
+    pub robot_config: Option<String>,
+
+    // The site whose radio addresses to use (0 is the compile-time addresses)
+    #[arg(long, default_value_t = 0)]
+    pub site_id: u8,
+}
+
+impl RadioArgs {
+    fn radio_config(&self) -> Result<RadioConfig, Box<dyn Error>> {
+        let radio_config = RadioConfig {
+            channel: self.channel,
+            pa_level: parse_pa_level(&self.pa_level).ok_or("Invalid PA Level (expected min, low, high or max)")?,
+            base_station_address: AddressSet::new(TEAM, self.site_id).base_station_address(),
+        };
+        radio_config.validate()?;
+        Ok(radio_config)
+    }
+
+    fn robot_address(&self, robot_id: u8) -> Result<[u8; 5], Box<dyn Error>> {
+        let robot_table = match self.robot_config.as_ref() {
+            Some(path) => RobotTable::from_file(path)?,
+            None => AddressSet::new(TEAM, self.site_id).robot_table(),
+        };
+        robot_table.radio_address(robot_id)
+            .ok_or_else(|| format!("robot {} is not in the robot table", robot_id).into())
+    }
+}
+
+/// The nRF24L01+ on the base station
+struct HardwareRadio {
+    radio: Radio<OutputPin, OutputPin, Spi, Delay, Infallible, spi::Error>,
+    spi: Spi,
+    delay: Delay,
+}
+
+impl HardwareRadio {
+    /// Acquire and configure the radio to send to `writing_address` and receive payloads of
+    /// `payload_size` bytes from the robots
+    fn open(radio_config: &RadioConfig, payload_size: u8, writing_address: [u8; 5]) -> Result<Self, Box<dyn Error>> {
+        let mut spi = Spi::new(Bus::Spi0, SlaveSelect::Ss0, 1_000_000, Mode::Mode0)?;
+        let gpio = Gpio::new()?;
+        let csn = gpio.get(RADIO_CSN)?.into_output();
+        let ce = gpio.get(RADIO_CE)?.into_output();
+        let mut delay = Delay::new();
+
+        let mut radio = Radio::new(ce, csn);
+        if let Err(err) = radio.begin(&mut spi, &mut delay) {
+            println!("Configuration: {:?}", radio.get_registers(&mut spi, &mut delay));
+            return Err(format!("Unable to initialize the radio: {:?}", err).into());
+        }
+
+        radio.set_pa_level(radio_config.pa_level, &mut spi, &mut delay);
+        radio.set_channel(radio_config.channel, &mut spi, &mut delay);
+        radio.set_payload_size(payload_size, &mut spi, &mut delay);
+        radio.open_writing_pipe(writing_address, &mut spi, &mut delay);
+        radio.open_reading_pipe(1, radio_config.base_station_address, &mut spi, &mut delay);
+        radio.stop_listening(&mut spi, &mut delay);
+
+        Ok(Self { radio, spi, delay })
+    }
+
+    fn write(&mut self, payload: &[u8]) -> bool {
+        let acknowledged = self.radio.write(payload, &mut self.spi, &mut self.delay);
+        self.radio.flush_tx(&mut self.spi, &mut self.delay);
+        acknowledged
+    }
+
+    fn start_listening(&mut self) {
+        self.radio.start_listening(&mut self.spi, &mut self.delay);
+    }
+
+    fn stop_listening(&mut self) {
+        self.radio.stop_listening(&mut self.spi, &mut self.delay);
+    }
+
+    fn read(&mut self, buffer: &mut [u8]) -> bool {
+        if !self.radio.packet_ready(&mut self.spi, &mut self.delay) {
+            return false;
+        }
+        self.radio.read(buffer, &mut self.spi, &mut self.delay);
+        true
+    }
+}
+
+/// Stop the tools when ctrl-c is pressed
+fn stop_signal() -> Receiver<bool> {
+    let (stop_tx, stop_rx) = mpsc::channel();
+    ctrlc::set_handler(move || {
+        let _ = stop_tx.send(true);
+    }).expect("Unable to set ctrl-c handler");
+    stop_rx
+}
+
+fn ping_pong(radio_args: RadioArgs, robot_id: u8, interval: Duration) -> Result<(), Box<dyn Error>> {
+    let mut radio = HardwareRadio::open(&radio_args.radio_config()?, 4, radio_args.robot_address(robot_id)?)?;
+    let stop = stop_signal();
+
+    let mut listening = false;
+    let mut payload = 0u32;
+    while stop.try_recv().is_err() {
+        if listening {
+            let mut read_buffer = [0u8; 4];
+            if radio.read(&mut read_buffer) {
+                payload = u32::from_le_bytes(read_buffer);
+                println!("Received: {}", payload);
+
+                listening = false;
+                radio.stop_listening();
+                payload = payload.wrapping_add(1);
+            }
+        } else if radio.write(&payload.to_le_bytes()) {
+            println!("Sent: {}", payload);
+            listening = true;
+            radio.start_listening();
+        } else {
+            println!("Transmission Timed Out");
+        }
+
+        thread::sleep(interval);
+    }
+    Ok(())
+}
+
+fn drive_line(radio_args: RadioArgs, robot_id: u8, duration: Duration, speed: f32, interval: Duration) -> Result<(), Box<dyn Error>> {
+    let mut radio = HardwareRadio::open(&radio_args.radio_config()?, CONTROL_MESSAGE_SIZE as u8, radio_args.robot_address(robot_id)?)?;
+    let stop = stop_signal();
+
+    let start = Instant::now();
+    while start.elapsed() < duration && stop.try_recv().is_err() {
+        println!("Forward");
+        let control_message = ControlMessageBuilder::new()
+            .team(TEAM)
+            .robot_id(robot_id)
+            .body_y(speed)
+            .build();
+        if !radio.write(&control_message.pack()?) {
+            println!("No Ack");
+        }
+        radio.delay.delay_ms(interval.as_millis() as u32);
+    }
+
+    // Always stop the robot (even when interrupted)
+    let control_message = ControlMessageBuilder::new()
+        .team(TEAM)
+        .robot_id(robot_id)
+        .build();
+    radio.write(&control_message.pack()?);
+    println!("Done");
+    Ok(())
+}
+
+fn bench_send(radio_args: RadioArgs, robot_id: u8, messages: usize, interval: Duration) -> Result<(), Box<dyn Error>> {
+    let mut radio = HardwareRadio::open(&radio_args.radio_config()?, CONTROL_MESSAGE_SIZE as u8, radio_args.robot_address(robot_id)?)?;
+    let stop = stop_signal();
+
+    let mut sent = 0;
+    let mut acknowledged_packets = 0;
+    while sent < messages && stop.try_recv().is_err() {
+        let control_message = ControlMessageBuilder::new()
+            .team(TEAM)
+            .robot_id(robot_id)
+            .body_x(random())
+            .body_y(random())
+            .body_w(random())
+            .build();
+
+        sent += 1;
+        if radio.write(&control_message.pack()?) {
+            acknowledged_packets += 1;
+        }
+        radio.delay.delay_ms(interval.as_millis() as u32);
+    }
+
+    println!("{} / {} Packets Were Acknowledged", acknowledged_packets, sent);
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+fn bench_matrix(
+    radio_args: RadioArgs,
+    robot_id: u8,
+    matrix: BenchMatrix,
+    messages: usize,
+    interval: Duration,
+    response_timeout: Duration,
+    report_path: &str,
+) -> Result<(), Box<dyn Error>> {
+    let robot_address = radio_args.robot_address(robot_id)?;
+    let spi = Spi::new(Bus::Spi0, SlaveSelect::Ss0, 1_000_000, Mode::Mode0)?;
+    let gpio = Gpio::new()?;
+    let csn = gpio.get(RADIO_CSN)?.into_output();
+    let ce = gpio.get(RADIO_CE)?.into_output();
+
+    let mut bench = RadioBench::new(ce, csn, spi, Delay::new());
+    if !bench.begin() {
+        return Err("Unable to initialize the radio".into());
+    }
+    let stop = stop_signal();
+
+    let mut report = BenchReport::new(robot_id);
+    for settings in matrix.combinations() {
+        if stop.try_recv().is_ok() {
+            break;
+        }
+
+        let result = bench.run(&settings, robot_id, robot_address, messages, interval, response_timeout);
+        println!(
+            "{} channel {} {} {}us x{}: {}/{} acked, {:.2} retransmits, {} responses",
+            pa_level_name(settings.pa_level),
+            settings.channel,
+            settings.data_rate.name(),
+            settings.retransmits.delay_us,
+            settings.retransmits.count,
+            result.acked,
+            result.sent,
+            result.mean_retransmits(),
+            result.responses,
+        );
+        report.results.push(result);
+    }
+
+    report.write(report_path)?;
+    println!("Wrote {}", report_path);
+    Ok(())
+}
+
+fn bench_receive(radio_args: RadioArgs, interval: Duration) -> Result<(), Box<dyn Error>> {
+    let mut radio = HardwareRadio::open(&radio_args.radio_config()?, ROBOT_STATUS_SIZE as u8, radio_args.robot_address(0)?)?;
+    radio.start_listening();
+    let stop = stop_signal();
+
+    while stop.try_recv().is_err() {
+        let mut buffer = [0u8; ROBOT_STATUS_SIZE];
+        if radio.read(&mut buffer) {
+            match RobotStatusMessage::unpack_from_slice(&buffer[..]) {
+                Ok(data) => println!("Received: {:?}", data),
+                Err(err) => println!("Unable to Unpack Data: {:?}", err),
+            }
+            radio.radio.flush_rx(&mut radio.spi, &mut radio.delay);
+        }
+        radio.delay.delay_ms(interval.as_millis() as u32);
+    }
+    Ok(())
+}
+
+fn send_status(bind: &str, address: &str, robot_id: u8, interval: Duration) -> Result<(), Box<dyn Error>> {
+    let mut robot_status_publisher = DatagramPublisher::new(bind, vec![address]);
+    let stop = stop_signal();
+
+    while stop.try_recv().is_err() {
+        let robot_status_message = RobotStatusMessageBuilder::new()
+            .robot_id(robot_id)
+            .ball_sense_status(true)
+            .battery_voltage(10)
+            .fpga_status(true)
+            .kick_healthy(true)
+            .kick_status(true)
+            .motor_errors(0)
+            .team(TEAM)
+            .build();
+
+        println!("Sending Robot Status Message\n{:?}", robot_status_message);
+        robot_status_publisher.send(robot_status_message);
+        thread::sleep(interval);
+    }
+    Ok(())
+}
+
+fn send_alive(bind: &str, address: &str, interval: Duration) -> Result<(), Box<dyn Error>> {
+    let mut alive_robots_publisher = DatagramPublisher::new(bind, vec![address]);
+    let stop = stop_signal();
+
+    let mut counter = 0;
+    while stop.try_recv().is_err() {
+        let mut alive_robots = AliveRobots::new(16);
+        alive_robots.set_alive(counter, true);
+        counter = (counter + 1) % 16;
+
+        println!("Sending Alive Robots: {:?}", alive_robots.alive_ids());
+        alive_robots_publisher.send(alive_robots.encode(AliveRobotsEncoding::Legacy));
+        thread::sleep(interval);
+    }
+    Ok(())
+}
+
+fn robot_alive(radio_args: RadioArgs, robot_id: u8, interval: Duration) -> Result<(), Box<dyn Error>> {
+    let mut radio = HardwareRadio::open(&radio_args.radio_config()?, CONTROL_MESSAGE_SIZE as u8, radio_args.robot_address(robot_id)?)?;
+    let stop = stop_signal();
+
+    let control_message = ControlMessageBuilder::new()
+        .team(TEAM)
+        .robot_id(robot_id)
+        .build()
+        .pack()?;
+    while stop.try_recv().is_err() {
+        // Give the robot a few attempts to answer before the next stop command
+        for _ in 0..5 {
+            radio.stop_listening();
+            if radio.write(&control_message) {
+                println!("Robot Acknowledged");
+            }
+            radio.start_listening();
+
+            let mut buffer = [0u8; ROBOT_STATUS_SIZE];
+            if radio.read(&mut buffer) {
+                match RobotStatusMessage::unpack_from_slice(&buffer[..]) {
+                    Ok(data) => println!("Received: {:?}", data),
+                    Err(err) => println!("Unable to Unpack Data: {:?}", err),
+                }
+                radio.radio.flush_rx(&mut radio.spi, &mut radio.delay);
+                break;
+            }
+            radio.delay.delay_ms(10u32);
+        }
+        radio.delay.delay_ms(interval.as_millis() as u32);
+    }
+    radio.stop_listening();
+    Ok(())
+}
+
+fn fake_robots(control_bind: &str, status: (&str, &str), alive: (&str, &str), interval: Duration) -> Result<(), Box<dyn Error>> {
+    let mut control_message_subscriber = ControlMessageSubscriber::new(control_bind);
+    let mut robot_status_publisher = DatagramPublisher::new(status.0, vec![status.1]);
+    let mut alive_robots_publisher = DatagramPublisher::new(alive.0, vec![alive.1]);
+    let stop = stop_signal();
+
+    while stop.try_recv().is_err() {
+        control_message_subscriber.update_data();
+
+        let mut alive_robots = AliveRobots::new(16);
+        let mut robot_ids: Vec<u8> = control_message_subscriber.data.keys().copied().collect();
+        robot_ids.sort_unstable();
+        for robot_id in robot_ids {
+            println!("Received: {:?}", control_message_subscriber.data[&robot_id]);
+            robot_status_publisher.send(RobotStatusMessageBuilder::new().robot_id(robot_id).team(TEAM).build());
+            alive_robots.set_alive(robot_id, true);
+        }
+        control_message_subscriber.data.clear();
+
+        alive_robots_publisher.send(alive_robots.encode(AliveRobotsEncoding::Legacy));
+        thread::sleep(interval);
+    }
+    Ok(())
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    match Cli::parse().tool {
+        Tool::PingPong { radio, robot_id, interval_ms } => ping_pong(radio, robot_id, Duration::from_millis(interval_ms)),
+        Tool::DriveLine { radio, robot_id, duration_ms, speed, interval_ms } => {
+            drive_line(radio, robot_id, Duration::from_millis(duration_ms), speed, Duration::from_millis(interval_ms))
+        },
+        Tool::BenchSend { radio, robot_id, messages, interval_ms } => bench_send(radio, robot_id, messages, Duration::from_millis(interval_ms)),
+        Tool::BenchMatrix { radio, robot_id, pa_levels, data_rate, retransmits, messages, interval_ms, response_timeout_ms, report } => {
+            let pa_levels = pa_levels.unwrap_or_else(|| radio.pa_level.clone());
+            let matrix = BenchMatrix::parse(&pa_levels, &retransmits, radio.channel, &data_rate)?;
+            bench_matrix(radio, robot_id, matrix, messages, Duration::from_millis(interval_ms), Duration::from_millis(response_timeout_ms), &report)
+        },
+        Tool::BenchReceive { radio, interval_ms } => bench_receive(radio, Duration::from_millis(interval_ms)),
+        Tool::SendStatus { bind, address, robot_id, interval_ms } => send_status(&bind, &address, robot_id, Duration::from_millis(interval_ms)),
+        Tool::SendAlive { bind, address, interval_ms } => send_alive(&bind, &address, Duration::from_millis(interval_ms)),
+        Tool::RobotAlive { radio, robot_id, interval_ms } => robot_alive(radio, robot_id, Duration::from_millis(interval_ms)),
+        Tool::FakeRobots { control_bind, status_bind, status_address, alive_bind, alive_address, interval_ms } => {
+            fake_robots(&control_bind, (&status_bind, &status_address), (&alive_bind, &alive_address), Duration::from_millis(interval_ms))
+        },
+    }
+}
@@ -0,0 +1,127 @@
+//!
+//! Simulated robots for running the base station without a radio.
+//!
+//! Start the simulated robots and then point the base station at them:
+//!
+//! robot-sim --bind 127.0.0.1:9001 --base-station 127.0.0.1:9000
+//! robocup-base-station --virtual-radio 127.0.0.1:9001 --virtual-radio-bind 127.0.0.1:9000
+//!
+//! Unix datagram sockets can be used instead of UDP by giving both addresses as unix:/path.
+//!
+
+use std::{error::Error, sync::mpsc, time::{Duration, Instant}};
+
+use robocup_base_station::config::{AddressSet, RobotTable};
+use robocup_base_station::publishers::virtual_radio::VirtualLink;
+use robocup_base_station::radio_auth::{AuthKey, ControlVerifier};
+use robocup_base_station::robot_sim::{RobotSimulator, SimulatedRobot};
+
+use robojackets_robocup_rtp::{Team, TEAM};
+
+use clap::Parser;
+
+/// The Arguments passed to the robot simulator.
+#[derive(Parser)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    // The address the simulated robots listen on (host:port or unix:/path)
+    #[arg(long, default_value_t = String::from("127.0.0.1:9001"))]
+    pub bind: String,
+
+    // The address of the base station's virtual radio (host:port or unix:/path)
+    #[arg(long, default_value_t = String::from("127.0.0.1:9000"))]
+    pub base_station: String,
+
+    // The ids of the robots to simulate
+    #[arg(long, value_delimiter = ',', default_values_t = [0u8, 1, 2, 3, 4, 5])]
+    pub robot_ids: Vec<u8>,
+
+    // Path to a TOML robot table (defaults to the robot radio addresses of the site)
+    #[arg(long)]
+    pub robot_config: Option<String>,
+
+    // The radio channel the simulated robots listen on
+    #[arg(long, default_value_t = robocup_base_station::CHANNEL)]
+    pub channel: u8,
+
+    // Only accept Control Messages authenticated with the pre-shared key (hex) in this file
+    #[arg(long)]
+    pub auth_key_file: Option<String>,
+
+    // The site whose radio addresses to use (0 is the compile-time addresses)
+    #[arg(long, default_value_t = 0)]
+    pub site_id: u8,
+
+    // Also simulate the other team's robots (for a base station in dual-team mode)
+    #[arg(long, default_value_t = false)]
+    pub dual_team: bool,
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let args = Args::parse();
+
+    let address_set = AddressSet::new(TEAM, args.site_id);
+    let robot_table = match args.robot_config.as_ref() {
+        Some(path) => RobotTable::from_file(path)?,
+        None => address_set.robot_table(),
+    };
+
+    let mut robots = Vec::new();
+    for robot_id in args.robot_ids.iter() {
+        let Some(robot) = robot_table.get(*robot_id) else {
+            return Err(format!("robot {} is not in the robot table", robot_id).into());
+        };
+        robots.push(SimulatedRobot::new(*robot_id, TEAM, robot.radio_address));
+    }
+    if args.dual_team {
+        let other_team = match TEAM {
+            Team::Blue => Team::Yellow,
+            Team::Yellow => Team::Blue,
+        };
+        let other_robot_table = AddressSet::new(other_team, args.site_id).robot_table();
+        for robot_id in args.robot_ids.iter() {
+            let Some(robot) = other_robot_table.get(*robot_id) else {
+                return Err(format!("robot {} is not in the other team's robot table", robot_id).into());
+            };
+            robots.push(SimulatedRobot::new(*robot_id, other_team, robot.radio_address));
+        }
+    }
+
+    let link = VirtualLink::open(&args.bind, &args.base_station)?;
+    let mut simulator = RobotSimulator::new(link, args.channel, robots);
+    simulator.set_base_station_address(address_set.base_station_address());
+    if let Some(path) = args.auth_key_file.as_ref() {
+        simulator.set_verifier(ControlVerifier::new(AuthKey::from_file(path)?));
+    }
+    println!("Simulating Robots {:?} on {}", args.robot_ids, args.bind);
+
+    let (stop_tx, stop_rx) = mpsc::channel();
+    ctrlc::set_handler(move || {
+        let _ = stop_tx.send(true);
+    }).expect("Unable to set ctrl-c handler");
+
+    let mut last_report = Instant::now();
+    while stop_rx.try_recv().is_err() {
+        simulator.poll(Duration::from_millis(100))?;
+
+        if last_report.elapsed() >= Duration::from_secs(1) {
+            for robot in simulator.robots() {
+                match robot.last_command.as_ref() {
+                    Some(command) => println!(
+                        "Robot {} ({:?}): {} commands (body x: {}, body y: {}, body w: {})",
+                        robot.robot_id,
+                        robot.team,
+                        robot.commands_received,
+                        *command.body_x,
+                        *command.body_y,
+                        *command.body_w,
+                    ),
+                    None => println!("Robot {} ({:?}): no commands", robot.robot_id, robot.team),
+                }
+            }
+            last_report = Instant::now();
+        }
+    }
+
+    Ok(())
+}
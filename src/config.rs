@@ -0,0 +1,348 @@
+//!
+//! Base Station Configuration
+//!
+//! The robot table (robot id, radio address and whether the robot is enabled) can be
+//! loaded from a TOML file of the form:
+//!
+//! ```toml
+//! [[robots]]
+//! id = 0
+//! radio_address = [0xC3, 0xC3, 0xC3, 0xC3, 0xC1]
+//! enabled = true
+//! ```
+//!
+//! When no file is given the table is built from `ROBOT_RADIO_ADDRESSES`, or from the
+//! address set of a site (see `AddressSet`) so two base stations on the same field don't
+//! talk to each other's robots.
+//!
+
+use std::collections::HashSet;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use packed_struct::PackedStruct;
+
+use serde::Deserialize;
+
+use robojackets_robocup_rtp::control_message::{ControlMessage, ControlMessageBuilder};
+use robojackets_robocup_rtp::{Team, BASE_STATION_ADDRESS, ROBOT_RADIO_ADDRESSES, TEAM};
+
+use rtic_nrf24l01::config::power_amplifier::PowerAmplifier;
+
+use crate::{BASE_AMPLIFICATION_LEVEL, CHANNEL};
+
+/// An error in the configuration of the base station
+#[derive(Debug)]
+pub enum ConfigError {
+    /// The configuration file couldn't be read
+    Io(std::io::Error),
+    /// The configuration file isn't valid TOML (or is missing fields)
+    Parse(toml::de::Error),
+    /// The same robot id appears more than once in the robot table
+    DuplicateRobot(u8),
+    /// The robot id can't be represented in a Control Message
+    UnrepresentableRobotId(u8),
+    /// A robot in play has no entry in the robot table
+    MissingRobot(u8),
+    /// More robots were requested than the protocol can represent
+    TooManyRobots { requested: usize, max: usize },
+    /// The radio channel is outside of the nRF24L01+ range (0-125)
+    InvalidChannel(u8),
+    /// A fault injection scenario has an invalid setting
+    InvalidFaultScenario(String),
+    /// The radio authentication key isn't valid hex of the right length
+    InvalidAuthKey(String),
+    /// The saved radio authentication counter isn't a number
+    InvalidAuthCounter(String),
+    /// The Control Message validation limits can't be applied
+    InvalidValidationLimits(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(err) => write!(f, "unable to read configuration: {}", err),
+            ConfigError::Parse(err) => write!(f, "unable to parse configuration: {}", err),
+            ConfigError::DuplicateRobot(id) => write!(f, "robot {} appears more than once in the robot table", id),
+            ConfigError::UnrepresentableRobotId(id) => write!(f, "robot id {} can't be represented in a control message", id),
+            ConfigError::MissingRobot(id) => write!(f, "robot {} is in play but has no entry in the robot table", id),
+            ConfigError::TooManyRobots { requested, max } => write!(
+                f,
+                "{} robots were requested but at most {} are supported (the radio protocol can only address {} robots, whatever the alive robots encoding)",
+                requested,
+                max,
+                max_protocol_robots(),
+            ),
+            ConfigError::InvalidChannel(channel) => write!(f, "radio channel {} is outside of the range 0-125", channel),
+            ConfigError::InvalidFaultScenario(reason) => write!(f, "invalid fault scenario: {}", reason),
+            ConfigError::InvalidAuthKey(reason) => write!(f, "invalid radio authentication key: {}", reason),
+            ConfigError::InvalidAuthCounter(reason) => write!(f, "invalid radio authentication counter: {}", reason),
+            ConfigError::InvalidValidationLimits(reason) => write!(f, "invalid control message limits: {}", reason),
+        }
+    }
+}
+
+impl Error for ConfigError {}
+
+impl From<std::io::Error> for ConfigError {
+    fn from(err: std::io::Error) -> Self {
+        ConfigError::Io(err)
+    }
+}
+
+impl From<toml::de::Error> for ConfigError {
+    fn from(err: toml::de::Error) -> Self {
+        ConfigError::Parse(err)
+    }
+}
+
+fn enabled_default() -> bool { true }
+
+/// A single robot in the robot table
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+pub struct RobotConfig {
+    pub id: u8,
+    pub radio_address: [u8; 5],
+    #[serde(default = "enabled_default")]
+    pub enabled: bool,
+}
+
+/// The table of robots the base station can communicate with
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+pub struct RobotTable {
+    pub robots: Vec<RobotConfig>,
+}
+
+impl RobotTable {
+    /// Build the robot table from the compile-time `ROBOT_RADIO_ADDRESSES`
+    pub fn from_default_addresses() -> Self {
+        Self {
+            robots: ROBOT_RADIO_ADDRESSES.iter()
+                .enumerate()
+                .map(|(id, radio_address)| RobotConfig {
+                    id: id as u8,
+                    radio_address: *radio_address,
+                    enabled: true,
+                })
+                .collect(),
+        }
+    }
+
+    /// Load the robot table from a TOML file
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, ConfigError> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    /// Parse the robot table from a TOML string
+    pub fn parse(config: &str) -> Result<Self, ConfigError> {
+        let table: RobotTable = toml::from_str(config)?;
+        table.validate()?;
+        Ok(table)
+    }
+
+    /// Check that each robot id is unique and can be represented in a Control Message
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let mut ids = HashSet::new();
+        for robot in self.robots.iter() {
+            if !ids.insert(robot.id) {
+                return Err(ConfigError::DuplicateRobot(robot.id));
+            }
+            if !robot_id_representable(robot.id) {
+                return Err(ConfigError::UnrepresentableRobotId(robot.id));
+            }
+        }
+        Ok(())
+    }
+
+    /// Check that every robot in play (0..num_robots) has an entry in the table
+    pub fn check_robots_in_play(&self, num_robots: u8) -> Result<(), ConfigError> {
+        for robot_id in 0..num_robots {
+            if self.get(robot_id).is_none() {
+                return Err(ConfigError::MissingRobot(robot_id));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn get(&self, robot_id: u8) -> Option<&RobotConfig> {
+        self.robots.iter().find(|robot| robot.id == robot_id)
+    }
+
+    /// Enable or disable a robot, returning false if the robot isn't in the table
+    pub fn set_enabled(&mut self, robot_id: u8, enabled: bool) -> bool {
+        match self.robots.iter_mut().find(|robot| robot.id == robot_id) {
+            Some(robot) => {
+                robot.enabled = enabled;
+                true
+            },
+            None => false,
+        }
+    }
+
+    pub fn is_enabled(&self, robot_id: u8) -> bool {
+        self.get(robot_id).map(|robot| robot.enabled).unwrap_or(false)
+    }
+
+    /// The radio address of a robot (if the robot exists and is enabled)
+    pub fn radio_address(&self, robot_id: u8) -> Option<[u8; 5]> {
+        self.get(robot_id)
+            .filter(|robot| robot.enabled)
+            .map(|robot| robot.radio_address)
+    }
+
+    /// The ids of every enabled robot
+    pub fn enabled_ids(&self) -> HashSet<u8> {
+        self.robots.iter()
+            .filter(|robot| robot.enabled)
+            .map(|robot| robot.id)
+            .collect()
+    }
+}
+
+/// The radio addresses of one team's base station and robots at a site.
+///
+/// Site 0 of the compile-time team uses the addresses from `robojackets_robocup_rtp` (so
+/// robots flashed with the defaults keep working).  Every other team and site uses
+/// addresses derived from the team and site id:
+///
+/// base station: [0xB5, 0x6D, 0xB0 | team, site id, 0xE7]
+/// robot n:      [0xB5, 0x6D, 0xB0 | team, site id, 0xC0 + n]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AddressSet {
+    pub team: Team,
+    pub site_id: u8,
+}
+
+impl AddressSet {
+    pub fn new(team: Team, site_id: u8) -> Self {
+        Self {
+            team,
+            site_id,
+        }
+    }
+
+    /// Whether the set is the compile-time addresses
+    pub fn is_default(&self) -> bool {
+        self.team == TEAM && self.site_id == 0
+    }
+
+    fn derived_address(&self, last_byte: u8) -> [u8; 5] {
+        [0xB5, 0x6D, 0xB0 | self.team as u8, self.site_id, last_byte]
+    }
+
+    /// The address the base station listens on for Robot Status Messages
+    pub fn base_station_address(&self) -> [u8; 5] {
+        if self.is_default() {
+            BASE_STATION_ADDRESS
+        } else {
+            self.derived_address(0xE7)
+        }
+    }
+
+    /// The robot table of the site (with as many robots as `ROBOT_RADIO_ADDRESSES`)
+    pub fn robot_table(&self) -> RobotTable {
+        if self.is_default() {
+            return RobotTable::from_default_addresses();
+        }
+
+        RobotTable {
+            robots: (0..ROBOT_RADIO_ADDRESSES.len() as u8)
+                .map(|id| RobotConfig {
+                    id,
+                    radio_address: self.derived_address(0xC0 + id),
+                    enabled: true,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Default for AddressSet {
+    fn default() -> Self {
+        Self::new(TEAM, 0)
+    }
+}
+
+/// The highest channel supported by the nRF24L01+ (f = 2400 + CHANNEL (MHz))
+pub const MAX_CHANNEL: u8 = 125;
+
+/// The configuration applied to the radio when it is initialized
+#[derive(Clone, Copy, Debug)]
+pub struct RadioConfig {
+    pub channel: u8,
+    pub pa_level: PowerAmplifier,
+    /// The address the base station listens on for Robot Status Messages
+    pub base_station_address: [u8; 5],
+}
+
+impl Default for RadioConfig {
+    fn default() -> Self {
+        Self {
+            channel: CHANNEL,
+            pa_level: BASE_AMPLIFICATION_LEVEL,
+            base_station_address: BASE_STATION_ADDRESS,
+        }
+    }
+}
+
+impl RadioConfig {
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.channel > MAX_CHANNEL {
+            return Err(ConfigError::InvalidChannel(self.channel));
+        }
+        Ok(())
+    }
+}
+
+/// Parse a power amplifier level (min, low, high or max)
+pub fn parse_pa_level(pa_level: &str) -> Option<PowerAmplifier> {
+    match pa_level.to_lowercase().as_str() {
+        "min" => Some(PowerAmplifier::PAMin),
+        "low" => Some(PowerAmplifier::PALow),
+        "high" => Some(PowerAmplifier::PAHigh),
+        "max" => Some(PowerAmplifier::PAMax),
+        _ => None,
+    }
+}
+
+/// The name of a power amplifier level (as accepted by `parse_pa_level`)
+pub fn pa_level_name(pa_level: PowerAmplifier) -> &'static str {
+    match pa_level {
+        PowerAmplifier::PAMin => "min",
+        PowerAmplifier::PALow => "low",
+        PowerAmplifier::PAHigh => "high",
+        PowerAmplifier::PAMax => "max",
+    }
+}
+
+/// The team of a Control or Robot Status Message, which is a single bit on the air (false
+/// is blue and true is yellow, see rtp's `Into<bool> for Team`)
+pub fn team_from_bit(team: bool) -> Team {
+    if team {
+        Team::Yellow
+    } else {
+        Team::Blue
+    }
+}
+
+/// Whether a robot id survives being packed into (and unpacked from) a Control Message
+pub fn robot_id_representable(robot_id: u8) -> bool {
+    let control_message = ControlMessageBuilder::new()
+        .robot_id(robot_id)
+        .build();
+    match control_message.pack() {
+        Ok(bytes) => match ControlMessage::unpack(&bytes) {
+            Ok(unpacked) => *unpacked.robot_id == robot_id,
+            Err(_) => false,
+        },
+        Err(_) => false,
+    }
+}
+
+/// The maximum number of robots the radio protocol can address (rtp's robot id is 4 bits,
+/// so 16)
+pub fn max_protocol_robots() -> usize {
+    (0..=u8::MAX).take_while(|robot_id| robot_id_representable(*robot_id)).count()
+}
@@ -0,0 +1,264 @@
+//!
+//! Local Control API for the Base Station
+//!
+//! Clients connect over TCP (by default to 127.0.0.1:8004) and send one command per line.
+//! Every command is answered with a single line starting with either "ok" or "error".
+//!
+//! Commands:
+//! get - Get the current configuration
+//! set {key} {value} - Change the configuration (keys: channel, pa_level, robots, timeout,
+//!     heartbeat_timeout)
+//! robots - List every robot in the robot table
+//! enable {robot id} - Enable a robot in the robot table
+//! disable {robot id} - Disable a robot in the robot table
+//! reinit - Re-initialize the radio with the current configuration
+//! remap {logical id} {physical id} - Send commands for a logical robot to a physical robot
+//! unmap {logical id} - Send commands for a logical robot to the physical robot with the same id
+//! mappings - List every remapped robot
+//! latency [stage] - Get the p50/p95/p99 latency (in microseconds) of each robot for a stage
+//!     (queue, radio, response, round_trip or end_to_end (the default))
+//! latency export {file name} - Write every latency histogram to a CSV file in the
+//!     directory given by --latency-export-dir (disabled without it)
+//! latency reset - Clear every latency histogram
+//! link - Get the state of the link to the Field Computer (heartbeats and lost, stale and
+//!     duplicate sequenced commands)
+//!
+//! Changes are applied between radio cycles without restarting the base station.
+//!
+
+use std::io::{ErrorKind, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+
+use rtic_nrf24l01::config::power_amplifier::PowerAmplifier;
+
+use crate::config::{pa_level_name, parse_pa_level};
+use crate::latency::LatencyStage;
+
+/// The shortest robot timeout that can be set (milliseconds).  The Timeout Checker runs
+/// once per timeout, so shorter timeouts would have it spin.
+pub const MIN_TIMEOUT_MS: u128 = 10;
+/// The shortest heartbeat timeout that can be set (milliseconds)
+pub const MIN_HEARTBEAT_TIMEOUT_MS: u64 = 10;
+
+/// A configuration value that can be changed over the control API
+#[derive(Clone, Copy, Debug)]
+pub enum ConfigSetting {
+    Channel(u8),
+    PaLevel(PowerAmplifier),
+    Robots(u8),
+    Timeout(u128),
+    HeartbeatTimeout(u64),
+}
+
+impl ConfigSetting {
+    /// Parse a configuration key and value
+    pub fn parse(key: &str, value: &str) -> Result<Self, String> {
+        match key {
+            "channel" => Ok(ConfigSetting::Channel(parse_argument(value)?)),
+            "pa_level" => parse_pa_level(value)
+                .map(ConfigSetting::PaLevel)
+                .ok_or_else(|| format!("invalid pa level: {}", value)),
+            "robots" => Ok(ConfigSetting::Robots(parse_argument(value)?)),
+            "timeout" => match parse_argument(value)? {
+                timeout if timeout < MIN_TIMEOUT_MS => Err(format!("timeout must be at least {} ms", MIN_TIMEOUT_MS)),
+                timeout => Ok(ConfigSetting::Timeout(timeout)),
+            },
+            "heartbeat_timeout" => match parse_argument(value)? {
+                heartbeat_timeout if heartbeat_timeout < MIN_HEARTBEAT_TIMEOUT_MS => {
+                    Err(format!("heartbeat_timeout must be at least {} ms", MIN_HEARTBEAT_TIMEOUT_MS))
+                },
+                heartbeat_timeout => Ok(ConfigSetting::HeartbeatTimeout(heartbeat_timeout)),
+            },
+            _ => Err(format!("unknown configuration key: {}", key)),
+        }
+    }
+}
+
+// PowerAmplifier can't be compared, so levels are compared by name
+impl PartialEq for ConfigSetting {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (ConfigSetting::Channel(channel), ConfigSetting::Channel(other)) => channel == other,
+            (ConfigSetting::PaLevel(pa_level), ConfigSetting::PaLevel(other)) => pa_level_name(*pa_level) == pa_level_name(*other),
+            (ConfigSetting::Robots(robots), ConfigSetting::Robots(other)) => robots == other,
+            (ConfigSetting::Timeout(timeout), ConfigSetting::Timeout(other)) => timeout == other,
+            (ConfigSetting::HeartbeatTimeout(timeout), ConfigSetting::HeartbeatTimeout(other)) => timeout == other,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for ConfigSetting {}
+
+/// A command received over the control API
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ControlCommand {
+    Get,
+    Set(ConfigSetting),
+    Robots,
+    Enable { robot_id: u8 },
+    Disable { robot_id: u8 },
+    Reinit,
+    Remap { logical_id: u8, physical_id: u8 },
+    Unmap { logical_id: u8 },
+    Mappings,
+    Link,
+    Latency { stage: LatencyStage },
+    LatencyExport { path: String },
+    LatencyReset,
+}
+
+impl ControlCommand {
+    /// Parse a single line of the control protocol
+    pub fn parse(line: &str) -> Result<Self, String> {
+        let arguments: Vec<&str> = line.split_whitespace().collect();
+        match arguments.as_slice() {
+            ["get"] => Ok(ControlCommand::Get),
+            ["set", key, value] => Ok(ControlCommand::Set(ConfigSetting::parse(key, value)?)),
+            ["robots"] => Ok(ControlCommand::Robots),
+            ["enable", robot_id] => Ok(ControlCommand::Enable {
+                robot_id: parse_argument(robot_id)?,
+            }),
+            ["disable", robot_id] => Ok(ControlCommand::Disable {
+                robot_id: parse_argument(robot_id)?,
+            }),
+            ["reinit"] => Ok(ControlCommand::Reinit),
+            ["remap", logical_id, physical_id] => Ok(ControlCommand::Remap {
+                logical_id: parse_argument(logical_id)?,
+                physical_id: parse_argument(physical_id)?,
+            }),
+            ["unmap", logical_id] => Ok(ControlCommand::Unmap {
+                logical_id: parse_argument(logical_id)?,
+            }),
+            ["mappings"] => Ok(ControlCommand::Mappings),
+            ["link"] => Ok(ControlCommand::Link),
+            ["latency"] => Ok(ControlCommand::Latency { stage: LatencyStage::EndToEnd }),
+            ["latency", "export", path] => Ok(ControlCommand::LatencyExport { path: path.to_string() }),
+            ["latency", "reset"] => Ok(ControlCommand::LatencyReset),
+            ["latency", stage] => LatencyStage::parse(stage)
+                .map(|stage| ControlCommand::Latency { stage })
+                .ok_or_else(|| format!("unknown latency stage: {}", stage)),
+            [] => Err(String::from("empty command")),
+            _ => Err(format!("unknown command: {}", line.trim())),
+        }
+    }
+}
+
+fn parse_argument<T: std::str::FromStr>(argument: &str) -> Result<T, String> {
+    argument.parse().map_err(|_| format!("invalid argument: {}", argument))
+}
+
+/// A command from a connected client that is waiting on a response
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ControlRequest {
+    pub client: usize,
+    pub command: Result<ControlCommand, String>,
+}
+
+struct ControlClient {
+    stream: TcpStream,
+    buffer: Vec<u8>,
+    open: bool,
+}
+
+/// Non-blocking line-based TCP server for the control API
+pub struct ControlServer {
+    listener: TcpListener,
+    clients: Vec<Option<ControlClient>>,
+}
+
+impl ControlServer {
+    pub fn new(bind_address: &str) -> Self {
+        let listener = TcpListener::bind(bind_address)
+            .expect("Unable to bind the control API socket");
+        listener.set_nonblocking(true)
+            .expect("Unable to set the control API socket to non-blocking");
+
+        Self {
+            listener,
+            clients: Vec::new(),
+        }
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.listener.local_addr().expect("Unable to get the control API address")
+    }
+
+    /// Accept new clients and collect every complete command line that has been received
+    pub fn poll(&mut self) -> Vec<ControlRequest> {
+        // Clients that disconnected during the last poll have been sent their responses
+        for slot in self.clients.iter_mut() {
+            if slot.as_ref().is_some_and(|client| !client.open) {
+                *slot = None;
+            }
+        }
+
+        loop {
+            match self.listener.accept() {
+                Ok((stream, _)) => {
+                    if stream.set_nonblocking(true).is_err() {
+                        continue;
+                    }
+                    let client = ControlClient { stream, buffer: Vec::new(), open: true };
+                    match self.clients.iter().position(|client| client.is_none()) {
+                        Some(index) => self.clients[index] = Some(client),
+                        None => self.clients.push(Some(client)),
+                    }
+                },
+                Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                Err(err) => {
+                    println!("Unable to accept control API client: {:?}", err);
+                    break;
+                },
+            }
+        }
+
+        let mut requests = Vec::new();
+        for (index, slot) in self.clients.iter_mut().enumerate() {
+            let Some(client) = slot else { continue };
+            if !client.open {
+                continue;
+            }
+
+            let mut buffer = [0u8; 256];
+            loop {
+                match client.stream.read(&mut buffer) {
+                    Ok(0) => {
+                        client.open = false;
+                        break;
+                    },
+                    Ok(size) => client.buffer.extend_from_slice(&buffer[..size]),
+                    Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                    Err(_) => {
+                        client.open = false;
+                        break;
+                    },
+                }
+            }
+
+            while let Some(end) = client.buffer.iter().position(|byte| *byte == b'\n') {
+                let line: Vec<u8> = client.buffer.drain(..=end).collect();
+                let line = String::from_utf8_lossy(&line);
+                if line.trim().is_empty() {
+                    continue;
+                }
+                requests.push(ControlRequest {
+                    client: index,
+                    command: ControlCommand::parse(&line),
+                });
+            }
+        }
+        requests
+    }
+
+    /// Send a single line response to a client
+    pub fn respond(&mut self, client: usize, response: &str) {
+        let Some(Some(control_client)) = self.clients.get_mut(client) else { return };
+
+        let mut line = String::from(response);
+        line.push('\n');
+        if control_client.stream.write_all(line.as_bytes()).is_err() {
+            self.clients[client] = None;
+        }
+    }
+}
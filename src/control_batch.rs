@@ -0,0 +1,124 @@
+//!
+//! Batched and Sequenced Control Messages from the Field Computer
+//!
+//! Instead of one datagram per robot, the Field Computer can send every robot's Control
+//! Message for a frame in a single datagram:
+//!
+//! [magic: "RB" (2 bytes)][frame: u32 (big endian)][count: u8][count packed ControlMessages]
+//!
+//! Every Control Message in a batch is applied together in the same radio cycle.  Batches
+//! are never CONTROL_MESSAGE_SIZE bytes long so they can't be mistaken for a single
+//! Control Message.
+//!
+//! A single Control Message can also be wrapped in a sequenced envelope so stale and
+//! duplicate commands can be dropped:
+//!
+//! [magic: "RS" (2 bytes)][sequence: u32 (big endian)][packed ControlMessage]
+//!
+//! Sequence numbers are counted separately for each robot.
+//!
+
+use packed_struct::{PackedStruct, PackedStructSlice};
+
+use robojackets_robocup_rtp::control_message::{ControlMessage, CONTROL_MESSAGE_SIZE};
+
+/// The first two bytes of every batch
+pub const CONTROL_BATCH_MAGIC: [u8; 2] = *b"RB";
+
+/// The size of the batch header (magic, frame and count)
+pub const CONTROL_BATCH_HEADER_SIZE: usize = 7;
+
+/// The first two bytes of every sequenced Control Message
+pub const SEQUENCED_CONTROL_MAGIC: [u8; 2] = *b"RS";
+
+/// The size of a sequenced Control Message (magic, sequence and Control Message)
+pub const SEQUENCED_CONTROL_MESSAGE_SIZE: usize = 6 + CONTROL_MESSAGE_SIZE;
+
+/// The Control Messages for every robot in a single frame
+#[derive(Clone, Debug)]
+pub struct ControlBatch {
+    pub frame: u32,
+    pub control_messages: Vec<ControlMessage>,
+}
+
+impl ControlBatch {
+    pub fn new(frame: u32, control_messages: Vec<ControlMessage>) -> Self {
+        Self {
+            frame,
+            control_messages,
+        }
+    }
+
+    /// Whether a datagram is a batch (rather than a single Control Message)
+    pub fn is_batch(bytes: &[u8]) -> bool {
+        bytes.len() >= CONTROL_BATCH_HEADER_SIZE && bytes.len() != CONTROL_MESSAGE_SIZE && bytes[..2] == CONTROL_BATCH_MAGIC
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut buffer = Vec::with_capacity(CONTROL_BATCH_HEADER_SIZE + self.control_messages.len() * CONTROL_MESSAGE_SIZE);
+        buffer.extend_from_slice(&CONTROL_BATCH_MAGIC);
+        buffer.extend_from_slice(&self.frame.to_be_bytes());
+        buffer.push(self.control_messages.len() as u8);
+        for control_message in self.control_messages.iter() {
+            buffer.extend_from_slice(&control_message.pack().expect("Unable to pack Control Message"));
+        }
+        buffer
+    }
+
+    pub fn decode(bytes: &[u8]) -> Option<Self> {
+        if !Self::is_batch(bytes) {
+            return None;
+        }
+
+        let frame = u32::from_be_bytes(bytes[2..6].try_into().ok()?);
+        let count = bytes[6] as usize;
+        let control_messages = &bytes[CONTROL_BATCH_HEADER_SIZE..];
+        if control_messages.len() != count * CONTROL_MESSAGE_SIZE {
+            return None;
+        }
+
+        let control_messages = control_messages.chunks_exact(CONTROL_MESSAGE_SIZE)
+            .map(|control_message| ControlMessage::unpack_from_slice(control_message).ok())
+            .collect::<Option<Vec<_>>>()?;
+        Some(Self { frame, control_messages })
+    }
+}
+
+/// A Control Message with a sequence number
+#[derive(Clone, Copy, Debug)]
+pub struct SequencedControlMessage {
+    pub sequence: u32,
+    pub control_message: ControlMessage,
+}
+
+impl SequencedControlMessage {
+    pub fn new(sequence: u32, control_message: ControlMessage) -> Self {
+        Self {
+            sequence,
+            control_message,
+        }
+    }
+
+    /// Whether a datagram is a sequenced Control Message
+    pub fn is_sequenced(bytes: &[u8]) -> bool {
+        bytes.len() == SEQUENCED_CONTROL_MESSAGE_SIZE && bytes[..2] == SEQUENCED_CONTROL_MAGIC
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut buffer = Vec::with_capacity(SEQUENCED_CONTROL_MESSAGE_SIZE);
+        buffer.extend_from_slice(&SEQUENCED_CONTROL_MAGIC);
+        buffer.extend_from_slice(&self.sequence.to_be_bytes());
+        buffer.extend_from_slice(&self.control_message.pack().expect("Unable to pack Control Message"));
+        buffer
+    }
+
+    pub fn decode(bytes: &[u8]) -> Option<Self> {
+        if !Self::is_sequenced(bytes) {
+            return None;
+        }
+
+        let sequence = u32::from_be_bytes(bytes[2..6].try_into().ok()?);
+        let control_message = ControlMessage::unpack_from_slice(&bytes[6..]).ok()?;
+        Some(Self { sequence, control_message })
+    }
+}
@@ -0,0 +1,116 @@
+//!
+//! Field Computer Link Monitoring
+//!
+//! The Field Computer periodically sends a heartbeat datagram (any payload) to the
+//! heartbeat port and the base station echoes it straight back.  If no heartbeat is
+//! received within the timeout the link is considered lost and the base station holds
+//! every robot still until heartbeats resume.
+//!
+//! (field::any -> 0.0.0.0:8005) - Field Sends Heartbeats
+//! (0.0.0.0:8005 -> field::any) - We Echo Heartbeats
+//!
+
+use std::io::ErrorKind;
+use std::net::{SocketAddr, UdpSocket};
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::net_errors::RebindingSocket;
+use crate::publishers::datagram::bind_socket;
+
+/// A change in the state of the link to the Field Computer
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LinkEvent {
+    /// No heartbeat was received within the timeout
+    Lost { at: SystemTime },
+    /// Heartbeats were received again after the link was lost (or for the first time)
+    Restored { at: SystemTime, down_for: Option<Duration> },
+}
+
+/// Tracks heartbeats from the Field Computer to decide whether the link is up
+pub struct FieldLinkMonitor {
+    socket: RebindingSocket<UdpSocket>,
+    timeout: Duration,
+    last_heartbeat: Option<Instant>,
+    lost_at: Option<Instant>,
+    connected: bool,
+    heartbeats_received: u64,
+}
+
+impl FieldLinkMonitor {
+    pub fn new(bind_address: &str, timeout: Duration) -> Self {
+        let socket = RebindingSocket::new(bind_address, bind_socket, "Field link heartbeat error")
+            .expect("Unable to bind the field link heartbeat socket");
+
+        Self {
+            socket,
+            timeout,
+            last_heartbeat: None,
+            lost_at: None,
+            connected: false,
+            heartbeats_received: 0,
+        }
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.socket.bound()
+            .expect("The field link heartbeat socket isn't bound")
+            .local_addr()
+            .expect("Unable to get the field link heartbeat address")
+    }
+
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    /// Whether heartbeats are currently being received from the Field Computer
+    pub fn is_connected(&self) -> bool {
+        self.connected
+    }
+
+    pub fn heartbeats_received(&self) -> u64 {
+        self.heartbeats_received
+    }
+
+    /// Echo any pending heartbeats and report whether the state of the link changed
+    pub fn poll(&mut self) -> Option<LinkEvent> {
+        let mut buffer = [0u8; 64];
+        // While the socket is waiting to be bound again no heartbeats arrive, so the link
+        // times out as it would if the field computer went away
+        while let Some(socket) = self.socket.socket() {
+            match socket.recv_from(&mut buffer) {
+                Ok((size, field_computer)) => {
+                    self.last_heartbeat = Some(Instant::now());
+                    self.heartbeats_received += 1;
+                    if let Err(err) = socket.send_to(&buffer[..size], field_computer) {
+                        self.socket.record_error(&format!("unable to echo heartbeat to {}", field_computer), &err);
+                    }
+                },
+                Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                Err(err) => {
+                    self.socket.record_error("unable to receive heartbeat", &err);
+                    break;
+                },
+            }
+        }
+
+        let heartbeat_fresh = self.last_heartbeat
+            .map(|last_heartbeat| last_heartbeat.elapsed() < self.timeout)
+            .unwrap_or(false);
+
+        if heartbeat_fresh && !self.connected {
+            self.connected = true;
+            let down_for = self.lost_at.take().map(|lost_at| lost_at.elapsed());
+            Some(LinkEvent::Restored { at: SystemTime::now(), down_for })
+        } else if !heartbeat_fresh && self.connected {
+            self.connected = false;
+            self.lost_at = Some(Instant::now());
+            Some(LinkEvent::Lost { at: SystemTime::now() })
+        } else {
+            None
+        }
+    }
+}
@@ -0,0 +1,243 @@
+//!
+//! Human-Readable JSON Mirror of the Field Computer Streams
+//!
+//! Robot statuses, alive robots and field link events can optionally be mirrored as
+//! newline-delimited JSON (one object per line) for debugging with netcat, Python, ...
+//! The binary streams sent to the Field Computer are unchanged.
+//!
+//! Each line has a "type" ("robot_status", "alive_robots", "link_lost" or "link_restored")
+//! and a "timestamp_ms" (milliseconds since the UNIX epoch), for example:
+//!
+//! {"type":"robot_status","timestamp_ms":1700000000000,"team":"blue","robot_id":1,...}
+//!
+//! Over UDP each line is sent as its own datagram.  Over TCP every connected client
+//! receives every line.  Clients are written to without blocking: lines a slow client
+//! hasn't read yet are buffered and a client that falls more than `MAX_PENDING_BYTES`
+//! behind is disconnected.
+//!
+
+use std::io::{self, ErrorKind, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::time::{Duration, SystemTime};
+
+use clap::ValueEnum;
+
+use serde::Serialize;
+
+use ncomm::publisher_subscriber::Publish;
+
+use robojackets_robocup_rtp::robot_status_message::RobotStatusMessage;
+
+use crate::alive_robots::AliveRobots;
+use crate::config::team_from_bit;
+use crate::field_link::LinkEvent;
+use crate::net_errors::RebindingSocket;
+use crate::publishers::datagram::{Datagram, DatagramPublisher};
+
+/// The transport the JSON mirror is sent over
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum JsonMirrorTransport {
+    /// Each line is sent as a datagram to the given addresses
+    Udp,
+    /// Each line is written to every client connected to the mirror port
+    Tcp,
+}
+
+/// A single line of the JSON mirror
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MirrorEvent {
+    RobotStatus {
+        timestamp_ms: u64,
+        team: String,
+        robot_id: u8,
+        ball_sense_status: bool,
+        kick_status: bool,
+        kick_healthy: bool,
+        fpga_status: bool,
+        battery_voltage: u8,
+        motor_errors: u8,
+    },
+    AliveRobots {
+        timestamp_ms: u64,
+        num_robots: u8,
+        alive: Vec<u8>,
+    },
+    LinkLost {
+        timestamp_ms: u64,
+    },
+    LinkRestored {
+        timestamp_ms: u64,
+        down_for_ms: Option<u64>,
+    },
+}
+
+fn timestamp_ms(time: SystemTime) -> u64 {
+    time.duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+impl MirrorEvent {
+    pub fn robot_status(status: &RobotStatusMessage, received_at: SystemTime) -> Self {
+        MirrorEvent::RobotStatus {
+            timestamp_ms: timestamp_ms(received_at),
+            team: format!("{:?}", team_from_bit(status.team)).to_lowercase(),
+            robot_id: *status.robot_id,
+            ball_sense_status: status.ball_sense_status,
+            kick_status: status.kick_status,
+            kick_healthy: status.kick_healthy,
+            fpga_status: status.fpga_status,
+            battery_voltage: *status.battery_voltage,
+            motor_errors: *status.motor_errors,
+        }
+    }
+
+    pub fn alive_robots(alive_robots: &AliveRobots, at: SystemTime) -> Self {
+        MirrorEvent::AliveRobots {
+            timestamp_ms: timestamp_ms(at),
+            num_robots: alive_robots.num_robots(),
+            alive: alive_robots.alive_ids(),
+        }
+    }
+
+    pub fn link_event(link_event: &LinkEvent) -> Self {
+        match link_event {
+            LinkEvent::Lost { at } => MirrorEvent::LinkLost { timestamp_ms: timestamp_ms(*at) },
+            LinkEvent::Restored { at, down_for } => MirrorEvent::LinkRestored {
+                timestamp_ms: timestamp_ms(*at),
+                down_for_ms: down_for.as_ref().map(Duration::as_millis).map(|down_for| down_for as u64),
+            },
+        }
+    }
+
+    /// The event as a single line of JSON (including the trailing newline)
+    pub fn to_json_line(&self) -> String {
+        let mut line = serde_json::to_string(self).expect("Unable to serialize mirror event");
+        line.push('\n');
+        line
+    }
+}
+
+impl Datagram for MirrorEvent {
+    fn to_datagram(&self) -> Vec<u8> {
+        self.to_json_line().into_bytes()
+    }
+}
+
+/// How many bytes a TCP client can fall behind before it is disconnected
+pub const MAX_PENDING_BYTES: usize = 64 * 1024;
+
+struct JsonClient {
+    stream: TcpStream,
+    pending: Vec<u8>,
+}
+
+impl JsonClient {
+    /// Write as much of the pending lines as the client will take without blocking,
+    /// returning false if the client has disconnected or fallen too far behind
+    fn flush(&mut self) -> bool {
+        while !self.pending.is_empty() {
+            match self.stream.write(&self.pending) {
+                Ok(0) => return false,
+                Ok(size) => {
+                    self.pending.drain(..size);
+                },
+                Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                Err(err) if err.kind() == ErrorKind::Interrupted => (),
+                Err(_) => return false,
+            }
+        }
+        self.pending.len() <= MAX_PENDING_BYTES
+    }
+}
+
+/// Bind a non-blocking TCP listener
+fn bind_listener(bind_address: &str) -> io::Result<TcpListener> {
+    let listener = TcpListener::bind(bind_address)?;
+    listener.set_nonblocking(true)?;
+    Ok(listener)
+}
+
+/// Non-blocking TCP server that writes every line to each connected client
+pub struct JsonStreamServer {
+    listener: RebindingSocket<TcpListener>,
+    clients: Vec<JsonClient>,
+}
+
+impl JsonStreamServer {
+    pub fn new(bind_address: &str) -> Self {
+        let listener = RebindingSocket::new(bind_address, bind_listener, "JSON mirror error")
+            .expect("Unable to bind the JSON mirror socket");
+
+        Self {
+            listener,
+            clients: Vec::new(),
+        }
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.listener.bound()
+            .expect("The JSON mirror socket isn't bound")
+            .local_addr()
+            .expect("Unable to get the JSON mirror address")
+    }
+
+    /// Accept any clients waiting to connect
+    pub fn accept_clients(&mut self) {
+        while let Some(listener) = self.listener.socket() {
+            match listener.accept() {
+                Ok((stream, _)) => {
+                    // Accepted streams don't inherit non-blocking mode from the listener
+                    if stream.set_nonblocking(true).is_err() {
+                        continue;
+                    }
+                    self.clients.push(JsonClient { stream, pending: Vec::new() });
+                },
+                Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                Err(err) => {
+                    self.listener.record_error("unable to accept client", &err);
+                    break;
+                },
+            }
+        }
+    }
+
+    pub fn num_clients(&self) -> usize {
+        self.clients.len()
+    }
+
+    /// Write a line to every client, dropping any client that has disconnected or stalled
+    pub fn write_line(&mut self, line: &str) {
+        self.accept_clients();
+        self.clients.retain_mut(|client| {
+            client.pending.extend_from_slice(line.as_bytes());
+            client.flush()
+        });
+    }
+}
+
+/// Publishes mirror events over either UDP or TCP
+pub enum JsonMirror<'a> {
+    Udp(Box<DatagramPublisher<'a>>),
+    Tcp(JsonStreamServer),
+}
+
+impl<'a> JsonMirror<'a> {
+    pub fn udp(bind_address: &'a str, addresses: Vec<&'a str>) -> Self {
+        JsonMirror::Udp(Box::new(DatagramPublisher::new(bind_address, addresses)))
+    }
+
+    pub fn tcp(bind_address: &str) -> Self {
+        JsonMirror::Tcp(JsonStreamServer::new(bind_address))
+    }
+}
+
+impl<'a> Publish<MirrorEvent> for JsonMirror<'a> {
+    fn send(&mut self, data: MirrorEvent) {
+        match self {
+            JsonMirror::Udp(publisher) => publisher.send(data),
+            JsonMirror::Tcp(server) => server.write_line(&data.to_json_line()),
+        }
+    }
+}
@@ -0,0 +1,305 @@
+//!
+//! End-to-End Latency Instrumentation
+//!
+//! Each command is timestamped when it is received from the Field Computer (UDP receive),
+//! when the radio starts transmitting it, when the robot acknowledges it and when the
+//! robot's status is received.  The time between these timestamps is recorded in a
+//! histogram per robot and stage:
+//!
+//! queue: UDP receive -> radio transmit start (first transmission of a command only)
+//! radio: radio transmit start -> ack (stamped by the radio link when the ack arrives, the
+//!     nRF24L01+ driver polls STATUS for it so this includes up to one polling interval)
+//! response: ack -> status receive
+//! round_trip: radio transmit start -> status receive
+//! end_to_end: UDP receive -> status receive (first transmission of a command only)
+//!
+
+use std::collections::BTreeMap;
+use std::fmt::Write as FmtWrite;
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// The width of each histogram bucket
+pub const DEFAULT_BUCKET_WIDTH: Duration = Duration::from_micros(50);
+
+/// The number of histogram buckets (latencies longer than the last bucket are counted as
+/// overflow)
+pub const DEFAULT_BUCKETS: usize = 400;
+
+/// A stage of a command's journey from the Field Computer to a robot and back
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LatencyStage {
+    Queue,
+    Radio,
+    Response,
+    RoundTrip,
+    EndToEnd,
+}
+
+impl LatencyStage {
+    pub const ALL: [LatencyStage; 5] = [
+        LatencyStage::Queue,
+        LatencyStage::Radio,
+        LatencyStage::Response,
+        LatencyStage::RoundTrip,
+        LatencyStage::EndToEnd,
+    ];
+
+    pub fn parse(name: &str) -> Option<Self> {
+        LatencyStage::ALL.into_iter().find(|stage| stage.name() == name)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            LatencyStage::Queue => "queue",
+            LatencyStage::Radio => "radio",
+            LatencyStage::Response => "response",
+            LatencyStage::RoundTrip => "round_trip",
+            LatencyStage::EndToEnd => "end_to_end",
+        }
+    }
+}
+
+/// Fixed width latency histogram
+#[derive(Clone, Debug)]
+pub struct LatencyHistogram {
+    bucket_width: Duration,
+    buckets: Vec<u64>,
+    overflow: u64,
+    count: u64,
+    total: Duration,
+    max: Duration,
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        Self::new(DEFAULT_BUCKET_WIDTH, DEFAULT_BUCKETS)
+    }
+}
+
+impl LatencyHistogram {
+    pub fn new(bucket_width: Duration, buckets: usize) -> Self {
+        Self {
+            bucket_width,
+            buckets: vec![0; buckets],
+            overflow: 0,
+            count: 0,
+            total: Duration::ZERO,
+            max: Duration::ZERO,
+        }
+    }
+
+    pub fn record(&mut self, latency: Duration) {
+        let bucket = (latency.as_nanos() / self.bucket_width.as_nanos().max(1)) as usize;
+        match self.buckets.get_mut(bucket) {
+            Some(count) => *count += 1,
+            None => self.overflow += 1,
+        }
+        self.count += 1;
+        self.total += latency;
+        self.max = self.max.max(latency);
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    pub fn max(&self) -> Duration {
+        self.max
+    }
+
+    pub fn mean(&self) -> Option<Duration> {
+        if self.count == 0 {
+            return None;
+        }
+        Some(self.total / self.count as u32)
+    }
+
+    /// The latency below which `percentile` percent of the samples fall (rounded up to the
+    /// end of a bucket)
+    pub fn percentile(&self, percentile: f64) -> Option<Duration> {
+        if self.count == 0 {
+            return None;
+        }
+
+        let target = ((percentile / 100.0) * self.count as f64).ceil().max(1.0) as u64;
+        let mut seen = 0;
+        for (bucket, count) in self.buckets.iter().enumerate() {
+            seen += count;
+            if seen >= target {
+                return Some(self.bucket_width * (bucket as u32 + 1));
+            }
+        }
+        Some(self.max)
+    }
+
+    /// The count of each (non-empty) bucket as (bucket start, bucket end, count)
+    pub fn buckets(&self) -> Vec<(Duration, Duration, u64)> {
+        self.buckets.iter()
+            .enumerate()
+            .filter(|(_, count)| **count > 0)
+            .map(|(bucket, count)| (
+                self.bucket_width * bucket as u32,
+                self.bucket_width * (bucket as u32 + 1),
+                *count,
+            ))
+            .collect()
+    }
+
+    pub fn overflow(&self) -> u64 {
+        self.overflow
+    }
+}
+
+/// The timestamps of the command currently being sent to a robot
+#[derive(Clone, Copy, Debug, Default)]
+struct InFlightCommand {
+    received_at: Option<Instant>,
+    transmit_at: Option<Instant>,
+    ack_at: Option<Instant>,
+}
+
+#[derive(Clone, Debug, Default)]
+struct RobotLatency {
+    histograms: BTreeMap<LatencyStage, LatencyHistogram>,
+    in_flight: InFlightCommand,
+    last_received_at: Option<Instant>,
+}
+
+impl RobotLatency {
+    fn record(&mut self, stage: LatencyStage, latency: Duration) {
+        self.histograms.entry(stage).or_default().record(latency);
+    }
+}
+
+/// Latency histograms for every robot
+#[derive(Clone, Debug, Default)]
+pub struct LatencyTracker {
+    robots: BTreeMap<u8, RobotLatency>,
+}
+
+impl LatencyTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The radio started transmitting a command (received from the Field Computer at
+    /// `received_at`, or None for commands generated by the base station)
+    pub fn transmit_started(&mut self, robot_id: u8, received_at: Option<Instant>, at: Instant) {
+        let robot = self.robots.entry(robot_id).or_default();
+
+        // Commands are re-sent every cycle so only the first transmission measures the queue
+        let received_at = received_at.filter(|received_at| robot.last_received_at != Some(*received_at));
+        if let Some(received_at) = received_at {
+            robot.last_received_at = Some(received_at);
+            robot.record(LatencyStage::Queue, at.saturating_duration_since(received_at));
+        }
+
+        robot.in_flight = InFlightCommand {
+            received_at,
+            transmit_at: Some(at),
+            ack_at: None,
+        };
+    }
+
+    /// The robot acknowledged the command
+    pub fn acked(&mut self, robot_id: u8, at: Instant) {
+        let Some(robot) = self.robots.get_mut(&robot_id) else { return };
+        let Some(transmit_at) = robot.in_flight.transmit_at else { return };
+
+        robot.in_flight.ack_at = Some(at);
+        robot.record(LatencyStage::Radio, at.saturating_duration_since(transmit_at));
+    }
+
+    /// A status was received from the robot
+    pub fn status_received(&mut self, robot_id: u8, at: Instant) {
+        let Some(robot) = self.robots.get_mut(&robot_id) else { return };
+        let in_flight = std::mem::take(&mut robot.in_flight);
+
+        if let Some(ack_at) = in_flight.ack_at {
+            robot.record(LatencyStage::Response, at.saturating_duration_since(ack_at));
+        }
+        if let Some(transmit_at) = in_flight.transmit_at {
+            robot.record(LatencyStage::RoundTrip, at.saturating_duration_since(transmit_at));
+        }
+        if let Some(received_at) = in_flight.received_at {
+            robot.record(LatencyStage::EndToEnd, at.saturating_duration_since(received_at));
+        }
+    }
+
+    pub fn histogram(&self, robot_id: u8, stage: LatencyStage) -> Option<&LatencyHistogram> {
+        self.robots.get(&robot_id)?.histograms.get(&stage)
+    }
+
+    /// The robots with at least one latency sample
+    pub fn robot_ids(&self) -> Vec<u8> {
+        self.robots.keys().copied().collect()
+    }
+
+    pub fn clear(&mut self) {
+        self.robots.clear();
+    }
+
+    /// One line summary of the p50/p95/p99 latency (in microseconds) of a stage for every robot
+    pub fn summary(&self, stage: LatencyStage) -> String {
+        let mut summary = String::new();
+        for robot_id in self.robot_ids() {
+            let Some(histogram) = self.histogram(robot_id, stage) else { continue };
+            let (Some(p50), Some(p95), Some(p99)) = (histogram.percentile(50.0), histogram.percentile(95.0), histogram.percentile(99.0)) else { continue };
+            if !summary.is_empty() {
+                summary.push(' ');
+            }
+            let _ = write!(
+                summary,
+                "{}:n={},p50={},p95={},p99={}",
+                robot_id,
+                histogram.count(),
+                p50.as_micros(),
+                p95.as_micros(),
+                p99.as_micros(),
+            );
+        }
+        summary
+    }
+
+    /// Every histogram as CSV (latencies in microseconds)
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("robot_id,stage,count,mean_us,p50_us,p95_us,p99_us,max_us,overflow\n");
+        for (robot_id, robot) in self.robots.iter() {
+            for (stage, histogram) in robot.histograms.iter() {
+                let micros = |latency: Option<Duration>| latency.map(|latency| latency.as_micros().to_string()).unwrap_or_default();
+                let _ = writeln!(
+                    csv,
+                    "{},{},{},{},{},{},{},{},{}",
+                    robot_id,
+                    stage.name(),
+                    histogram.count(),
+                    micros(histogram.mean()),
+                    micros(histogram.percentile(50.0)),
+                    micros(histogram.percentile(95.0)),
+                    micros(histogram.percentile(99.0)),
+                    histogram.max().as_micros(),
+                    histogram.overflow(),
+                );
+            }
+        }
+        csv
+    }
+
+    /// Write every histogram to a CSV file
+    pub fn export_csv<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, self.to_csv())
+    }
+}
+
+/// The path of a latency export requested over the control API.  Exports are only
+/// written to `export_dir` and `file_name` has to be a plain file name (no directories).
+pub fn export_path(export_dir: &Path, file_name: &str) -> Result<PathBuf, String> {
+    let mut components = Path::new(file_name).components();
+    match (components.next(), components.next()) {
+        (Some(Component::Normal(name)), None) => Ok(export_dir.join(name)),
+        _ => Err(format!("{} is not a file name", file_name)),
+    }
+}
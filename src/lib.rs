@@ -2,12 +2,72 @@ use rtic_nrf24l01::config::power_amplifier::PowerAmplifier;
 
 pub mod timeout_checker;
 
+// Base Station Configuration
+pub mod config;
+
+// Alive Robots Encoding
+pub mod alive_robots;
+
 // All Functionality Involving 1 Radio Communication
 pub mod one_radio;
 
 // Radio Publishers
 pub mod publishers;
 
+// Latest Robot Status Cache
+pub mod status_cache;
+
+// Control Message Validation
+pub mod validation;
+
+// Logical to Physical Robot Id Mapping
+pub mod robot_mapping;
+
+// Local Control API
+pub mod control_api;
+
+// Field Computer Link Monitoring
+pub mod field_link;
+
+// JSON Mirror of the Field Computer Streams
+pub mod json_mirror;
+
+// Hostname, IPv4 and IPv6 Addresses
+pub mod net_address;
+
+// Rate-Limited Network Error Logging
+pub mod net_errors;
+
+// Authenticated Control Messages
+pub mod radio_auth;
+
+// Batched Control Messages from the Field Computer
+pub mod control_batch;
+
+// End-to-End Latency Instrumentation
+pub mod latency;
+
+// Simulated Robots (for the Virtual Radio)
+pub mod robot_sim;
+
+// nRF24L01+ Register Map
+pub mod nrf_registers;
+
+// Emulated nRF24L01+ (for SPI Level Tests)
+pub mod nrf_emulator;
+
+// Radio Benchmark Matrix
+pub mod radio_bench;
+
+// Radio Configuration Verification
+pub mod radio_verify;
+
+// Actors (State Owned by a Dedicated Thread)
+pub mod actor;
+
+// Running Nodes Until Ctrl-C (and Shutting Them Down)
+pub mod node_executor;
+
 // PIN DEFINITIONS
 /// Chip Select (CSN) for the Radio
 pub const RADIO_CSN: u8 = 8;
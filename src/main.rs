@@ -7,21 +7,56 @@
 //! We will also be using 2 sx127 radios.
 //! 
 //! Communication with the Field Computer is as follows:
-//! (field::8000 -> 0.0.0.0:8000) - Field Sends Control Commands
+//! (field::8000 -> 0.0.0.0:8000) - Field Sends Control Commands (one per robot or a batch per frame)
 //! (0.0.0.0:8001 -> field::8001) - We Send Robot Statuses
 //! (0.0.0.0:8002 -> field::8002) - We Send Alive Robots
+//! (any -> 0.0.0.0:8003) - Clients Request a Snapshot of the Latest Robot Statuses
+//! (local -> 127.0.0.1:8004) - Local Control API (TCP)
+//! (field::any <-> 0.0.0.0:8005) - Field Sends Heartbeats and We Echo Them
+//! (0.0.0.0:8006 -> debug::8006) - Optional JSON Mirror of Robot Statuses, Alive Robots and Link Events
+//!
+//! Robot statuses and alive robots can also be sent to additional destinations (including
+//! multicast groups) with --robot-status-address and --alive-robots-address.
+//!
+//! The field computer can be given as a hostname (re-resolved periodically), an IPv4 address
+//! or an IPv6 address.  Every socket binds to --bind-address (0.0.0.0 by default, use :: for
+//! IPv6) except the control API which only listens on 127.0.0.1.
+//!
+//! With --dual-team the base station also serves our other team (for scrimmages between
+//! our own robots) on the same ports offset by --other-team-port-offset (100 by default).
 //! 
 
-use std::{error::Error, sync::mpsc, thread::{self, spawn}, time::Duration};
+use std::{error::Error, net::Ipv4Addr, path::PathBuf, sync::mpsc, thread, time::Duration};
 
 use ncomm::node::Node;
 
+use robocup_base_station::alive_robots::AliveRobotsEncoding;
+use robocup_base_station::config::{max_protocol_robots, parse_pa_level, AddressSet, ConfigError, RadioConfig, RobotTable};
+use robocup_base_station::field_link::FieldLinkMonitor;
+use robocup_base_station::json_mirror::{JsonMirror, JsonMirrorTransport};
+use robocup_base_station::net_address::socket_address;
+use robocup_base_station::node_executor::run_nodes;
 use robocup_base_station::one_radio::radio_node::RadioNode;
-use robocup_base_station::timeout_checker::TimeoutCheckerNode;
+use robocup_base_station::one_radio::scrimmage_node::ScrimmageNode;
+use robocup_base_station::publishers::datagram::MulticastOptions;
+use robocup_base_station::publishers::faulty_link::{FaultScenario, FaultyLink};
+use robocup_base_station::publishers::nrf_pubsub::NrfPublisherSubscriber;
+use robocup_base_station::publishers::radio_irq::GpioIrq;
+use robocup_base_station::publishers::radio_link::RadioLink;
+use robocup_base_station::publishers::radio_thread::RadioThread;
+use robocup_base_station::publishers::virtual_radio::{VirtualLink, VirtualRadio};
+use robocup_base_station::radio_auth::{AuthKey, ControlSigner};
+use robocup_base_station::radio_verify::verify_radio;
+use robocup_base_station::timeout_checker::{TimeoutCheckerNode, TimeoutSettings};
+use robocup_base_station::validation::{ValidationLimits, DEFAULT_MAX_ANGULAR_VELOCITY, DEFAULT_MAX_LINEAR_VELOCITY};
+use robocup_base_station::RADIO_IRQ;
 
 use rppal::{spi::{Spi, Bus, SlaveSelect, Mode}, gpio::Gpio, hal::Delay};
 
-use robojackets_robocup_rtp::TEAM;
+use robojackets_robocup_rtp::control_message::VELOCITY_SCALE_FACTOR;
+use robojackets_robocup_rtp::{Team, TEAM};
+
+use rtic_nrf24l01::Radio;
 
 use clap::Parser;
 
@@ -29,10 +64,18 @@ use clap::Parser;
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 struct Args {
-    // The IPv4 Address of the Field Computer
+    // The Hostname, IPv4 or IPv6 Address of the Field Computer
     #[arg(default_value_t = String::from("10.42.0.1"))]
     pub field_computer_address: String,
 
+    // The address every socket is bound to (e.g. 0.0.0.0, :: or the address of a single interface)
+    #[arg(long, default_value_t = String::from("0.0.0.0"))]
+    pub bind_address: String,
+
+    // The network interface robot statuses and alive robots are sent over (e.g. eth0, linux only)
+    #[arg(long)]
+    pub bind_interface: Option<String>,
+
     // Control Message Port
     #[arg(default_value_t = 8000)]
     pub control_message_port: u16,
@@ -45,10 +88,89 @@ struct Args {
     #[arg(default_value_t = 8002)]
     pub alive_robots_port: u16,
 
+    // The addresses robot statuses are sent to (defaults to the field computer), which
+    // can be given multiple times and can include multicast groups
+    #[arg(long)]
+    pub robot_status_address: Vec<String>,
+
+    // The addresses alive robots are sent to (defaults to the field computer), which
+    // can be given multiple times and can include multicast groups
+    #[arg(long)]
+    pub alive_robots_address: Vec<String>,
+
+    // The time to live of datagrams sent to multicast groups
+    #[arg(long, default_value_t = 1)]
+    pub multicast_ttl: u32,
+
+    // The IPv4 address of the interface to send multicast datagrams from
+    #[arg(long)]
+    pub multicast_interface: Option<Ipv4Addr>,
+
+    // Robot Status Snapshot Port
+    #[arg(long, default_value_t = 8003)]
+    pub status_snapshot_port: u16,
+
+    // Local Control API Port (TCP)
+    #[arg(long, default_value_t = 8004)]
+    pub control_api_port: u16,
+
+    // Field Computer Heartbeat Port
+    #[arg(long, default_value_t = 8005)]
+    pub heartbeat_port: u16,
+
+    // The length in milliseconds without a heartbeat before the field computer is considered
+    // disconnected and every robot is held still (heartbeats are ignored when not given)
+    #[arg(long)]
+    pub heartbeat_timeout: Option<u64>,
+
+    // Mirror robot statuses, alive robots and link events as newline-delimited JSON
+    // (not mirrored when not given)
+    #[arg(long, value_enum)]
+    pub json_mirror: Option<JsonMirrorTransport>,
+
+    // JSON Mirror Port
+    #[arg(long, default_value_t = 8006)]
+    pub json_mirror_port: u16,
+
+    // The addresses the JSON mirror is sent to over UDP (defaults to the field computer)
+    #[arg(long)]
+    pub json_mirror_address: Vec<String>,
+
+    // Follow each robot status with the sequence number of the last sequenced command sent
+    // to the robot.  This changes the robot status datagram (4 more bytes), so every
+    // consumer of the robot status stream has to expect it.
+    #[arg(long, default_value_t = false)]
+    pub echo_sequence: bool,
+
+    // Write per-robot latency histograms to this CSV file on shutdown
+    #[arg(long)]
+    pub latency_csv: Option<String>,
+
+    // The directory `latency export` over the control API writes to (the command is
+    // disabled when not given)
+    #[arg(long)]
+    pub latency_export_dir: Option<PathBuf>,
+
+    // The radio channel (f = 2400 + CHANNEL (MHz))
+    #[arg(long, default_value_t = robocup_base_station::CHANNEL)]
+    pub channel: u8,
+
+    // The radio power amplifier level (min, low, high or max)
+    #[arg(long, default_value_t = String::from("low"))]
+    pub pa_level: String,
+
     // The number of robots in play (most likely either 6 or 11)
     #[arg(short, long, default_value_t = 6)]
     pub robots: u8,
 
+    // Path to a TOML robot table (defaults to the robot radio addresses of the site)
+    #[arg(long)]
+    pub robot_config: Option<String>,
+
+    // The encoding used to send alive robots to the field computer
+    #[arg(long, value_enum, default_value_t = AliveRobotsEncoding::Legacy)]
+    pub alive_robots_encoding: AliveRobotsEncoding,
+
     // The maximum timeout between sends to the robot
     #[arg(default_value_t = 5)]
     pub send_timeout_ms: u128,
@@ -57,76 +179,412 @@ struct Args {
     #[arg(short, long, default_value_t = 500)]
     pub timeout: u128,
 
+    // Reject Control Messages with a linear velocity above this (m/s)
+    #[arg(long, default_value_t = DEFAULT_MAX_LINEAR_VELOCITY / VELOCITY_SCALE_FACTOR)]
+    pub max_linear_velocity: f32,
+
+    // Reject Control Messages with an angular velocity above this (rad/s)
+    #[arg(long, default_value_t = DEFAULT_MAX_ANGULAR_VELOCITY / VELOCITY_SCALE_FACTOR)]
+    pub max_angular_velocity: f32,
+
+    // Clamp the dribbler speed of Control Messages to this range (defaults to the full
+    // range of the field)
+    #[arg(long, default_value_t = i8::MIN, allow_negative_numbers = true)]
+    pub min_dribbler_speed: i8,
+
+    #[arg(long, default_value_t = i8::MAX, allow_negative_numbers = true)]
+    pub max_dribbler_speed: i8,
+
+    // Clamp the kick strength of Control Messages to this (defaults to the full range of
+    // the field)
+    #[arg(long, default_value_t = u8::MAX)]
+    pub max_kick_strength: u8,
+
     // The number of radios used by the base-station to communicate with the robots
     #[arg(long, default_value_t = false)]
     pub two_radios: bool,
+
+    // Talk to simulated robots (see robot-sim) at this address (host:port or unix:/path)
+    // instead of the radio
+    #[arg(long)]
+    pub virtual_radio: Option<String>,
+
+    // The address the virtual radio binds to (host:port or unix:/path)
+    #[arg(long, default_value_t = String::from("127.0.0.1:9000"))]
+    pub virtual_radio_bind: String,
+
+    // Inject the faults of this scenario (TOML) into the virtual radio
+    #[arg(long, requires = "virtual_radio")]
+    pub fault_scenario: Option<String>,
+
+    // Poll the radio over SPI instead of waiting on its IRQ line
+    #[arg(long, default_value_t = false)]
+    pub poll_radio: bool,
+
+    // Authenticate Control Messages with the pre-shared key (hex) in this file
+    #[arg(long)]
+    pub auth_key_file: Option<String>,
+
+    // Where to save the high-water mark of the authentication counter (defaults to the key
+    // file with ".counter" appended)
+    #[arg(long)]
+    pub auth_counter_file: Option<String>,
+
+    // Refuse to start if the radio doesn't hold the intended configuration once it has
+    // been initialized (otherwise the differences are only logged)
+    #[arg(long, default_value_t = false)]
+    pub strict_radio_config: bool,
+
+    // The site whose radio addresses to use so base stations on the same field don't
+    // collide (0 is the compile-time addresses)
+    #[arg(long, default_value_t = 0)]
+    pub site_id: u8,
+
+    // Serve both of our teams (for scrimmages between our own robots).  The other team
+    // uses our ports offset by --other-team-port-offset.
+    #[arg(long, default_value_t = false)]
+    pub dual_team: bool,
+
+    // How far the other team's ports are from ours in dual-team mode
+    #[arg(long, default_value_t = 100, value_parser = clap::value_parser!(u16).range(1..))]
+    pub other_team_port_offset: u16,
+
+    // Path to a TOML robot table for the other team in dual-team mode (defaults to the
+    // other team's robot radio addresses of the site)
+    #[arg(long, requires = "dual_team")]
+    pub other_team_robot_config: Option<String>,
 }
 
-fn main() -> Result<(), Box<dyn Error>> {
-    let args = Args::parse();
+/// Offset the port of a host:port address (addresses without a port are left alone)
+fn offset_port(address: &str, port_offset: u16) -> Result<String, String> {
+    match address.rsplit_once(':').and_then(|(host, port)| Some((host, port.parse::<u16>().ok()?))) {
+        Some((host, port)) => Ok(format!("{}:{}", host, offset(port, port_offset)?)),
+        None => Ok(address.to_string()),
+    }
+}
 
-    if args.two_radios {
-        unimplemented!();
+fn offset(port: u16, port_offset: u16) -> Result<u16, String> {
+    port.checked_add(port_offset)
+        .ok_or_else(|| format!("Port {} offset by {} is out of range (--other-team-port-offset)", port, port_offset))
+}
+
+/// The addresses a stream is sent to, defaulting to the field computer when none are given
+fn send_addresses(addresses: &[String], field_computer_address: &str, port: u16, port_offset: u16) -> Result<Vec<String>, String> {
+    if addresses.is_empty() {
+        Ok(vec![socket_address(field_computer_address, offset(port, port_offset)?)])
     } else {
-        // Acquire the peripherals
-        let spi = Spi::new(Bus::Spi0, SlaveSelect::Ss0, 1_000_000, Mode::Mode0)?;
-        let gpio = Gpio::new()?;
-        let csn = gpio.get(8)?.into_output();
-        let ce = gpio.get(22)?.into_output();
-        let delay = Delay::new();
-
-        let control_message_bind_address = format!("0.0.0.0:{}", args.control_message_port);
-        // let control_message_send_address = format!("{}:{}", args.field_computer_address, args.control_message_port);
-        let robot_status_bind_address = format!("0.0.0.0:{}", args.robot_status_port);
-        let robot_status_send_address = format!("{}:{}", args.field_computer_address, args.robot_status_port);
-        let alive_robots_bind_address = format!("0.0.0.0:{}", args.alive_robots_port);
-        let alive_robots_send_address = format!("{}:{}", args.field_computer_address, args.alive_robots_port);
-
-        let mut radio_node = RadioNode::new(
-            TEAM,
-            args.robots,
-            ce,
-            csn,
-            spi,
-            delay,
-            &control_message_bind_address,
-            &robot_status_bind_address,
-            &robot_status_send_address,
-        );
-
-        let receive_message_subscriber = radio_node.create_subscriber();
-        let mut timeout_node = TimeoutCheckerNode::new(
-            args.robots,
-            args.timeout,
-            Box::leak(Box::new(alive_robots_bind_address)),
-            Box::leak(Box::new(alive_robots_send_address)),
-            receive_message_subscriber,
-        );
-
-        let (radio_tx, radio_rx) = mpsc::channel();
-        let (timeout_tx, timeout_rx) = mpsc::channel();
-
-        ctrlc::set_handler(move || {
-            let _ = radio_tx.send(true);
-            let _ = timeout_tx.send(true);
-        }).expect("Unable to set ctrl-c handler");
-
-        radio_node.start();
-        timeout_node.start();
-
-        let handle = spawn(move || {
-            while let Err(_) = timeout_rx.try_recv() {
-                timeout_node.update();
-                thread::sleep(Duration::from_millis(args.timeout as u64));
-            }
+        addresses.iter().map(|address| offset_port(address, port_offset)).collect()
+    }
+}
+
+/// The addresses of one team's streams to and from the Field Computer
+struct TeamAddresses {
+    control_message_bind_address: String,
+    robot_status_bind_address: String,
+    robot_status_send_addresses: Vec<String>,
+    alive_robots_bind_address: String,
+    alive_robots_send_addresses: Vec<String>,
+    status_snapshot_bind_address: String,
+    control_api_bind_address: String,
+    heartbeat_bind_address: String,
+    json_mirror_bind_address: String,
+    json_mirror_send_addresses: Vec<String>,
+}
+
+impl TeamAddresses {
+    /// The addresses given on the command line with every port offset by `port_offset`
+    fn new(args: &Args, port_offset: u16) -> Result<Self, String> {
+        let bind_address = |port| Ok::<_, String>(socket_address(&args.bind_address, offset(port, port_offset)?));
+        Ok(Self {
+            control_message_bind_address: bind_address(args.control_message_port)?,
+            robot_status_bind_address: bind_address(args.robot_status_port)?,
+            robot_status_send_addresses: send_addresses(&args.robot_status_address, &args.field_computer_address, args.robot_status_port, port_offset)?,
+            alive_robots_bind_address: bind_address(args.alive_robots_port)?,
+            alive_robots_send_addresses: send_addresses(&args.alive_robots_address, &args.field_computer_address, args.alive_robots_port, port_offset)?,
+            status_snapshot_bind_address: bind_address(args.status_snapshot_port)?,
+            control_api_bind_address: format!("127.0.0.1:{}", offset(args.control_api_port, port_offset)?),
+            heartbeat_bind_address: bind_address(args.heartbeat_port)?,
+            json_mirror_bind_address: bind_address(args.json_mirror_port)?,
+            json_mirror_send_addresses: send_addresses(&args.json_mirror_address, &args.field_computer_address, args.json_mirror_port, port_offset)?,
+        })
+    }
+}
+
+/// The Control Message validation limits given on the command line (velocities in the raw
+/// units of the packed message)
+fn validation_limits(args: &Args) -> ValidationLimits {
+    ValidationLimits {
+        max_linear_velocity: args.max_linear_velocity * VELOCITY_SCALE_FACTOR,
+        max_angular_velocity: args.max_angular_velocity * VELOCITY_SCALE_FACTOR,
+        min_dribbler_speed: args.min_dribbler_speed,
+        max_dribbler_speed: args.max_dribbler_speed,
+        max_kick_strength: args.max_kick_strength,
+    }
+}
+
+/// Create the Radio Node and Timeout Checker of one team
+fn team_nodes<'a>(
+    args: &Args,
+    team: Team,
+    robot_table: RobotTable,
+    radio_config: RadioConfig,
+    max_robots: usize,
+    radio_link: RadioThread,
+    addresses: &'a TeamAddresses,
+) -> (RadioNode<'a, RadioThread>, TimeoutCheckerNode<'a>) {
+    let multicast_options = MulticastOptions {
+        ttl: args.multicast_ttl,
+        interface: args.multicast_interface,
+    };
+
+    let mut radio_node = RadioNode::new(
+        team,
+        args.robots,
+        robot_table,
+        radio_config,
+        radio_link,
+        &addresses.control_message_bind_address,
+        &addresses.robot_status_bind_address,
+        addresses.robot_status_send_addresses.iter().map(String::as_str).collect(),
+        &addresses.status_snapshot_bind_address,
+        &addresses.control_api_bind_address,
+    );
+
+    if let Some(heartbeat_timeout) = args.heartbeat_timeout {
+        radio_node.add_field_link_monitor(FieldLinkMonitor::new(
+            &addresses.heartbeat_bind_address,
+            Duration::from_millis(heartbeat_timeout),
+        ));
+    }
+
+    match args.json_mirror {
+        Some(JsonMirrorTransport::Udp) => radio_node.add_json_mirror(JsonMirror::udp(
+            &addresses.json_mirror_bind_address,
+            addresses.json_mirror_send_addresses.iter().map(String::as_str).collect(),
+        )),
+        Some(JsonMirrorTransport::Tcp) => radio_node.add_json_mirror(JsonMirror::tcp(&addresses.json_mirror_bind_address)),
+        None => (),
+    }
+
+    radio_node.set_multicast(&multicast_options);
+    if let Some(bind_interface) = args.bind_interface.as_ref() {
+        radio_node.set_bind_interface(bind_interface);
+    }
+    radio_node.set_max_robots(max_robots);
+    let timeout_settings_subscriber = radio_node.create_timeout_settings_subscriber(TimeoutSettings {
+        num_robots: args.robots,
+        timeout: args.timeout,
+    });
+
+    let receive_message_subscriber = radio_node.create_subscriber();
+    let mut timeout_node = TimeoutCheckerNode::new(
+        args.robots,
+        args.timeout,
+        args.alive_robots_encoding,
+        &addresses.alive_robots_bind_address,
+        addresses.alive_robots_send_addresses.iter().map(String::as_str).collect(),
+        receive_message_subscriber,
+    );
+    timeout_node.set_multicast(&multicast_options);
+    if let Some(bind_interface) = args.bind_interface.as_ref() {
+        timeout_node.set_bind_interface(bind_interface);
+    }
+    timeout_node.add_timeout_settings_subscriber(timeout_settings_subscriber);
+    if args.json_mirror.is_some() {
+        radio_node.add_alive_robots_mirror_subscriber(timeout_node.create_alive_robots_subscriber());
+    }
+
+    radio_node.set_validation_limits(validation_limits(args));
+    radio_node.set_echo_sequences(args.echo_sequence);
+    if let Some(latency_export_dir) = args.latency_export_dir.clone() {
+        radio_node.set_latency_export_dir(latency_export_dir);
+    }
+    radio_node.set_update_delay(args.send_timeout_ms);
+    (radio_node, timeout_node)
+}
+
+/// Run the Radio Node and Timeout Checker of each team (with the addresses in
+/// `team_addresses`) until ctrl-c is pressed
+fn run(
+    args: Args,
+    teams: Vec<(Team, RobotTable)>,
+    team_addresses: Vec<TeamAddresses>,
+    radio_config: RadioConfig,
+    max_robots: usize,
+    mut radio_link: RadioThread,
+) -> Result<(), Box<dyn Error>> {
+    // The radio is shared by every team so it is only initialized (and checked) once
+    for (team, robot_table) in teams.iter() {
+        radio_link.set_robot_table(*team, robot_table);
+    }
+    if !radio_link.initialize(&radio_config) {
+        return Err("Unable to Initialize the radio".into());
+    }
+    let radio_mismatches = match verify_radio(&mut radio_link, &radio_config) {
+        Some(radio_mismatches) if args.strict_radio_config && !radio_mismatches.is_empty() => {
+            return Err("The radio doesn't hold the intended configuration (--strict-radio-config)".into());
+        },
+        Some(radio_mismatches) => radio_mismatches,
+        None if args.strict_radio_config => {
+            return Err("Unable to read the radio's registers to verify its configuration (--strict-radio-config)".into());
+        },
+        None => Vec::new(),
+    };
+
+    let mut radio_nodes = Vec::new();
+    let mut timeout_nodes = Vec::new();
+    for ((team, robot_table), addresses) in teams.into_iter().zip(team_addresses.iter()) {
+        let (mut radio_node, timeout_node) = team_nodes(&args, team, robot_table, radio_config, max_robots, radio_link.clone(), addresses);
+        radio_node.set_radio_mismatches(radio_mismatches.clone());
+        radio_nodes.push(radio_node);
+        timeout_nodes.push(timeout_node);
+    }
+
+    let mut radio_nodes = radio_nodes.into_iter();
+    let mut our_radio_node = radio_nodes.next().ok_or("No teams to serve")?;
+    if let Some(latency_csv) = args.latency_csv.clone() {
+        our_radio_node.set_latency_export(latency_csv);
+    }
+    // Both teams are sent their commands in the same radio cycle
+    let mut radio_node: Box<dyn Node + '_> = match radio_nodes.next() {
+        Some(other_radio_node) => Box::new(ScrimmageNode::new(our_radio_node, other_radio_node)),
+        None => Box::new(our_radio_node),
+    };
+
+    // Each thread updates its nodes every `get_update_delay` milliseconds and shuts them
+    // down once ctrl-c is pressed.  The Timeout Checkers run on their own thread so sending
+    // alive robots never holds up the Radio Node.
+    let (radio_interrupt_tx, radio_interrupt_rx) = mpsc::channel();
+    let (timeout_interrupt_tx, timeout_interrupt_rx) = mpsc::channel();
+    ctrlc::set_handler(move || {
+        let _ = radio_interrupt_tx.send(true);
+        let _ = timeout_interrupt_tx.send(true);
+    }).expect("Unable to set ctrl-c handler");
+
+    thread::scope(|scope| {
+        let timeout_nodes = &mut timeout_nodes;
+        scope.spawn(move || {
+            let mut timeout_nodes: Vec<&mut dyn Node> = timeout_nodes.iter_mut()
+                .map(|timeout_node| timeout_node as &mut dyn Node)
+                .collect();
+            run_nodes(&mut timeout_nodes, timeout_interrupt_rx);
         });
 
-        while let Err(_) = radio_rx.try_recv() {
-            radio_node.update();
-        }
+        run_nodes(&mut [radio_node.as_mut()], radio_interrupt_rx);
+    });
+
+    Ok(())
+}
+
+/// Open the virtual radio (optionally injecting faults) on the radio thread
+fn open_virtual_radio(
+    bind_address: &str,
+    peer_address: &str,
+    fault_scenario: Option<FaultScenario>,
+    robot_table: &RobotTable,
+) -> Result<Box<dyn RadioLink>, Box<dyn Error>> {
+    let link = VirtualLink::open(bind_address, peer_address)?;
+    let radio_link = VirtualRadio::new(link, robot_table);
+    match fault_scenario {
+        Some(fault_scenario) => Ok(Box::new(FaultyLink::new(radio_link, fault_scenario))),
+        None => Ok(Box::new(radio_link)),
+    }
+}
+
+/// Acquire the peripherals and open the nRF24L01+ on the radio thread
+fn open_hardware_radio(poll_radio: bool, robot_table: &RobotTable) -> Result<Box<dyn RadioLink>, Box<dyn Error>> {
+    let spi = Spi::new(Bus::Spi0, SlaveSelect::Ss0, 1_000_000, Mode::Mode0)?;
+    let gpio = Gpio::new()?;
+    let csn = gpio.get(8)?.into_output();
+    let ce = gpio.get(22)?.into_output();
+    let delay = Delay::new();
+
+    let mut radio_link = NrfPublisherSubscriber::new(Radio::new(ce, csn), spi, delay, robot_table);
+    if !poll_radio {
+        let irq = gpio.get(RADIO_IRQ)?.into_input_pullup();
+        radio_link.set_irq(Box::new(GpioIrq::new(irq)?));
+    }
+    Ok(Box::new(radio_link))
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let args = Args::parse();
+
+    // Load the robot table and make sure the robots in play can be represented
+    let address_set = AddressSet::new(TEAM, args.site_id);
+    if !address_set.is_default() {
+        println!("Using the radio addresses of site {}", args.site_id);
+    }
+    let robot_table = match args.robot_config.as_ref() {
+        Some(path) => RobotTable::from_file(path)?,
+        None => address_set.robot_table(),
+    };
+    let max_robots = max_protocol_robots().min(args.alive_robots_encoding.max_robots());
+    if args.robots as usize > max_robots {
+        return Err(Box::new(ConfigError::TooManyRobots { requested: args.robots as usize, max: max_robots }));
+    }
+    robot_table.check_robots_in_play(args.robots)?;
 
-        handle.join().unwrap();
+    let mut teams = vec![(TEAM, robot_table.clone())];
+    if args.dual_team {
+        let other_team = match TEAM {
+            Team::Blue => Team::Yellow,
+            Team::Yellow => Team::Blue,
+        };
+        let other_robot_table = match args.other_team_robot_config.as_ref() {
+            Some(path) => RobotTable::from_file(path)?,
+            None => AddressSet::new(other_team, args.site_id).robot_table(),
+        };
+        other_robot_table.check_robots_in_play(args.robots)?;
+        println!("Serving Both Teams (the {:?} team's ports are offset by {})", other_team, args.other_team_port_offset);
+        teams.push((other_team, other_robot_table));
+    }
+
+    // The first team uses the ports given on the command line and the second (in dual-team
+    // mode) the ports offset by --other-team-port-offset
+    let team_addresses = (0..teams.len() as u16)
+        .map(|index| TeamAddresses::new(&args, args.other_team_port_offset * index))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let radio_config = RadioConfig {
+        channel: args.channel,
+        pa_level: parse_pa_level(&args.pa_level).ok_or("Invalid PA Level (expected min, low, high or max)")?,
+        base_station_address: address_set.base_station_address(),
+    };
+    radio_config.validate()?;
+    validation_limits(&args).validate()?;
+    let signer = match args.auth_key_file.as_ref() {
+        Some(auth_key_file) => {
+            let counter_file = args.auth_counter_file.clone()
+                .unwrap_or_else(|| format!("{}.counter", auth_key_file));
+            Some(ControlSigner::with_counter_file(AuthKey::from_file(auth_key_file)?, counter_file)?)
+        },
+        None => None,
+    };
+
+    let mut radio_link = if args.two_radios {
+        unimplemented!();
+    } else if let Some(virtual_radio) = args.virtual_radio.clone() {
+        // Talk to simulated robots (see robot-sim) instead of the radio
+        let bind_address = args.virtual_radio_bind.clone();
+        let fault_scenario = args.fault_scenario.as_ref().map(FaultScenario::from_file).transpose()?;
+        let robot_table = robot_table.clone();
+        RadioThread::spawn(move || {
+            open_virtual_radio(&bind_address, &virtual_radio, fault_scenario, &robot_table)
+                .map_err(|err| err.to_string())
+        })?
+    } else {
+        let poll_radio = args.poll_radio;
+        let robot_table = robot_table.clone();
+        RadioThread::spawn(move || {
+            open_hardware_radio(poll_radio, &robot_table).map_err(|err| err.to_string())
+        })?
+    };
+    if let Some(signer) = signer {
+        println!("Authenticating Control Messages");
+        radio_link.set_signer(Some(signer));
     }
+    run(args, teams, team_addresses, radio_config, max_robots, radio_link)?;
 
     Ok(())
 }
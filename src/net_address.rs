@@ -0,0 +1,60 @@
+//!
+//! Network Addresses
+//!
+//! The field computer (and every other destination) can be given as a hostname, an IPv4
+//! address or an IPv6 address.  Hostnames are resolved periodically so a field computer
+//! that changes address (e.g. over DHCP) is followed without restarting the base station.
+//!
+
+use std::io;
+use std::net::{IpAddr, Ipv6Addr, SocketAddr, SocketAddrV6, ToSocketAddrs, UdpSocket};
+use std::time::Duration;
+
+/// How often destinations given as hostnames are re-resolved
+pub const DEFAULT_RESOLVE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Join a host (hostname, IPv4 or IPv6 address) and port into an address that can be
+/// bound to or resolved (IPv6 addresses are wrapped in brackets)
+pub fn socket_address(host: &str, port: u16) -> String {
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    if host.parse::<Ipv6Addr>().is_ok() || host.contains(':') {
+        format!("[{}]:{}", host, port)
+    } else {
+        format!("{}:{}", host, port)
+    }
+}
+
+/// Resolve an address of the form host:port into every matching socket address
+pub fn resolve(address: &str) -> io::Result<Vec<SocketAddr>> {
+    Ok(address.to_socket_addrs()?.collect())
+}
+
+/// Pick the resolved address a socket bound to `local_address` can send to.  Sockets
+/// bound to IPv6 prefer IPv6 addresses but can reach IPv4 addresses through IPv4-mapped
+/// IPv6 addresses.
+pub fn usable_address(addresses: &[SocketAddr], local_address: &SocketAddr) -> Option<SocketAddr> {
+    match local_address {
+        SocketAddr::V4(_) => addresses.iter().find(|address| address.is_ipv4()).copied(),
+        SocketAddr::V6(_) => addresses.iter()
+            .find(|address| address.is_ipv6())
+            .copied()
+            .or_else(|| addresses.iter().find_map(|address| match address.ip() {
+                IpAddr::V4(ip) => Some(SocketAddr::V6(SocketAddrV6::new(ip.to_ipv6_mapped(), address.port(), 0, 0))),
+                IpAddr::V6(_) => None,
+            })),
+    }
+}
+
+/// Bind a UDP socket to a specific network interface (e.g. "eth0") so datagrams are only
+/// sent and received over that interface
+#[cfg(any(target_os = "linux", target_os = "android", target_os = "fuchsia"))]
+pub fn bind_to_interface(socket: &UdpSocket, interface: &str) -> io::Result<()> {
+    socket2::SockRef::from(socket).bind_device(Some(interface.as_bytes()))
+}
+
+/// Bind a UDP socket to a specific network interface (e.g. "eth0") so datagrams are only
+/// sent and received over that interface
+#[cfg(not(any(target_os = "linux", target_os = "android", target_os = "fuchsia")))]
+pub fn bind_to_interface(_socket: &UdpSocket, _interface: &str) -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "binding to an interface is only supported on linux"))
+}
@@ -0,0 +1,161 @@
+//!
+//! Network Error Accounting
+//!
+//! While the field computer is down (or the Ethernet cable is pulled) every send fails.
+//! Errors are counted and logged at most once per interval (with how many were
+//! suppressed in between) so the log stays readable and a dead network can't slow the
+//! base station down.
+//!
+//! Sockets that serve the field computer (`RebindingSocket`) are bound again once a network
+//! that went away (or wasn't up yet when the base station started) comes back.
+//!
+
+use std::fmt::Display;
+use std::io::{self, ErrorKind};
+use std::time::{Duration, Instant};
+
+/// How often a repeating network error is logged
+pub const DEFAULT_ERROR_LOG_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How long to wait between attempts to bind a socket whose network went away
+pub const REBIND_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The error (`ENODEV` on Linux) returned once the interface a socket was bound to
+/// (SO_BINDTODEVICE) has been removed
+pub const ENODEV: i32 = 19;
+
+/// Counts errors and logs them at most once per interval
+pub struct ErrorLog {
+    context: String,
+    interval: Duration,
+    count: u64,
+    suppressed: u64,
+    last_logged: Option<Instant>,
+}
+
+impl ErrorLog {
+    pub fn new(context: &str) -> Self {
+        Self::with_interval(context, DEFAULT_ERROR_LOG_INTERVAL)
+    }
+
+    pub fn with_interval(context: &str, interval: Duration) -> Self {
+        Self {
+            context: context.to_string(),
+            interval,
+            count: 0,
+            suppressed: 0,
+            last_logged: None,
+        }
+    }
+
+    /// Record an error, returning whether it was logged (it isn't if another error was
+    /// logged less than an interval ago)
+    pub fn record<E: Display>(&mut self, err: E) -> bool {
+        self.count += 1;
+        let log_due = self.last_logged
+            .map(|last_logged| last_logged.elapsed() >= self.interval)
+            .unwrap_or(true);
+        if !log_due {
+            self.suppressed += 1;
+            return false;
+        }
+
+        if self.suppressed > 0 {
+            println!("{}: {} ({} similar errors suppressed)", self.context, err, self.suppressed);
+        } else {
+            println!("{}: {}", self.context, err);
+        }
+        self.suppressed = 0;
+        self.last_logged = Some(Instant::now());
+        true
+    }
+
+    /// The number of errors recorded
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+}
+
+/// Whether an error means the network (or the interface a socket is bound to) has gone
+/// away, in which case the socket should be bound again once it comes back
+pub fn is_network_down(err: &io::Error) -> bool {
+    matches!(
+        err.kind(),
+        ErrorKind::NetworkDown | ErrorKind::NetworkUnreachable | ErrorKind::HostUnreachable | ErrorKind::AddrNotAvailable
+    ) || err.raw_os_error() == Some(ENODEV)
+}
+
+/// A socket that is bound again (at most every REBIND_INTERVAL) after its network went
+/// away.  If the network is down when the socket is created binding is deferred instead of
+/// failing.
+pub struct RebindingSocket<S> {
+    bind_address: String,
+    bind: fn(&str) -> io::Result<S>,
+    socket: Option<S>,
+    last_bind_attempt: Instant,
+    errors: ErrorLog,
+}
+
+impl<S> RebindingSocket<S> {
+    /// Bind `bind_address` with `bind`, returning any error that doesn't mean the network
+    /// is down.  Errors are logged (at most once per interval) with `context`.
+    pub fn new(bind_address: &str, bind: fn(&str) -> io::Result<S>, context: &str) -> io::Result<Self> {
+        let mut errors = ErrorLog::new(context);
+        let socket = match bind(bind_address) {
+            Ok(socket) => Some(socket),
+            Err(err) if is_network_down(&err) => {
+                errors.record(format!("unable to bind {}: {:?} (binding again once the network is up)", bind_address, err));
+                None
+            },
+            Err(err) => return Err(err),
+        };
+
+        Ok(Self {
+            bind_address: bind_address.to_string(),
+            bind,
+            socket,
+            last_bind_attempt: Instant::now(),
+            errors,
+        })
+    }
+
+    pub fn bind_address(&self) -> &str {
+        &self.bind_address
+    }
+
+    /// The socket if it is currently bound
+    pub fn bound(&self) -> Option<&S> {
+        self.socket.as_ref()
+    }
+
+    /// The socket, binding it again first if it was lost and an attempt is due
+    pub fn socket(&mut self) -> Option<&S> {
+        if self.socket.is_none() && self.last_bind_attempt.elapsed() >= REBIND_INTERVAL {
+            self.last_bind_attempt = Instant::now();
+            match (self.bind)(&self.bind_address) {
+                Ok(socket) => {
+                    println!("Bound {} again", self.bind_address);
+                    self.socket = Some(socket);
+                },
+                Err(err) => {
+                    self.errors.record(format!("unable to bind {}: {:?}", self.bind_address, err));
+                },
+            }
+        }
+        self.socket.as_ref()
+    }
+
+    /// Record an error from the socket, dropping the socket (so it is bound again later) if
+    /// the network went away
+    pub fn record_error(&mut self, context: &str, err: &io::Error) {
+        if is_network_down(err) && self.socket.take().is_some() {
+            self.last_bind_attempt = Instant::now();
+        }
+        self.errors.record(format!("{}: {:?}", context, err));
+    }
+
+    /// The number of errors recorded
+    pub fn errors(&self) -> u64 {
+        self.errors.count()
+    }
+}
@@ -0,0 +1,36 @@
+//!
+//! Node Executor
+//!
+//! ncomm's `SimpleExecutor` starts its nodes and updates each one every `get_update_delay`
+//! milliseconds until it is interrupted, but it never shuts them down.  `run_nodes` runs
+//! nodes on the calling thread with a `SimpleExecutor` and then shuts every one of them
+//! down, so nodes can rely on `shutdown` being called (e.g. the Radio Node writes its
+//! latency CSV and the Timeout Checker sends its final alive robots).
+//!
+
+use std::sync::mpsc::Receiver;
+
+use ncomm::executor::{simple_executor::SimpleExecutor, Executor, SingleThreadedExecutor};
+use ncomm::node::Node;
+
+/// Start and update `nodes` until `true` is received on `interrupt_rx`, then shut them
+/// down
+pub fn run_nodes(nodes: &mut [&mut dyn Node], interrupt_rx: Receiver<bool>) {
+    if nodes.is_empty() {
+        return;
+    }
+
+    // The executor borrows the nodes until it is dropped
+    {
+        let mut executor = SimpleExecutor::new(interrupt_rx);
+        for node in nodes.iter_mut() {
+            executor.add_node(&mut **node);
+        }
+        executor.start();
+        executor.update_loop();
+    }
+
+    for node in nodes.iter_mut() {
+        node.shutdown();
+    }
+}
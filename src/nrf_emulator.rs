@@ -0,0 +1,650 @@
+//!
+//! Emulated nRF24L01+
+//!
+//! A register-level model of the nRF24L01+ that implements the embedded-hal SPI, output pin
+//! and delay traits so `rtic_nrf24l01::Radio` (and the `NrfPublisherSubscriber`) can run
+//! without hardware.  The emulator models the SPI command set, the register map (with the
+//! datasheet reset values), the TX and RX FIFOs and the behaviour of CE:
+//!
+//! - while CE is high in PTX mode (PRIM_RX = 0) every payload in the TX FIFO is transmitted
+//!   and is either acknowledged (TX_DS) or runs out of retransmits (MAX_RT)
+//! - while CE is high in PRX mode (PRIM_RX = 1) payloads sent to an enabled pipe's address
+//!   are received into the RX FIFO (RX_DR)
+//!
+//! The IRQ line (`EmulatedIrq`) is asserted while an interrupt flag that isn't masked in
+//! CONFIG is set in STATUS.
+//!
+//! Every handle (`NrfEmulator`, `EmulatedSpi`, `EmulatedPin`, `EmulatedDelay` and
+//! `EmulatedIrq`) shares the same chip so tests can keep the `NrfEmulator` to inspect
+//! registers, see what was transmitted and deliver payloads to the radio (directly or by
+//! responding to each acknowledged transmission).
+//!
+//! Addresses are stored exactly as they are written over SPI (least significant byte first).
+//!
+
+use std::collections::VecDeque;
+use std::convert::Infallible;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use embedded_hal::blocking::delay::{DelayMs, DelayUs};
+use embedded_hal::blocking::spi::{Transfer, Write};
+use embedded_hal::digital::v2::OutputPin;
+
+use rtic_nrf24l01::config::power_amplifier::PowerAmplifier;
+
+use crate::publishers::radio_irq::RadioIrq;
+
+pub use crate::nrf_registers::*;
+
+/// The depth of the TX and RX FIFOs
+pub const FIFO_DEPTH: usize = 3;
+
+/// The largest payload the nRF24L01+ can send
+pub const MAX_PAYLOAD_SIZE: usize = 32;
+
+/// A payload transmitted by the emulated radio
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Transmission {
+    pub channel: u8,
+    pub address: [u8; 5],
+    pub payload: Vec<u8>,
+    /// Whether the payload was sent with W_TX_PAYLOAD_NO_ACK
+    pub no_ack: bool,
+    /// Whether the payload was acknowledged
+    pub acked: bool,
+    /// The number of times the payload was retransmitted
+    pub retransmits: u8,
+}
+
+/// Decides how the receiver of a transmission responds (with a payload sent to an address)
+pub type Responder = Box<dyn FnMut(&Transmission) -> Option<([u8; 5], Vec<u8>)> + Send>;
+
+/// A single SPI command (from CSN low to CSN high)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SpiCommand {
+    pub command: u8,
+    /// The bytes clocked in after the command byte
+    pub data: Vec<u8>,
+}
+
+#[derive(Clone, Debug)]
+struct TxPayload {
+    payload: Vec<u8>,
+    no_ack: bool,
+}
+
+#[derive(Clone, Debug)]
+struct RxPayload {
+    pipe: u8,
+    payload: Vec<u8>,
+}
+
+/// The state of the emulated chip
+struct NrfChip {
+    registers: [u8; 0x1E],
+    pipe_addresses: [[u8; 5]; 2],
+    tx_address: [u8; 5],
+    tx_fifo: VecDeque<TxPayload>,
+    rx_fifo: VecDeque<RxPayload>,
+    csn: bool,
+    ce: bool,
+    transaction: Option<SpiCommand>,
+    commands: Vec<SpiCommand>,
+    transmissions: Vec<Transmission>,
+    ack: bool,
+    retransmits: u8,
+    lost_packets: u8,
+    responder: Option<Responder>,
+    responses: VecDeque<([u8; 5], Vec<u8>)>,
+    spi_errors: u64,
+    delayed: Duration,
+}
+
+impl NrfChip {
+    fn new() -> Self {
+        let mut registers = [0u8; 0x1E];
+        registers[CONFIG as usize] = 0x08;
+        registers[EN_AA as usize] = 0x3F;
+        registers[EN_RXADDR as usize] = 0x03;
+        registers[SETUP_AW as usize] = 0x03;
+        registers[SETUP_RETR as usize] = 0x03;
+        registers[RF_CH as usize] = 0x02;
+        registers[RF_SETUP as usize] = 0x0E;
+        registers[STATUS as usize] = 0x0E;
+        registers[0x0C] = 0xC3;
+        registers[0x0D] = 0xC4;
+        registers[0x0E] = 0xC5;
+        registers[0x0F] = 0xC6;
+
+        Self {
+            registers,
+            pipe_addresses: [[0xE7; 5], [0xC2; 5]],
+            tx_address: [0xE7; 5],
+            tx_fifo: VecDeque::new(),
+            rx_fifo: VecDeque::new(),
+            csn: true,
+            ce: false,
+            transaction: None,
+            commands: Vec::new(),
+            transmissions: Vec::new(),
+            ack: true,
+            retransmits: 0,
+            lost_packets: 0,
+            responder: None,
+            responses: VecDeque::new(),
+            spi_errors: 0,
+            delayed: Duration::ZERO,
+        }
+    }
+
+    fn powered_up(&self) -> bool {
+        self.registers[CONFIG as usize] & PWR_UP != 0
+    }
+
+    fn primary_rx(&self) -> bool {
+        self.registers[CONFIG as usize] & PRIM_RX != 0
+    }
+
+    fn status(&self) -> u8 {
+        let rx_pipe = match self.rx_fifo.front() {
+            Some(rx_payload) => rx_payload.pipe,
+            None => 0b111,
+        };
+        let tx_full = (self.tx_fifo.len() >= FIFO_DEPTH) as u8;
+        (self.registers[STATUS as usize] & (RX_DR | TX_DS | MAX_RT)) | (rx_pipe << 1) | tx_full
+    }
+
+    /// Whether the IRQ line is asserted
+    fn irq_asserted(&self) -> bool {
+        let flags = self.registers[STATUS as usize] & (RX_DR | TX_DS | MAX_RT);
+        let masked = self.registers[CONFIG as usize] & (MASK_RX_DR | MASK_TX_DS | MASK_MAX_RT);
+        flags & !masked != 0
+    }
+
+    fn fifo_status(&self) -> u8 {
+        let mut fifo_status = 0;
+        if self.tx_fifo.len() >= FIFO_DEPTH {
+            fifo_status |= 1 << 5;
+        }
+        if self.tx_fifo.is_empty() {
+            fifo_status |= 1 << 4;
+        }
+        if self.rx_fifo.len() >= FIFO_DEPTH {
+            fifo_status |= 1 << 1;
+        }
+        if self.rx_fifo.is_empty() {
+            fifo_status |= 1 << 0;
+        }
+        fifo_status
+    }
+
+    /// The value of a register (addresses return their least significant byte)
+    fn register(&self, register: u8) -> u8 {
+        match register {
+            STATUS => self.status(),
+            FIFO_STATUS => self.fifo_status(),
+            RX_ADDR_P0 => self.pipe_addresses[0][0],
+            RX_ADDR_P1 => self.pipe_addresses[1][0],
+            TX_ADDR => self.tx_address[0],
+            _ => self.registers.get(register as usize).copied().unwrap_or(0),
+        }
+    }
+
+    /// The bytes clocked out when reading a register
+    fn read_register(&self, register: u8) -> Vec<u8> {
+        match register {
+            RX_ADDR_P0 => self.pipe_addresses[0].to_vec(),
+            RX_ADDR_P1 => self.pipe_addresses[1].to_vec(),
+            TX_ADDR => self.tx_address.to_vec(),
+            _ => vec![self.register(register)],
+        }
+    }
+
+    fn write_register(&mut self, register: u8, data: &[u8]) {
+        let Some(first) = data.first().copied() else { return };
+        match register {
+            STATUS => {
+                // Interrupt flags are cleared by writing 1 to them
+                self.registers[STATUS as usize] &= !(first & (RX_DR | TX_DS | MAX_RT));
+            },
+            RX_ADDR_P0 | RX_ADDR_P1 | TX_ADDR => {
+                let address = match register {
+                    RX_ADDR_P0 => &mut self.pipe_addresses[0],
+                    RX_ADDR_P1 => &mut self.pipe_addresses[1],
+                    _ => &mut self.tx_address,
+                };
+                for (byte, value) in address.iter_mut().zip(data.iter()) {
+                    *byte = *value;
+                }
+            },
+            RF_CH => {
+                // Changing the channel resets the lost packet count
+                self.registers[RF_CH as usize] = first;
+                self.lost_packets = 0;
+                self.registers[OBSERVE_TX as usize] &= 0x0F;
+            },
+            OBSERVE_TX | RPD | FIFO_STATUS => (),
+            _ => if let Some(value) = self.registers.get_mut(register as usize) {
+                *value = first;
+            },
+        }
+    }
+
+    /// The address of a pipe (pipes 2-5 share the upper bytes of pipe 1)
+    fn pipe_address(&self, pipe: u8) -> [u8; 5] {
+        match pipe {
+            0 | 1 => self.pipe_addresses[pipe as usize],
+            _ => {
+                let mut address = self.pipe_addresses[1];
+                address[0] = self.registers[(RX_ADDR_P0 + pipe) as usize];
+                address
+            },
+        }
+    }
+
+    /// The bytes clocked out for the `index`th data byte of a command
+    fn output(&self, command: u8, index: usize) -> u8 {
+        match command {
+            command if command & 0xE0 == R_REGISTER => {
+                self.read_register(command & 0x1F).get(index).copied().unwrap_or(0)
+            },
+            R_RX_PAYLOAD => self.rx_fifo.front()
+                .and_then(|rx_payload| rx_payload.payload.get(index).copied())
+                .unwrap_or(0),
+            R_RX_PL_WID => self.rx_fifo.front().map(|rx_payload| rx_payload.payload.len() as u8).unwrap_or(0),
+            _ => 0,
+        }
+    }
+
+    /// Clock bytes through the SPI bus, replacing them with the bytes clocked out
+    fn clock(&mut self, words: &mut [u8]) {
+        if self.csn {
+            // The chip ignores the bus while it isn't selected
+            self.spi_errors += 1;
+            words.iter_mut().for_each(|word| *word = 0xFF);
+            return;
+        }
+
+        for word in words.iter_mut() {
+            let input = *word;
+            *word = match self.transaction.as_mut() {
+                None => {
+                    self.transaction = Some(SpiCommand { command: input, data: Vec::new() });
+                    self.status()
+                },
+                Some(transaction) => {
+                    transaction.data.push(input);
+                    let (command, index) = (transaction.command, transaction.data.len() - 1);
+                    self.output(command, index)
+                },
+            };
+        }
+    }
+
+    /// Carry out the command clocked in while CSN was low
+    fn finish_transaction(&mut self) {
+        let Some(transaction) = self.transaction.take() else { return };
+        let SpiCommand { command, data } = transaction.clone();
+        self.commands.push(transaction);
+
+        match command {
+            command if command & 0xE0 == R_REGISTER => (),
+            command if command & 0xE0 == W_REGISTER => self.write_register(command & 0x1F, &data),
+            R_RX_PAYLOAD if !data.is_empty() => {
+                self.rx_fifo.pop_front();
+            },
+            W_TX_PAYLOAD | W_TX_PAYLOAD_NO_ACK if self.tx_fifo.len() < FIFO_DEPTH => {
+                let mut payload = data;
+                payload.truncate(MAX_PAYLOAD_SIZE);
+                self.tx_fifo.push_back(TxPayload { payload, no_ack: command == W_TX_PAYLOAD_NO_ACK });
+            },
+            FLUSH_TX => self.tx_fifo.clear(),
+            FLUSH_RX => self.rx_fifo.clear(),
+            _ => (),
+        }
+        self.run();
+    }
+
+    /// Transmit the TX FIFO while CE is high in PTX mode and deliver responses while
+    /// listening in PRX mode
+    fn run(&mut self) {
+        if !self.ce || !self.powered_up() {
+            return;
+        }
+
+        if self.primary_rx() {
+            while let Some((address, payload)) = self.responses.pop_front() {
+                self.receive(address, &payload);
+            }
+            return;
+        }
+
+        // Transmission stops until MAX_RT is cleared
+        while self.registers[STATUS as usize] & MAX_RT == 0 {
+            let Some(tx_payload) = self.tx_fifo.front().cloned() else { return };
+            let auto_retransmits = self.registers[SETUP_RETR as usize] & 0x0F;
+            let acked = tx_payload.no_ack || (self.ack && self.retransmits <= auto_retransmits);
+            let retransmits = match (tx_payload.no_ack, acked) {
+                (true, _) => 0,
+                (false, true) => self.retransmits,
+                (false, false) => auto_retransmits,
+            };
+            let transmission = Transmission {
+                channel: self.registers[RF_CH as usize],
+                address: self.tx_address,
+                payload: tx_payload.payload,
+                no_ack: tx_payload.no_ack,
+                acked,
+                retransmits,
+            };
+
+            if acked {
+                self.tx_fifo.pop_front();
+                self.registers[STATUS as usize] |= TX_DS;
+                if let Some(response) = self.responder.as_mut().and_then(|responder| responder(&transmission)) {
+                    self.responses.push_back(response);
+                }
+            } else {
+                // The payload stays in the TX FIFO after running out of retransmits
+                self.registers[STATUS as usize] |= MAX_RT;
+                self.lost_packets = (self.lost_packets + 1).min(0x0F);
+            }
+            self.registers[OBSERVE_TX as usize] = (self.lost_packets << 4) | retransmits;
+            self.transmissions.push(transmission);
+        }
+    }
+
+    fn receive(&mut self, address: [u8; 5], payload: &[u8]) -> bool {
+        if !self.ce || !self.powered_up() || !self.primary_rx() || self.rx_fifo.len() >= FIFO_DEPTH {
+            return false;
+        }
+
+        let enabled = self.registers[EN_RXADDR as usize];
+        let Some(pipe) = (0..6u8).find(|pipe| enabled & (1 << pipe) != 0 && self.pipe_address(*pipe) == address) else {
+            return false;
+        };
+
+        // With dynamic payloads disabled, the payload width is fixed by RX_PW_Px
+        let mut payload = payload.to_vec();
+        if self.registers[DYNPD as usize] & (1 << pipe) == 0 {
+            payload.resize(self.registers[(RX_PW_P0 + pipe) as usize] as usize, 0);
+        }
+        self.rx_fifo.push_back(RxPayload { pipe, payload });
+        self.registers[STATUS as usize] |= RX_DR;
+        true
+    }
+}
+
+/// Inspection handle of an emulated nRF24L01+
+#[derive(Clone)]
+pub struct NrfEmulator {
+    chip: Arc<Mutex<NrfChip>>,
+}
+
+impl Default for NrfEmulator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl NrfEmulator {
+    pub fn new() -> Self {
+        Self {
+            chip: Arc::new(Mutex::new(NrfChip::new())),
+        }
+    }
+
+    fn chip(&self) -> MutexGuard<'_, NrfChip> {
+        self.chip.lock().expect("The emulated nRF24L01+ was poisoned")
+    }
+
+    /// The SPI bus of the chip
+    pub fn spi(&self) -> EmulatedSpi {
+        EmulatedSpi { chip: self.chip.clone() }
+    }
+
+    /// The chip select (CSN) pin of the chip
+    pub fn csn(&self) -> EmulatedPin {
+        EmulatedPin { chip: self.chip.clone(), pin: EmulatedPinKind::Csn }
+    }
+
+    /// The chip enable (CE) pin of the chip
+    pub fn ce(&self) -> EmulatedPin {
+        EmulatedPin { chip: self.chip.clone(), pin: EmulatedPinKind::Ce }
+    }
+
+    /// The IRQ line of the chip
+    pub fn irq(&self) -> EmulatedIrq {
+        EmulatedIrq { chip: self.chip.clone() }
+    }
+
+    /// A delay that returns immediately (recording how long it was asked to wait)
+    pub fn delay(&self) -> EmulatedDelay {
+        EmulatedDelay { chip: self.chip.clone() }
+    }
+
+    /// The value of a register (addresses return their least significant byte)
+    pub fn register(&self, register: u8) -> u8 {
+        self.chip().register(register)
+    }
+
+    /// The address of a pipe (0-5)
+    pub fn pipe_address(&self, pipe: u8) -> [u8; 5] {
+        self.chip().pipe_address(pipe)
+    }
+
+    pub fn tx_address(&self) -> [u8; 5] {
+        self.chip().tx_address
+    }
+
+    pub fn channel(&self) -> u8 {
+        self.register(RF_CH)
+    }
+
+    pub fn pa_level(&self) -> PowerAmplifier {
+        match (self.register(RF_SETUP) >> 1) & 0b11 {
+            0 => PowerAmplifier::PAMin,
+            1 => PowerAmplifier::PALow,
+            2 => PowerAmplifier::PAHigh,
+            _ => PowerAmplifier::PAMax,
+        }
+    }
+
+    /// The static payload width of a pipe (0-5)
+    pub fn payload_size(&self, pipe: u8) -> u8 {
+        self.register(RX_PW_P0 + pipe)
+    }
+
+    /// Whether a pipe (0-5) is enabled
+    pub fn pipe_enabled(&self, pipe: u8) -> bool {
+        self.register(EN_RXADDR) & (1 << pipe) != 0
+    }
+
+    pub fn is_powered_up(&self) -> bool {
+        self.chip().powered_up()
+    }
+
+    /// Whether the chip is listening (powered up in PRX mode with CE high)
+    pub fn is_listening(&self) -> bool {
+        let chip = self.chip();
+        chip.ce && chip.powered_up() && chip.primary_rx()
+    }
+
+    pub fn irq_asserted(&self) -> bool {
+        self.chip().irq_asserted()
+    }
+
+    pub fn ce_high(&self) -> bool {
+        self.chip().ce
+    }
+
+    pub fn csn_high(&self) -> bool {
+        self.chip().csn
+    }
+
+    pub fn tx_fifo_len(&self) -> usize {
+        self.chip().tx_fifo.len()
+    }
+
+    pub fn rx_fifo_len(&self) -> usize {
+        self.chip().rx_fifo.len()
+    }
+
+    /// Whether payloads are acknowledged by the receiver (true by default).  Unacknowledged
+    /// payloads run out of retransmits (MAX_RT).
+    pub fn set_ack(&self, ack: bool) {
+        self.chip().ack = ack;
+    }
+
+    /// The number of retransmits the receiver needs before it acknowledges a payload (0 by
+    /// default).  Payloads that need more retransmits than SETUP_RETR allows run out of
+    /// retransmits (MAX_RT).
+    pub fn set_retransmits(&self, retransmits: u8) {
+        self.chip().retransmits = retransmits;
+    }
+
+    /// Respond to every acknowledged transmission (the response is received once the chip
+    /// is listening)
+    pub fn set_responder(&self, responder: Option<Responder>) {
+        let mut chip = self.chip();
+        chip.responder = responder;
+        chip.responses.clear();
+    }
+
+    /// Deliver a payload sent to `address`, returning false if the chip wasn't listening on
+    /// that address (or the RX FIFO was full)
+    pub fn receive(&self, address: [u8; 5], payload: &[u8]) -> bool {
+        self.chip().receive(address, payload)
+    }
+
+    /// Take every payload transmitted since the last call
+    pub fn take_transmissions(&self) -> Vec<Transmission> {
+        self.chip().transmissions.drain(..).collect()
+    }
+
+    /// Take every SPI command issued since the last call
+    pub fn take_commands(&self) -> Vec<SpiCommand> {
+        self.chip().commands.drain(..).collect()
+    }
+
+    /// The number of times the SPI bus was used without selecting the chip
+    pub fn spi_errors(&self) -> u64 {
+        self.chip().spi_errors
+    }
+
+    /// The total time the driver asked to be delayed
+    pub fn delayed(&self) -> Duration {
+        self.chip().delayed
+    }
+}
+
+/// SPI bus connected to an emulated nRF24L01+
+pub struct EmulatedSpi {
+    chip: Arc<Mutex<NrfChip>>,
+}
+
+impl Transfer<u8> for EmulatedSpi {
+    type Error = Infallible;
+
+    fn transfer<'w>(&mut self, words: &'w mut [u8]) -> Result<&'w [u8], Self::Error> {
+        self.chip.lock().expect("The emulated nRF24L01+ was poisoned").clock(words);
+        Ok(words)
+    }
+}
+
+impl Write<u8> for EmulatedSpi {
+    type Error = Infallible;
+
+    fn write(&mut self, words: &[u8]) -> Result<(), Self::Error> {
+        let mut words = words.to_vec();
+        self.chip.lock().expect("The emulated nRF24L01+ was poisoned").clock(&mut words);
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum EmulatedPinKind {
+    Csn,
+    Ce,
+}
+
+/// CSN or CE pin connected to an emulated nRF24L01+
+pub struct EmulatedPin {
+    chip: Arc<Mutex<NrfChip>>,
+    pin: EmulatedPinKind,
+}
+
+impl EmulatedPin {
+    fn set(&mut self, high: bool) {
+        let mut chip = self.chip.lock().expect("The emulated nRF24L01+ was poisoned");
+        match self.pin {
+            EmulatedPinKind::Csn => {
+                let selected = chip.csn && !high;
+                let deselected = !chip.csn && high;
+                chip.csn = high;
+                if selected {
+                    chip.transaction = None;
+                } else if deselected {
+                    chip.finish_transaction();
+                }
+            },
+            EmulatedPinKind::Ce => {
+                chip.ce = high;
+                chip.run();
+            },
+        }
+    }
+}
+
+impl OutputPin for EmulatedPin {
+    type Error = Infallible;
+
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.set(false);
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        self.set(true);
+        Ok(())
+    }
+}
+
+/// Delay for drivers of an emulated nRF24L01+ (returns immediately)
+pub struct EmulatedDelay {
+    chip: Arc<Mutex<NrfChip>>,
+}
+
+impl DelayMs<u32> for EmulatedDelay {
+    fn delay_ms(&mut self, ms: u32) {
+        self.chip.lock().expect("The emulated nRF24L01+ was poisoned").delayed += Duration::from_millis(ms as u64);
+    }
+}
+
+impl DelayUs<u32> for EmulatedDelay {
+    fn delay_us(&mut self, us: u32) {
+        self.chip.lock().expect("The emulated nRF24L01+ was poisoned").delayed += Duration::from_micros(us as u64);
+    }
+}
+
+/// IRQ line of an emulated nRF24L01+
+pub struct EmulatedIrq {
+    chip: Arc<Mutex<NrfChip>>,
+}
+
+impl RadioIrq for EmulatedIrq {
+    fn wait(&mut self, timeout: Duration) -> bool {
+        let start = Instant::now();
+        loop {
+            if self.chip.lock().expect("The emulated nRF24L01+ was poisoned").irq_asserted() {
+                return true;
+            }
+            if start.elapsed() >= timeout {
+                return false;
+            }
+            thread::sleep(Duration::from_micros(50));
+        }
+    }
+}
@@ -0,0 +1,55 @@
+//!
+//! nRF24L01+ Registers, Register Bits and SPI Commands
+//!
+
+// Registers
+pub const CONFIG: u8 = 0x00;
+pub const EN_AA: u8 = 0x01;
+pub const EN_RXADDR: u8 = 0x02;
+pub const SETUP_AW: u8 = 0x03;
+pub const SETUP_RETR: u8 = 0x04;
+pub const RF_CH: u8 = 0x05;
+pub const RF_SETUP: u8 = 0x06;
+pub const STATUS: u8 = 0x07;
+pub const OBSERVE_TX: u8 = 0x08;
+pub const RPD: u8 = 0x09;
+pub const RX_ADDR_P0: u8 = 0x0A;
+pub const RX_ADDR_P1: u8 = 0x0B;
+pub const TX_ADDR: u8 = 0x10;
+pub const RX_PW_P0: u8 = 0x11;
+pub const FIFO_STATUS: u8 = 0x17;
+pub const DYNPD: u8 = 0x1C;
+pub const FEATURE: u8 = 0x1D;
+
+// Register Bits
+pub const PRIM_RX: u8 = 1 << 0;
+pub const PWR_UP: u8 = 1 << 1;
+pub const MAX_RT: u8 = 1 << 4;
+pub const TX_DS: u8 = 1 << 5;
+pub const RX_DR: u8 = 1 << 6;
+
+// CONFIG Interrupt Mask Bits (a set bit keeps the matching STATUS flag off the IRQ line)
+pub const MASK_MAX_RT: u8 = 1 << 4;
+pub const MASK_TX_DS: u8 = 1 << 5;
+pub const MASK_RX_DR: u8 = 1 << 6;
+
+// RF_SETUP Bits (RF_DR_LOW selects 250kbps, RF_DR_HIGH selects 2Mbps)
+pub const RF_DR_HIGH: u8 = 1 << 3;
+pub const RF_DR_LOW: u8 = 1 << 5;
+
+// RF_SETUP Power Amplifier Level (0 is the minimum, 3 the maximum)
+pub const RF_PWR: u8 = 0b11 << 1;
+
+// Commands
+pub const R_REGISTER: u8 = 0x00;
+pub const W_REGISTER: u8 = 0x20;
+pub const R_RX_PL_WID: u8 = 0x60;
+pub const R_RX_PAYLOAD: u8 = 0x61;
+pub const W_TX_PAYLOAD: u8 = 0xA0;
+pub const W_ACK_PAYLOAD: u8 = 0xA8;
+pub const W_TX_PAYLOAD_NO_ACK: u8 = 0xB0;
+pub const FLUSH_TX: u8 = 0xE1;
+pub const FLUSH_RX: u8 = 0xE2;
+pub const REUSE_TX_PL: u8 = 0xE3;
+pub const ACTIVATE: u8 = 0x50;
+pub const NOP: u8 = 0xFF;
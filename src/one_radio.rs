@@ -1 +1,3 @@
-pub mod radio_node;
\ No newline at end of file
+pub mod radio_node;
+
+pub mod scrimmage_node;
\ No newline at end of file
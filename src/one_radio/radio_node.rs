@@ -3,158 +3,636 @@
 //! communication for the robots.
 //! 
 
-use std::time::SystemTime;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime};
 use std::sync::Arc;
 
 use ncomm::node::Node;
-use ncomm::publisher_subscriber::{Receive, Publish};
+use ncomm::publisher_subscriber::{Receive, Publish, Subscribe};
 use ncomm::publisher_subscriber::local::{LocalPublisher, LocalSubscriber, MappedLocalSubscriber};
-use ncomm::publisher_subscriber::packed_udp::{MappedPackedUdpSubscriber, PackedUdpPublisher};
 
-use embedded_hal::blocking::{spi::{Transfer, Write}, delay::{DelayMs, DelayUs}};
-use embedded_hal::digital::v2::OutputPin;
-
-use robojackets_robocup_rtp::control_message::{ControlMessage, ControlMessageBuilder, CONTROL_MESSAGE_SIZE};
+use robojackets_robocup_rtp::control_message::{ControlMessage, ControlMessageBuilder};
 use robojackets_robocup_rtp::robot_status_message::RobotStatusMessage;
 use robojackets_robocup_rtp::Team;
-use robojackets_robocup_rtp::{BASE_STATION_ADDRESS, ROBOT_RADIO_ADDRESSES};
-
-use rtic_nrf24l01::Radio;
-
-use crate::publishers::nrf_pubsub::NrfPublisherSubscriber;
-use crate::{BASE_AMPLIFICATION_LEVEL, CHANNEL};
-
-pub struct RadioNode<
-    'a,
-    SPI: Transfer<u8, Error=SPIE> + Write<u8, Error=SPIE>,
-    CSN: OutputPin<Error=GPIOE>,
-    CE: OutputPin<Error=GPIOE>,
-    DELAY: DelayMs<u32> + DelayUs<u32>,
-    SPIE,
-    GPIOE,
-> {
+
+use crate::actor::Actor;
+use crate::alive_robots::AliveRobots;
+use crate::config::{max_protocol_robots, pa_level_name, team_from_bit, RadioConfig, RobotTable};
+use crate::control_api::{ConfigSetting, ControlCommand, ControlServer};
+use crate::field_link::{FieldLinkMonitor, LinkEvent};
+use crate::json_mirror::{JsonMirror, MirrorEvent};
+use crate::latency::{export_path, LatencyTracker};
+use crate::publishers::control_subscriber::ControlMessageSubscriber;
+use crate::publishers::datagram::{DatagramPublisher, MulticastOptions, SequencedRobotStatus};
+use crate::publishers::radio_link::RadioLink;
+use crate::radio_verify::{verify_radio, RegisterMismatch};
+use crate::robot_mapping::RobotIdMap;
+use crate::status_cache::{RobotStatusCache, StatusSnapshotServer};
+use crate::timeout_checker::TimeoutSettings;
+use crate::validation::{ControlMessageValidator, RejectionReason, ValidationLimits};
+
+/// How long to wait for a robot to respond to a Control Message
+const RESPONSE_TIMEOUT: Duration = Duration::from_millis(3);
+
+// Tweak this value, but I think sending a wave of commands every 50 milliseconds is not bad
+const DEFAULT_UPDATE_DELAY_MS: u128 = 50;
+
+pub struct RadioNode<'a, LINK: RadioLink> {
     team: Team,
     num_robots: u8,
-    control_message_subscriber: MappedPackedUdpSubscriber<ControlMessage, u8, 10>,
-    radio_publisher_subscriber: NrfPublisherSubscriber<SPI, CSN, CE, DELAY, SPIE, GPIOE>,
-    robot_status_publisher: PackedUdpPublisher<'a, RobotStatusMessage>,
+    max_robots: usize,
+    radio_config: RadioConfig,
+    radio_mismatches: Vec<RegisterMismatch>,
+    radio_settings_owner: Option<Team>,
+    other_team_statuses: Option<Vec<(RobotStatusMessage, Instant)>>,
+    control_message_subscriber: ControlMessageSubscriber,
+    radio_publisher_subscriber: LINK,
+    robot_status_publisher: Actor<DatagramPublisher<'static>>,
     receive_message_publisher: LocalPublisher<u8>,
-    alive_robots_intra_subscriber: Option<LocalSubscriber<u16>>,
+    alive_robots_intra_subscriber: Option<LocalSubscriber<AliveRobots>>,
+    alive_robots_mirror_subscriber: Option<LocalSubscriber<AliveRobots>>,
+    status_cache: RobotStatusCache,
+    status_snapshot_server: StatusSnapshotServer,
+    control_message_validator: ControlMessageValidator,
+    robot_table: RobotTable,
+    robot_id_map: RobotIdMap,
+    control_server: ControlServer,
+    field_link_monitor: Option<FieldLinkMonitor>,
+    link_event_publisher: LocalPublisher<LinkEvent>,
+    timeout_settings: Option<TimeoutSettings>,
+    timeout_settings_publisher: LocalPublisher<TimeoutSettings>,
+    json_mirror: Option<JsonMirror<'a>>,
+    applied_sequences: HashMap<u8, u32>,
+    echo_sequences: bool,
+    latency_tracker: LatencyTracker,
+    latency_export_path: Option<String>,
+    latency_export_dir: Option<PathBuf>,
+    update_delay_ms: u128,
 }
 
-impl<'a, SPI, CSN, CE, DELAY, SPIE, GPIOE> RadioNode<'a, SPI, CSN, CE, DELAY, SPIE, GPIOE> where
-    SPI: Transfer<u8, Error=SPIE> + Write<u8, Error=SPIE>,
-    CSN: OutputPin<Error=GPIOE>,
-    CE: OutputPin<Error=GPIOE>,
-    DELAY: DelayMs<u32> + DelayUs<u32>
-{
+impl<'a, LINK: RadioLink> RadioNode<'a, LINK> {
+    /// Create the Radio Node of a team.  The radio link has to be initialized already (the
+    /// radio is shared by every team, so it is only initialized once).
     pub fn new(
         team: Team,
         num_robots: u8,
-        ce: CE,
-        csn: CSN,
-        mut spi: SPI,
-        mut delay: DELAY,
+        robot_table: RobotTable,
+        radio_config: RadioConfig,
+        mut radio_link: LINK,
         control_message_bind_address: &'a str,
         robot_status_bind_address: &'a str,
-        robot_status_send_address: &'a str,
+        robot_status_send_addresses: Vec<&'a str>,
+        status_snapshot_bind_address: &'a str,
+        control_api_bind_address: &'a str,
     ) -> Self {
-        let mut radio = Radio::new(ce, csn);
-        if radio.begin(&mut spi, &mut delay).is_err() {
-            panic!("Unable to Initialize the radio");
-        }
-        radio.set_pa_level(BASE_AMPLIFICATION_LEVEL, &mut spi, &mut delay);
-        radio.set_payload_size(CONTROL_MESSAGE_SIZE as u8, &mut spi, &mut delay);
-        radio.set_channel(CHANNEL, &mut spi, &mut delay);
-        radio.open_writing_pipe(ROBOT_RADIO_ADDRESSES[0], &mut spi, &mut delay);
-        radio.open_reading_pipe(1, BASE_STATION_ADDRESS, &mut spi, &mut delay);
-        radio.start_listening(&mut spi, &mut delay);
-        delay.delay_ms(1_000);
-        radio.stop_listening(&mut spi, &mut delay);
-
-        let control_message_subscriber = MappedPackedUdpSubscriber::new(
-            control_message_bind_address,
-            None,
-            Arc::new(|message: &ControlMessage| { *message.robot_id })
-        );
-        let radio_publisher_subscriber = NrfPublisherSubscriber::new(radio, spi, delay);
-        let robot_status_publisher = PackedUdpPublisher::new(
-            robot_status_bind_address,
-            vec![robot_status_send_address],
-        );
+        radio_link.set_robot_table(team, &robot_table);
+
+        let control_message_subscriber = ControlMessageSubscriber::new(control_message_bind_address);
+        // Robot Statuses are sent from their own thread so a slow socket (or hostname
+        // lookup) never holds up the radio
+        let robot_status_bind_address = robot_status_bind_address.to_string();
+        let robot_status_send_addresses = robot_status_send_addresses.iter()
+            .map(|address| address.to_string())
+            .collect();
+        let robot_status_publisher = Actor::spawn("robot status egress", move || {
+            Ok(DatagramPublisher::owned(robot_status_bind_address, robot_status_send_addresses))
+        }).expect("Unable to start the robot status publisher");
         let receive_message_publisher = LocalPublisher::new();
+        let status_snapshot_server = StatusSnapshotServer::new(status_snapshot_bind_address);
+        let control_message_validator = ControlMessageValidator::new(team, num_robots, robot_table.clone(), ValidationLimits::default());
+        let control_server = ControlServer::new(control_api_bind_address);
 
         Self {
-            team: team,
+            team,
             num_robots,
+            max_robots: max_protocol_robots(),
+            radio_config,
+            radio_mismatches: Vec::new(),
+            radio_settings_owner: None,
+            other_team_statuses: None,
             control_message_subscriber,
-            radio_publisher_subscriber,
+            radio_publisher_subscriber: radio_link,
             robot_status_publisher,
             receive_message_publisher,
             alive_robots_intra_subscriber: None,
+            alive_robots_mirror_subscriber: None,
+            status_cache: RobotStatusCache::new(),
+            status_snapshot_server,
+            control_message_validator,
+            robot_table,
+            robot_id_map: RobotIdMap::new(),
+            control_server,
+            field_link_monitor: None,
+            link_event_publisher: LocalPublisher::new(),
+            timeout_settings: None,
+            timeout_settings_publisher: LocalPublisher::new(),
+            json_mirror: None,
+            applied_sequences: HashMap::new(),
+            echo_sequences: false,
+            latency_tracker: LatencyTracker::new(),
+            latency_export_path: None,
+            latency_export_dir: None,
+            update_delay_ms: DEFAULT_UPDATE_DELAY_MS,
         }
     }
 
+    /// The team this node sends Control Messages to
+    pub fn team(&self) -> Team {
+        self.team
+    }
+
+    pub fn radio_config(&self) -> RadioConfig {
+        self.radio_config
+    }
+
+    /// The fields of the radio configuration the radio didn't hold when it was last
+    /// initialized
+    pub fn radio_mismatches(&self) -> &[RegisterMismatch] {
+        &self.radio_mismatches
+    }
+
+    /// Record the fields of the radio configuration the radio didn't hold (see
+    /// `crate::radio_verify::verify_radio`)
+    pub fn set_radio_mismatches(&mut self, radio_mismatches: Vec<RegisterMismatch>) {
+        self.radio_mismatches = radio_mismatches;
+    }
+
+    /// Share the radio with the Radio Node of `owner`, which is the only one whose control
+    /// API can change the radio's settings.  This node's radio configuration follows the
+    /// owner's (see `follow_radio`).
+    pub fn set_radio_settings_owner(&mut self, owner: Team) {
+        self.radio_settings_owner = Some(owner);
+    }
+
+    /// Take on the radio configuration (and its mismatches) of the node that owns the radio
+    pub fn follow_radio(&mut self, radio_config: RadioConfig, radio_mismatches: &[RegisterMismatch]) {
+        self.radio_config = radio_config;
+        if self.radio_mismatches != radio_mismatches {
+            self.radio_mismatches = radio_mismatches.to_vec();
+        }
+    }
+
+    /// Keep the statuses of the other team's robots (which share the radio) so they can be
+    /// handed to the other team's Radio Node (see `take_other_team_statuses`)
+    pub fn keep_other_team_statuses(&mut self) {
+        self.other_team_statuses = Some(Vec::new());
+    }
+
+    /// Every status received from the other team's robots since the last call
+    pub fn take_other_team_statuses(&mut self) -> Vec<(RobotStatusMessage, Instant)> {
+        self.other_team_statuses.as_mut().map(std::mem::take).unwrap_or_default()
+    }
+
     pub fn create_subscriber(&mut self) -> MappedLocalSubscriber<u8, u8> {
         self.receive_message_publisher.create_mapped_subscriber(Arc::new(|data| { *data }))
     }
 
-    pub fn add_alive_robots_intra_publisher(&mut self, publisher: LocalSubscriber<u16>) {
+    /// The latest status received from each robot
+    pub fn status_cache(&self) -> &RobotStatusCache {
+        &self.status_cache
+    }
+
+    pub fn set_validation_limits(&mut self, limits: ValidationLimits) {
+        self.control_message_validator.set_limits(limits);
+    }
+
+    /// The number of control messages rejected (or clamped) for a given reason
+    pub fn rejections(&self, reason: RejectionReason) -> u64 {
+        self.control_message_validator.rejections(reason)
+    }
+
+    pub fn add_alive_robots_intra_publisher(&mut self, publisher: LocalSubscriber<AliveRobots>) {
         self.alive_robots_intra_subscriber = Some(publisher);
     }
 
-    fn send_and_await_response(&mut self, control_message: ControlMessage, robot_id: u8) {
-        // Send Control Message
-        self.radio_publisher_subscriber.send(control_message);
+    /// Set the ttl and interface used when sending robot statuses to multicast groups
+    pub fn set_multicast(&mut self, options: &MulticastOptions) {
+        let options = *options;
+        if let Some(Err(err)) = self.robot_status_publisher.call(move |publisher| publisher.set_multicast(&options)) {
+            println!("Unable to set Robot Status Multicast Options: {:?}", err);
+        }
+    }
+
+    /// Only send robot statuses over a specific network interface (e.g. "eth0")
+    pub fn set_bind_interface(&mut self, interface: &str) {
+        let bind_interface = interface.to_string();
+        if let Some(Err(err)) = self.robot_status_publisher.call(move |publisher| publisher.set_bind_interface(&bind_interface)) {
+            println!("Unable to bind Robot Statuses to {}: {:?}", interface, err);
+        }
+    }
+
+    /// Mirror robot statuses, alive robots and link events as newline-delimited JSON
+    pub fn add_json_mirror(&mut self, json_mirror: JsonMirror<'a>) {
+        self.json_mirror = Some(json_mirror);
+    }
+
+    /// Receive the alive robots from the Timeout Checker so they can be mirrored
+    pub fn add_alive_robots_mirror_subscriber(&mut self, subscriber: LocalSubscriber<AliveRobots>) {
+        self.alive_robots_mirror_subscriber = Some(subscriber);
+    }
+
+    /// Monitor heartbeats from the Field Computer, holding every robot still whenever
+    /// the link is lost
+    pub fn add_field_link_monitor(&mut self, field_link_monitor: FieldLinkMonitor) {
+        self.field_link_monitor = Some(field_link_monitor);
+    }
+
+    pub fn create_link_event_subscriber(&mut self) -> LocalSubscriber<LinkEvent> {
+        self.link_event_publisher.create_subscriber()
+    }
+
+    /// Create a subscriber the Timeout Checker can use to receive changes to its settings
+    /// made over the control API
+    pub fn create_timeout_settings_subscriber(&mut self, initial_settings: TimeoutSettings) -> LocalSubscriber<TimeoutSettings> {
+        self.timeout_settings = Some(initial_settings);
+        self.timeout_settings_publisher.create_subscriber()
+    }
+
+    /// Follow each robot status with the sequence number of the last sequenced Control
+    /// Message sent to the robot.  This changes the robot status datagram, so every
+    /// consumer of the robot status stream has to expect it.
+    pub fn set_echo_sequences(&mut self, echo_sequences: bool) {
+        self.echo_sequences = echo_sequences;
+    }
+
+    /// Set how often (in milliseconds) the executor sends a wave of commands to the robots
+    pub fn set_update_delay(&mut self, update_delay_ms: u128) {
+        self.update_delay_ms = update_delay_ms;
+    }
+
+    /// Set the maximum number of robots that can be put in play over the control API
+    pub fn set_max_robots(&mut self, max_robots: usize) {
+        self.max_robots = max_robots;
+    }
+
+    /// The latency of each robot's commands
+    pub fn latency_tracker(&self) -> &LatencyTracker {
+        &self.latency_tracker
+    }
+
+    /// Write the latency histograms to a CSV file when the node shuts down
+    pub fn set_latency_export(&mut self, path: String) {
+        self.latency_export_path = Some(path);
+    }
+
+    /// Allow `latency export {file name}` over the control API to write into a directory
+    pub fn set_latency_export_dir(&mut self, export_dir: PathBuf) {
+        self.latency_export_dir = Some(export_dir);
+    }
+
+    /// The mapping from logical robot ids to physical robots
+    pub fn robot_id_map(&self) -> &RobotIdMap {
+        &self.robot_id_map
+    }
+
+    /// Apply every command received over the control API
+    fn handle_control_requests(&mut self) {
+        for request in self.control_server.poll() {
+            let response = match request.command {
+                Ok(command) => self.handle_control_command(command),
+                Err(err) => format!("error {}", err),
+            };
+            self.control_server.respond(request.client, &response);
+        }
+    }
 
-        let start_instant = SystemTime::now();
-        while SystemTime::now().duration_since(start_instant).unwrap().as_millis() < 3 {
-            self.radio_publisher_subscriber.update_data();
-            if self.radio_publisher_subscriber.data.len() > 0 {
-                for data in self.radio_publisher_subscriber.data.drain(..) {
-                    self.robot_status_publisher.send(data);
-                    self.receive_message_publisher.send(*data.robot_id);
-                    if *data.robot_id == robot_id {
-                        return;
+    /// Apply a single control API command, returning the response for the client
+    fn handle_control_command(&mut self, command: ControlCommand) -> String {
+        match command {
+            ControlCommand::Get => {
+                let mut config = format!(
+                    "ok channel={} pa_level={} robots={}",
+                    self.radio_config.channel,
+                    pa_level_name(self.radio_config.pa_level),
+                    self.num_robots,
+                );
+                if let Some(timeout_settings) = self.timeout_settings {
+                    config.push_str(&format!(" timeout={}", timeout_settings.timeout));
+                }
+                if let Some(field_link_monitor) = self.field_link_monitor.as_ref() {
+                    config.push_str(&format!(" heartbeat_timeout={}", field_link_monitor.timeout().as_millis()));
+                }
+                if let Some(frame) = self.control_message_subscriber.last_frame() {
+                    config.push_str(&format!(" frame={}", frame));
+                }
+                config
+            },
+            ControlCommand::Set(setting) => match self.apply_setting(setting) {
+                Ok(()) => String::from("ok"),
+                Err(err) => format!("error {}", err),
+            },
+            ControlCommand::Robots => {
+                let robots: Vec<String> = self.robot_table.robots
+                    .iter()
+                    .map(|robot| format!(
+                        "{}:{:02X?}:{}",
+                        robot.id,
+                        robot.radio_address,
+                        if robot.enabled { "enabled" } else { "disabled" },
+                    ))
+                    .collect();
+                format!("ok {}", robots.join(" "))
+            },
+            ControlCommand::Enable { robot_id } => self.set_robot_enabled(robot_id, true),
+            ControlCommand::Disable { robot_id } => self.set_robot_enabled(robot_id, false),
+            ControlCommand::Reinit if self.radio_settings_owner.is_some() => format!("error {}", self.shared_radio_error()),
+            ControlCommand::Reinit => {
+                println!("Re-Initializing the Radio");
+                if !self.radio_publisher_subscriber.initialize(&self.radio_config) {
+                    return String::from("error unable to initialize the radio");
+                }
+                self.radio_mismatches = verify_radio(&mut self.radio_publisher_subscriber, &self.radio_config).unwrap_or_default();
+                if self.radio_mismatches.is_empty() {
+                    String::from("ok")
+                } else {
+                    let mismatches: Vec<String> = self.radio_mismatches.iter().map(ToString::to_string).collect();
+                    format!("error radio configuration mismatch: {}", mismatches.join(", "))
+                }
+            },
+            ControlCommand::Remap { logical_id, physical_id } => {
+                if self.robot_table.get(physical_id).is_none() {
+                    format!("error physical robot {} is not in the robot table", physical_id)
+                } else if physical_id != logical_id && physical_id < self.num_robots && self.robot_id_map.logical(physical_id) == Some(physical_id) {
+                    format!("error physical robot {} is in play as robot {}", physical_id, physical_id)
+                } else {
+                    match self.robot_id_map.set(logical_id, physical_id) {
+                        Ok(change) => format!("ok {}", change),
+                        Err(err) => format!("error {}", err),
                     }
                 }
+            },
+            ControlCommand::Unmap { logical_id } => match self.robot_id_map.reset(logical_id) {
+                Ok(change) => format!("ok {}", change),
+                Err(err) => format!("error {}", err),
+            },
+            ControlCommand::Link => {
+                let mut link = String::from("ok");
+                if let Some(field_link_monitor) = self.field_link_monitor.as_ref() {
+                    link.push_str(&format!(
+                        " connected={} heartbeats={}",
+                        field_link_monitor.is_connected(),
+                        field_link_monitor.heartbeats_received(),
+                    ));
+                }
+                link.push_str(&format!(
+                    " lost_commands={} stale_commands={} duplicate_commands={} stale_batches={}",
+                    self.control_message_subscriber.lost_commands(),
+                    self.control_message_subscriber.stale_commands(),
+                    self.control_message_subscriber.duplicate_commands(),
+                    self.control_message_subscriber.stale_batches(),
+                ));
+                link
+            },
+            ControlCommand::Latency { stage } => format!("ok {} {}", stage.name(), self.latency_tracker.summary(stage)),
+            ControlCommand::LatencyExport { path } => {
+                let Some(export_dir) = self.latency_export_dir.as_ref() else {
+                    return String::from("error latency export is disabled (see --latency-export-dir)");
+                };
+                match export_path(export_dir, &path) {
+                    Ok(path) => match self.latency_tracker.export_csv(&path) {
+                        Ok(()) => String::from("ok"),
+                        Err(err) => format!("error unable to write {}: {}", path.display(), err),
+                    },
+                    Err(err) => format!("error {}", err),
+                }
+            },
+            ControlCommand::LatencyReset => {
+                self.latency_tracker.clear();
+                String::from("ok")
+            },
+            ControlCommand::Mappings => {
+                let mappings: Vec<String> = self.robot_id_map.mappings()
+                    .iter()
+                    .map(|(logical_id, physical_id)| format!("{}->{}", logical_id, physical_id))
+                    .collect();
+                format!("ok {}", mappings.join(" "))
+            },
+        }
+    }
+
+    /// Why a node that doesn't own the radio can't change it
+    fn shared_radio_error(&self) -> String {
+        match self.radio_settings_owner {
+            Some(owner) => format!("the radio is shared, change it through the {:?} team's control API", owner),
+            None => String::from("the radio isn't shared"),
+        }
+    }
+
+    /// Apply a configuration change to the radio, this node or the Timeout Checker
+    fn apply_setting(&mut self, setting: ConfigSetting) -> Result<(), String> {
+        if self.radio_settings_owner.is_some() && matches!(setting, ConfigSetting::Channel(_) | ConfigSetting::PaLevel(_)) {
+            return Err(self.shared_radio_error());
+        }
+
+        match setting {
+            ConfigSetting::Channel(channel) => {
+                let radio_config = RadioConfig { channel, ..self.radio_config };
+                radio_config.validate().map_err(|err| err.to_string())?;
+                self.radio_publisher_subscriber.set_channel(channel);
+                self.radio_config = radio_config;
+                println!("Radio Channel Set to {}", channel);
+            },
+            ConfigSetting::PaLevel(pa_level) => {
+                self.radio_publisher_subscriber.set_pa_level(pa_level);
+                self.radio_config.pa_level = pa_level;
+                println!("Radio PA Level Set to {}", pa_level_name(pa_level));
+            },
+            ConfigSetting::Robots(num_robots) => {
+                if num_robots as usize > self.max_robots {
+                    return Err(format!("at most {} robots are supported", self.max_robots));
+                }
+                self.robot_table.check_robots_in_play(num_robots).map_err(|err| err.to_string())?;
+                self.num_robots = num_robots;
+                self.control_message_validator.set_num_robots(num_robots);
+                if let Some(timeout_settings) = self.timeout_settings.as_mut() {
+                    timeout_settings.num_robots = num_robots;
+                    self.timeout_settings_publisher.send(*timeout_settings);
+                }
+                println!("Number of Robots Set to {}", num_robots);
+            },
+            ConfigSetting::Timeout(timeout) => {
+                let Some(timeout_settings) = self.timeout_settings.as_mut() else {
+                    return Err(String::from("the timeout checker is not running"));
+                };
+                timeout_settings.timeout = timeout;
+                self.timeout_settings_publisher.send(*timeout_settings);
+                println!("Robot Timeout Set to {} ms", timeout);
+            },
+            ConfigSetting::HeartbeatTimeout(heartbeat_timeout) => {
+                let Some(field_link_monitor) = self.field_link_monitor.as_mut() else {
+                    return Err(String::from("heartbeats are not being monitored"));
+                };
+                field_link_monitor.set_timeout(Duration::from_millis(heartbeat_timeout));
+                println!("Heartbeat Timeout Set to {} ms", heartbeat_timeout);
+            },
+        }
+        Ok(())
+    }
+
+    /// Enable or disable a robot in the robot table
+    fn set_robot_enabled(&mut self, robot_id: u8, enabled: bool) -> String {
+        if !self.robot_table.set_enabled(robot_id, enabled) {
+            return format!("error robot {} is not in the robot table", robot_id);
+        }
+        self.radio_publisher_subscriber.set_robot_table(self.team, &self.robot_table);
+        self.control_message_validator.set_robot_table(self.robot_table.clone());
+        println!("Robot {} {}", robot_id, if enabled { "Enabled" } else { "Disabled" });
+        String::from("ok")
+    }
+
+    /// A Control Message that stops the robot in place
+    fn stop_message(&self, robot_id: u8) -> ControlMessage {
+        ControlMessageBuilder::new()
+            .team(self.team)
+            .robot_id(robot_id)
+            .build()
+    }
+
+    /// Receive the latest alive robots from the Timeout Checker and mirror any change
+    fn mirror_alive_robots(&mut self) {
+        let Some(subscriber) = self.alive_robots_mirror_subscriber.as_mut() else { return };
+        let previous_alive_robots = subscriber.data.clone();
+        subscriber.update_data();
+
+        if let (Some(json_mirror), Some(alive_robots)) = (self.json_mirror.as_mut(), subscriber.data.as_ref()) {
+            if previous_alive_robots.as_ref() != Some(alive_robots) {
+                json_mirror.send(MirrorEvent::alive_robots(alive_robots, SystemTime::now()));
             }
         }
     }
+
+    /// Check the link to the Field Computer, returning whether the link is up
+    fn check_field_link(&mut self) -> bool {
+        let Some(field_link_monitor) = self.field_link_monitor.as_mut() else { return true };
+
+        if let Some(link_event) = field_link_monitor.poll() {
+            match link_event {
+                LinkEvent::Lost { .. } => println!("Field Computer Link Lost: Holding All Robots"),
+                LinkEvent::Restored { down_for, .. } => println!("Field Computer Link Restored (Down For {:?})", down_for),
+            }
+            self.link_event_publisher.send(link_event);
+            if let Some(json_mirror) = self.json_mirror.as_mut() {
+                json_mirror.send(MirrorEvent::link_event(&link_event));
+            }
+        }
+
+        field_link_monitor.is_connected()
+    }
+
+    /// Send a Control Message to the physical robot mapped to the logical `robot_id` and
+    /// wait for its response.  `received_at` is when the Control Message was received from
+    /// the Field Computer (None for Control Messages generated by the base station).
+    fn send_and_await_response(&mut self, mut control_message: ControlMessage, robot_id: u8, received_at: Option<Instant>) {
+        // Send Control Message and listen for the response on the radio thread
+        control_message.robot_id = self.robot_id_map.physical(robot_id).into();
+        self.latency_tracker.transmit_started(robot_id, received_at, Instant::now());
+        let exchange = self.radio_publisher_subscriber.exchange(control_message, RESPONSE_TIMEOUT);
+        if let Some(acked_at) = exchange.acked_at {
+            self.latency_tracker.acked(robot_id, acked_at);
+        }
+
+        for (data, received_instant) in exchange.received {
+            self.handle_status(data, received_instant);
+        }
+    }
+
+    /// Publish a status received from a robot at `received_instant`
+    pub fn handle_status(&mut self, mut data: RobotStatusMessage, received_instant: Instant) {
+        // In a scrimmage the other team's robots share the radio, their statuses are handed
+        // to the other team's Radio Node
+        if team_from_bit(data.team) != self.team {
+            if let Some(other_team_statuses) = self.other_team_statuses.as_mut() {
+                other_team_statuses.push((data, received_instant));
+            }
+            return;
+        }
+
+        // Report the status under the logical robot id
+        match self.robot_id_map.logical(*data.robot_id) {
+            Some(logical_id) => data.robot_id = logical_id.into(),
+            None => return,
+        }
+
+        self.latency_tracker.status_received(*data.robot_id, received_instant);
+        let received_at = SystemTime::now() - received_instant.elapsed();
+        self.status_cache.update_at(data, received_at);
+        match self.applied_sequences.get(&*data.robot_id).copied().filter(|_| self.echo_sequences) {
+            Some(sequence) => self.robot_status_publisher.cast(move |publisher| publisher.send(SequencedRobotStatus { status: data, sequence })),
+            None => self.robot_status_publisher.cast(move |publisher| publisher.send(data)),
+        };
+        if let Some(json_mirror) = self.json_mirror.as_mut() {
+            json_mirror.send(MirrorEvent::robot_status(&data, received_at));
+        }
+        self.receive_message_publisher.send(*data.robot_id);
+    }
 }
 
-impl<'a, SPI, CSN, CE, DELAY, SPIE, GPIOE> Node for RadioNode<'a, SPI, CSN, CE, DELAY, SPIE, GPIOE> where
-    SPI: Transfer<u8, Error=SPIE> + Write<u8, Error=SPIE>,
-    CSN: OutputPin<Error=GPIOE>,
-    CE: OutputPin<Error=GPIOE>,
-    DELAY: DelayMs<u32> + DelayUs<u32>
-{
+impl<'a, LINK: RadioLink + Send> Node for RadioNode<'a, LINK> {
     fn name(&self) -> String { String::from("CPU --> Base Station --> Radio --> Base Station --> CPU")}
 
-    // Tweak this value, but I think sending a wave of commands every 50 milliseconds is not bad
-    fn get_update_delay(&self) -> u128 { 50u128 }
+    fn get_update_delay(&self) -> u128 { self.update_delay_ms }
 
     fn start(&mut self) { }
 
     fn update(&mut self) {
         self.control_message_subscriber.update_data();
+        self.mirror_alive_robots();
+        self.status_snapshot_server.poll(&self.status_cache);
+        self.handle_control_requests();
+
+        // Without the Field Computer, stop every robot and forget any stale commands
+        if !self.check_field_link() {
+            self.control_message_subscriber.data.clear();
+            for robot_id in 0..self.num_robots {
+                let stop_message = self.stop_message(robot_id);
+                self.send_and_await_response(stop_message, robot_id, None);
+            }
+            return;
+        }
+
+        // Drop any commands for robots that are not in play
+        let num_robots = self.num_robots;
+        let invalid_robots: Vec<u8> = self.control_message_subscriber.data.keys()
+            .filter(|robot_id| **robot_id >= num_robots)
+            .copied()
+            .collect();
+        for robot_id in invalid_robots {
+            self.control_message_subscriber.data.remove(&robot_id);
+            self.control_message_validator.count(RejectionReason::InvalidRobotId);
+            println!("Rejected Control Message for Robot {}: {:?}", robot_id, RejectionReason::InvalidRobotId);
+        }
 
         // For each robot, send them a control message and wait for a response
         for robot_id in 0..self.num_robots {
             if let Some(control_message) = self.control_message_subscriber.data.get(&robot_id) {
+                let physical_id = self.robot_id_map.physical(robot_id);
+                let control_message = match self.control_message_validator.validate_mapped(*control_message, physical_id) {
+                    Ok(control_message) => control_message,
+                    Err(reason) => {
+                        println!("Rejected Control Message for Robot {}: {:?}", robot_id, reason);
+                        self.control_message_subscriber.data.remove(&robot_id);
+                        continue;
+                    },
+                };
+                // Keep the clamped message so it is only counted once (not every cycle)
+                self.control_message_subscriber.data.insert(robot_id, control_message);
                 if *control_message.body_y != 0 {
                     println!("{:?}", control_message);
                 }
-                self.send_and_await_response(*control_message, robot_id);
+                // An unsequenced command replacing a sequenced one has no sequence to echo
+                match self.control_message_subscriber.sequence(robot_id) {
+                    Some(sequence) => self.applied_sequences.insert(robot_id, sequence),
+                    None => self.applied_sequences.remove(&robot_id),
+                };
+                let received_at = self.control_message_subscriber.received_at(robot_id);
+                self.send_and_await_response(control_message, robot_id, received_at);
             } else if let Some(subscriber) = self.alive_robots_intra_subscriber.as_ref() {
                 // The robot might be considered dead, but we should still check in with him.
-                if let Some(alive_robots) = subscriber.data {
-                    if alive_robots & (1 << robot_id) == 0 {
-                        let blank_control_message = ControlMessageBuilder::new()
-                            .team(self.team)
-                            .build();
-
-                        self.send_and_await_response(blank_control_message, robot_id);
+                if let Some(alive_robots) = subscriber.data.as_ref() {
+                    if !alive_robots.is_alive(robot_id) {
+                        let blank_control_message = self.stop_message(robot_id);
+                        self.send_and_await_response(blank_control_message, robot_id, None);
                     }
                 }
             }
@@ -162,7 +640,14 @@ impl<'a, SPI, CSN, CE, DELAY, SPIE, GPIOE> Node for RadioNode<'a, SPI, CSN, CE,
     }
 
     // TODO: Possibly Reset Radio Status on Shutdown (?)
-    fn shutdown(&mut self) {}
+    fn shutdown(&mut self) {
+        if let Some(path) = self.latency_export_path.as_ref() {
+            match self.latency_tracker.export_csv(path) {
+                Ok(()) => println!("Latency Histograms Written to {}", path),
+                Err(err) => println!("Unable to Write Latency Histograms to {}: {:?}", path, err),
+            }
+        }
+    }
 
     fn debug(&self) -> String { self.name() }
 }
@@ -0,0 +1,83 @@
+//!
+//! The Scrimmage Node serves both of our teams from one base station for practice matches
+//! between our own blue and yellow robots.  Each team has its own Radio Node (with its own
+//! ports to the Field Computer and robot table) and both share the radio, so every radio
+//! cycle sends one team's commands and then the other's.
+//!
+//! The radio's settings (channel, PA level and re-initializing) belong to the first team's
+//! control API, the second team's Radio Node follows them.  Statuses from one team's robots
+//! received while sending the other team's commands are handed to the right Radio Node.
+//!
+
+use ncomm::node::Node;
+
+use crate::one_radio::radio_node::RadioNode;
+use crate::publishers::radio_link::RadioLink;
+
+pub struct ScrimmageNode<'a, LINK: RadioLink> {
+    radio_nodes: [RadioNode<'a, LINK>; 2],
+}
+
+impl<'a, LINK: RadioLink> ScrimmageNode<'a, LINK> {
+    /// Run two Radio Nodes (one per team) in the same radio cycle
+    pub fn new(mut first: RadioNode<'a, LINK>, mut second: RadioNode<'a, LINK>) -> Self {
+        if first.team() == second.team() {
+            panic!("Both Radio Nodes of a scrimmage are sending to the same team");
+        }
+
+        second.set_radio_settings_owner(first.team());
+        second.follow_radio(first.radio_config(), first.radio_mismatches());
+        first.keep_other_team_statuses();
+        second.keep_other_team_statuses();
+
+        Self {
+            radio_nodes: [first, second],
+        }
+    }
+
+    pub fn radio_nodes(&self) -> &[RadioNode<'a, LINK>] {
+        &self.radio_nodes
+    }
+
+    /// Hand the statuses each Radio Node received for the other team to the other Radio
+    /// Node and keep the second team's radio configuration in step with the first's
+    fn share_radio(&mut self) {
+        let [first, second] = &mut self.radio_nodes;
+        for (status, received_instant) in first.take_other_team_statuses() {
+            second.handle_status(status, received_instant);
+        }
+        for (status, received_instant) in second.take_other_team_statuses() {
+            first.handle_status(status, received_instant);
+        }
+        second.follow_radio(first.radio_config(), first.radio_mismatches());
+    }
+}
+
+impl<'a, LINK: RadioLink + Send> Node for ScrimmageNode<'a, LINK> {
+    fn name(&self) -> String { String::from("Scrimmage: CPU --> Base Station --> Radio --> Base Station --> CPU") }
+
+    fn get_update_delay(&self) -> u128 {
+        self.radio_nodes.iter().map(|radio_node| radio_node.get_update_delay()).min().unwrap_or_default()
+    }
+
+    fn start(&mut self) {
+        for radio_node in self.radio_nodes.iter_mut() {
+            radio_node.start();
+        }
+    }
+
+    fn update(&mut self) {
+        for radio_node in self.radio_nodes.iter_mut() {
+            radio_node.update();
+        }
+        self.share_radio();
+    }
+
+    fn shutdown(&mut self) {
+        for radio_node in self.radio_nodes.iter_mut() {
+            radio_node.shutdown();
+        }
+    }
+
+    fn debug(&self) -> String { self.name() }
+}
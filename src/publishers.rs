@@ -1 +1,8 @@
-pub mod nrf_pubsub;
\ No newline at end of file
+pub mod nrf_pubsub;
+pub mod datagram;
+pub mod control_subscriber;
+pub mod radio_link;
+pub mod virtual_radio;
+pub mod faulty_link;
+pub mod radio_irq;
+pub mod radio_thread;
\ No newline at end of file
@@ -0,0 +1,183 @@
+//!
+//! UDP Subscriber for Control Messages from the Field Computer
+//!
+//! Accepts single Control Messages (one datagram per robot), sequenced Control Messages
+//! and batches of Control Messages (see `crate::control_batch`).  A batch replaces every
+//! Control Message received before it so a radio cycle never mixes the commands of two
+//! frames.  Sequenced Control Messages that are older than (or duplicates of) the last
+//! command accepted for their robot are dropped.
+//!
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::time::Instant;
+
+use ncomm::publisher_subscriber::Receive;
+
+use packed_struct::PackedStructSlice;
+
+use robojackets_robocup_rtp::control_message::{ControlMessage, CONTROL_MESSAGE_SIZE};
+
+use crate::control_batch::{ControlBatch, SequencedControlMessage};
+use crate::publishers::datagram::DatagramIngress;
+
+/// Batches (and sequenced Control Messages) more than this many frames behind the latest
+/// are assumed to come from a restarted Field Computer instead of being stale
+pub const MAX_FRAME_REORDER: u32 = 64;
+
+/// Whether `frame` is at or before `last_frame` (accounting for wrap around)
+fn is_stale(last_frame: Option<u32>, frame: u32) -> bool {
+    match last_frame {
+        Some(last_frame) => last_frame.wrapping_sub(frame) <= MAX_FRAME_REORDER,
+        None => false,
+    }
+}
+
+/// Receives the latest Control Message for each robot
+pub struct ControlMessageSubscriber {
+    ingress: DatagramIngress,
+    pub data: HashMap<u8, ControlMessage>,
+    received_at: HashMap<u8, Instant>,
+    last_frame: Option<u32>,
+    batches_received: u64,
+    stale_batches: u64,
+    last_sequences: HashMap<u8, u32>,
+    data_sequences: HashMap<u8, u32>,
+    lost_commands: u64,
+    stale_commands: u64,
+    duplicate_commands: u64,
+}
+
+impl ControlMessageSubscriber {
+    pub fn new(bind_address: &str) -> Self {
+        let ingress = DatagramIngress::bind(bind_address)
+            .expect("Unable to bind the control message socket");
+
+        Self {
+            ingress,
+            data: HashMap::new(),
+            received_at: HashMap::new(),
+            last_frame: None,
+            batches_received: 0,
+            stale_batches: 0,
+            last_sequences: HashMap::new(),
+            data_sequences: HashMap::new(),
+            lost_commands: 0,
+            stale_commands: 0,
+            duplicate_commands: 0,
+        }
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.ingress.local_addr()
+    }
+
+    /// The frame of the latest batch applied
+    pub fn last_frame(&self) -> Option<u32> {
+        self.last_frame
+    }
+
+    pub fn batches_received(&self) -> u64 {
+        self.batches_received
+    }
+
+    /// The number of batches dropped because a newer frame had already been applied
+    pub fn stale_batches(&self) -> u64 {
+        self.stale_batches
+    }
+
+    /// When the current Control Message for a robot was received
+    pub fn received_at(&self, robot_id: u8) -> Option<Instant> {
+        self.received_at.get(&robot_id).copied()
+    }
+
+    /// The sequence number of the current Control Message for a robot (if it was sequenced)
+    pub fn sequence(&self, robot_id: u8) -> Option<u32> {
+        self.data_sequences.get(&robot_id).copied()
+    }
+
+    /// The number of sequenced Control Messages that never arrived (gaps in the sequence)
+    pub fn lost_commands(&self) -> u64 {
+        self.lost_commands
+    }
+
+    /// The number of sequenced Control Messages dropped because they arrived out of order
+    pub fn stale_commands(&self) -> u64 {
+        self.stale_commands
+    }
+
+    /// The number of sequenced Control Messages dropped because they were duplicates
+    pub fn duplicate_commands(&self) -> u64 {
+        self.duplicate_commands
+    }
+
+    fn apply_control_message(&mut self, control_message: ControlMessage, received_at: Instant) {
+        self.data_sequences.remove(&*control_message.robot_id);
+        self.received_at.insert(*control_message.robot_id, received_at);
+        self.data.insert(*control_message.robot_id, control_message);
+    }
+
+    fn apply_sequenced(&mut self, sequenced: SequencedControlMessage, received_at: Instant) {
+        let robot_id = *sequenced.control_message.robot_id;
+        let last_sequence = self.last_sequences.get(&robot_id).copied();
+        if last_sequence == Some(sequenced.sequence) {
+            self.duplicate_commands += 1;
+            return;
+        } else if is_stale(last_sequence, sequenced.sequence) {
+            self.stale_commands += 1;
+            return;
+        }
+
+        if let Some(last_sequence) = last_sequence {
+            let ahead = sequenced.sequence.wrapping_sub(last_sequence);
+            if ahead <= u32::MAX / 2 {
+                self.lost_commands += (ahead - 1) as u64;
+            }
+        }
+
+        self.last_sequences.insert(robot_id, sequenced.sequence);
+        self.data.insert(robot_id, sequenced.control_message);
+        self.data_sequences.insert(robot_id, sequenced.sequence);
+        self.received_at.insert(robot_id, received_at);
+    }
+
+    fn apply_batch(&mut self, batch: ControlBatch, received_at: Instant) {
+        self.batches_received += 1;
+        if is_stale(self.last_frame, batch.frame) {
+            self.stale_batches += 1;
+            return;
+        }
+
+        self.last_frame = Some(batch.frame);
+        self.data.clear();
+        self.data_sequences.clear();
+        self.received_at.clear();
+        for control_message in batch.control_messages {
+            self.received_at.insert(*control_message.robot_id, received_at);
+            self.data.insert(*control_message.robot_id, control_message);
+        }
+    }
+}
+
+impl Receive for ControlMessageSubscriber {
+    fn update_data(&mut self) {
+        while let Some((datagram, received_at)) = self.ingress.try_recv() {
+            if datagram.len() == CONTROL_MESSAGE_SIZE {
+                match ControlMessage::unpack_from_slice(&datagram) {
+                    Ok(control_message) => self.apply_control_message(control_message, received_at),
+                    Err(err) => println!("Unable to unpack Control Message: {:?}", err),
+                }
+            } else if SequencedControlMessage::is_sequenced(&datagram) {
+                match SequencedControlMessage::decode(&datagram) {
+                    Some(sequenced) => self.apply_sequenced(sequenced, received_at),
+                    None => println!("Unable to unpack Sequenced Control Message"),
+                }
+            } else {
+                match ControlBatch::decode(&datagram) {
+                    Some(batch) => self.apply_batch(batch, received_at),
+                    None => println!("Received Invalid Control Datagram ({} bytes)", datagram.len()),
+                }
+            }
+        }
+    }
+}
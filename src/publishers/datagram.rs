@@ -0,0 +1,441 @@
+//!
+//! UDP Publisher for messages whose encoded size isn't known at compile time
+//!
+//! Datagrams can be sent to any number of destinations, including UDP multicast groups
+//! (e.g. 239.0.0.1:8001), so multiple clients (the field computer, a logger, a dashboard)
+//! can receive the same stream.  Destinations can be hostnames, IPv4 or IPv6 addresses.
+//!
+//! Hostnames are resolved on a background thread so a slow or unreachable DNS server
+//! never holds up a send.  Datagrams to a hostname that hasn't been resolved yet are
+//! counted as send errors.
+//!
+//! Datagrams from the Field Computer are received on their own thread (`DatagramIngress`)
+//! and timestamped as they arrive.
+//!
+
+use std::borrow::Cow;
+use std::io::{self, ErrorKind};
+use std::net::{Ipv4Addr, SocketAddr, UdpSocket};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use socket2::SockRef;
+
+use ncomm::publisher_subscriber::Publish;
+
+use packed_struct::PackedStruct;
+
+use robojackets_robocup_rtp::robot_status_message::RobotStatusMessage;
+
+use crate::net_address::{bind_to_interface, resolve, usable_address, DEFAULT_RESOLVE_INTERVAL};
+use crate::net_errors::{is_network_down, ErrorLog, RebindingSocket, REBIND_INTERVAL};
+
+/// Data that can be encoded into a single UDP datagram
+pub trait Datagram {
+    fn to_datagram(&self) -> Vec<u8>;
+}
+
+impl Datagram for Vec<u8> {
+    fn to_datagram(&self) -> Vec<u8> {
+        self.clone()
+    }
+}
+
+impl Datagram for RobotStatusMessage {
+    fn to_datagram(&self) -> Vec<u8> {
+        self.pack().expect("Unable to pack Robot Status Message").to_vec()
+    }
+}
+
+/// A Robot Status Message followed by the sequence number of the last sequenced Control
+/// Message sent to the robot:
+///
+/// [packed RobotStatusMessage][sequence: u32 (big endian)]
+#[derive(Clone, Copy, Debug)]
+pub struct SequencedRobotStatus {
+    pub status: RobotStatusMessage,
+    pub sequence: u32,
+}
+
+impl Datagram for SequencedRobotStatus {
+    fn to_datagram(&self) -> Vec<u8> {
+        let mut datagram = self.status.to_datagram();
+        datagram.extend_from_slice(&self.sequence.to_be_bytes());
+        datagram
+    }
+}
+
+/// Options for datagrams sent to multicast groups
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MulticastOptions {
+    /// The number of hops multicast datagrams can take (1 keeps them on the local network)
+    pub ttl: u32,
+    /// The interface multicast datagrams are sent from (the system default when None)
+    pub interface: Option<Ipv4Addr>,
+}
+
+impl Default for MulticastOptions {
+    fn default() -> Self {
+        Self {
+            ttl: 1,
+            interface: None,
+        }
+    }
+}
+
+/// A destination and the socket addresses it last resolved to
+struct Destination<'a> {
+    address: Cow<'a, str>,
+    resolved: Vec<SocketAddr>,
+}
+
+/// The result of resolving every destination (in order) on the background thread
+type Resolution = Vec<io::Result<Vec<SocketAddr>>>;
+
+/// Bind a non-blocking UDP socket
+pub fn bind_socket(bind_address: &str) -> io::Result<UdpSocket> {
+    let socket = UdpSocket::bind(bind_address)?;
+    socket.set_nonblocking(true)?;
+    Ok(socket)
+}
+
+/// Publishes variable length datagrams to a list of addresses.  Addresses can be given
+/// as hostnames (which are re-resolved periodically), IPv4 or IPv6 addresses.
+///
+/// Sends never block and never fail loudly: errors are counted and logged at most every
+/// few seconds.  If the bind address isn't available yet (or the network goes away) the
+/// socket is bound again (with its interface and multicast options) once it comes back.
+pub struct DatagramPublisher<'a> {
+    bind_address: Cow<'a, str>,
+    socket: Option<UdpSocket>,
+    bind_interface: Option<String>,
+    multicast: Option<MulticastOptions>,
+    destinations: Vec<Destination<'a>>,
+    addresses: Vec<Cow<'a, str>>,
+    resolve_interval: Duration,
+    last_resolved: Option<Instant>,
+    resolving: Option<Receiver<Resolution>>,
+    resolve_errors: ErrorLog,
+    needs_rebind: bool,
+    last_bind_attempt: Instant,
+    rebinds: u64,
+    send_errors: ErrorLog,
+}
+
+impl<'a> DatagramPublisher<'a> {
+    pub fn new(bind_address: &'a str, addresses: Vec<&'a str>) -> Self {
+        Self::from_cow(Cow::Borrowed(bind_address), addresses.into_iter().map(Cow::Borrowed).collect())
+    }
+
+    /// Create a publisher that owns its addresses (e.g. one that lives on another thread)
+    pub fn owned(bind_address: String, addresses: Vec<String>) -> Self {
+        Self::from_cow(Cow::Owned(bind_address), addresses.into_iter().map(Cow::Owned).collect())
+    }
+
+    fn from_cow(bind_address: Cow<'a, str>, addresses: Vec<Cow<'a, str>>) -> Self {
+        let mut send_errors = ErrorLog::new(&format!("Unable to send datagrams from {}", bind_address));
+        let socket = match bind_socket(&bind_address) {
+            Ok(socket) => Some(socket),
+            Err(err) if is_network_down(&err) => {
+                send_errors.record(format!("{:?} (binding again once the network is up)", err));
+                None
+            },
+            Err(err) => panic!("Unable to bind the datagram publisher socket: {:?}", err),
+        };
+        // IP addresses don't need to be looked up so they can be sent to straight away
+        let destinations = addresses.iter()
+            .map(|address| Destination {
+                address: address.clone(),
+                resolved: address.parse::<SocketAddr>().map(|address| vec![address]).unwrap_or_default(),
+            })
+            .collect();
+
+        let resolve_errors = ErrorLog::new(&format!("Unable to resolve the destinations of {}", bind_address));
+        Self {
+            bind_address,
+            needs_rebind: socket.is_none(),
+            socket,
+            bind_interface: None,
+            multicast: None,
+            destinations,
+            addresses,
+            resolve_interval: DEFAULT_RESOLVE_INTERVAL,
+            last_resolved: None,
+            resolving: None,
+            resolve_errors,
+            last_bind_attempt: Instant::now(),
+            rebinds: 0,
+            send_errors,
+        }
+    }
+
+    /// The destinations every datagram is sent to
+    pub fn addresses(&self) -> &[Cow<'a, str>] {
+        &self.addresses
+    }
+
+    /// The address the socket is bound to (None while the network is down)
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.socket.as_ref().and_then(|socket| socket.local_addr().ok())
+    }
+
+    /// The number of datagrams that couldn't be sent (once per destination)
+    pub fn send_errors(&self) -> u64 {
+        self.send_errors.count()
+    }
+
+    /// The number of times the socket was bound again after the network went away
+    pub fn rebinds(&self) -> u64 {
+        self.rebinds
+    }
+
+    /// Set how often destinations are re-resolved
+    pub fn set_resolve_interval(&mut self, resolve_interval: Duration) {
+        self.resolve_interval = resolve_interval;
+    }
+
+    /// Only send datagrams over a specific network interface (e.g. "eth0")
+    pub fn set_bind_interface(&mut self, interface: &str) -> io::Result<()> {
+        self.bind_interface = Some(interface.to_string());
+        match self.socket.as_ref() {
+            Some(socket) => bind_to_interface(socket, interface),
+            None => Ok(()),
+        }
+    }
+
+    /// Set the ttl and interface used for datagrams sent to multicast groups
+    pub fn set_multicast(&mut self, options: &MulticastOptions) -> io::Result<()> {
+        self.multicast = Some(*options);
+        match self.socket.as_ref() {
+            Some(socket) => apply_multicast(socket, options),
+            None => Ok(()),
+        }
+    }
+
+    /// Start re-resolving every destination on a background thread (unless a resolution is
+    /// already running).  The new addresses are used once `poll_resolve` receives them.
+    pub fn resolve(&mut self) {
+        if self.resolving.is_some() {
+            return;
+        }
+        self.last_resolved = Some(Instant::now());
+
+        let addresses: Vec<String> = self.destinations.iter()
+            .map(|destination| destination.address.to_string())
+            .collect();
+        let (resolution_tx, resolution_rx) = mpsc::channel();
+        let spawned = thread::Builder::new()
+            .name(String::from("resolve"))
+            .spawn(move || {
+                let resolution: Resolution = addresses.iter().map(|address| resolve(address)).collect();
+                let _ = resolution_tx.send(resolution);
+            });
+        match spawned {
+            Ok(_) => self.resolving = Some(resolution_rx),
+            Err(err) => {
+                self.resolve_errors.record(format!("unable to spawn the resolver thread: {:?}", err));
+            },
+        }
+    }
+
+    /// Use the addresses from a finished resolution, keeping the previous addresses of any
+    /// destination that couldn't be resolved.  Returns whether a resolution is still running.
+    pub fn poll_resolve(&mut self) -> bool {
+        let Some(resolving) = self.resolving.as_ref() else { return false };
+        let resolution = match resolving.try_recv() {
+            Ok(resolution) => resolution,
+            Err(TryRecvError::Empty) => return true,
+            Err(TryRecvError::Disconnected) => {
+                self.resolving = None;
+                return false;
+            },
+        };
+        self.resolving = None;
+
+        for (destination, resolved) in self.destinations.iter_mut().zip(resolution) {
+            match resolved {
+                Ok(resolved) if !resolved.is_empty() => destination.resolved = resolved,
+                Ok(_) => {
+                    self.resolve_errors.record(format!("{}: no addresses found", destination.address));
+                },
+                Err(err) => {
+                    self.resolve_errors.record(format!("{}: {:?}", destination.address, err));
+                },
+            }
+        }
+        false
+    }
+
+    /// Bind the socket again (at most every REBIND_INTERVAL) after the network went away
+    fn rebind(&mut self) {
+        if self.last_bind_attempt.elapsed() < REBIND_INTERVAL {
+            return;
+        }
+        self.last_bind_attempt = Instant::now();
+
+        // Drop the old socket first so its port can be bound again
+        self.socket = None;
+        let socket = match bind_socket(&self.bind_address) {
+            Ok(socket) => socket,
+            Err(err) => {
+                self.send_errors.record(format!("Unable to bind {}: {:?}", self.bind_address, err));
+                return;
+            },
+        };
+
+        if let Some(interface) = self.bind_interface.as_ref() {
+            if let Err(err) = bind_to_interface(&socket, interface) {
+                self.send_errors.record(format!("Unable to bind to {}: {:?}", interface, err));
+                return;
+            }
+        }
+        if let Some(options) = self.multicast.as_ref() {
+            if let Err(err) = apply_multicast(&socket, options) {
+                println!("Unable to set multicast options on {}: {:?}", self.bind_address, err);
+            }
+        }
+
+        println!("Datagram publisher bound to {} again", self.bind_address);
+        self.socket = Some(socket);
+        self.needs_rebind = false;
+        self.rebinds += 1;
+        // The destinations may have moved while the network was down
+        self.last_resolved = None;
+    }
+}
+
+/// Apply multicast options to a socket
+fn apply_multicast(socket: &UdpSocket, options: &MulticastOptions) -> io::Result<()> {
+    if socket.local_addr()?.is_ipv6() {
+        return SockRef::from(socket).set_multicast_hops_v6(options.ttl);
+    }
+
+    socket.set_multicast_ttl_v4(options.ttl)?;
+    if let Some(interface) = options.interface.as_ref() {
+        SockRef::from(socket).set_multicast_if_v4(interface)?;
+    }
+    Ok(())
+}
+
+impl<'a, Data: Datagram + Send + Clone> Publish<Data> for DatagramPublisher<'a> {
+    fn send(&mut self, data: Data) {
+        if self.needs_rebind {
+            self.rebind();
+        }
+        let Some(local_address) = self.local_addr() else {
+            self.send_errors.record("the network is down");
+            return;
+        };
+
+        let resolve_due = self.last_resolved
+            .map(|last_resolved| last_resolved.elapsed() >= self.resolve_interval)
+            .unwrap_or(true);
+        self.poll_resolve();
+        if resolve_due {
+            self.resolve();
+        }
+
+        let datagram = data.to_datagram();
+        let Some(socket) = self.socket.as_ref() else {
+            return;
+        };
+        for destination in self.destinations.iter() {
+            let Some(address) = usable_address(&destination.resolved, &local_address) else {
+                self.send_errors.record(format!("no reachable address for {}", destination.address));
+                continue;
+            };
+            if let Err(err) = socket.send_to(&datagram, address) {
+                if is_network_down(&err) {
+                    self.needs_rebind = true;
+                }
+                self.send_errors.record(format!("{}: {:?}", destination.address, err));
+            }
+        }
+    }
+}
+
+/// How often a blocked ingress thread checks whether it should stop
+const INGRESS_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Receives datagrams on a dedicated thread so they are timestamped when they arrive (not
+/// when the node reading them gets around to it)
+pub struct DatagramIngress {
+    local_addr: SocketAddr,
+    datagrams: Receiver<(Vec<u8>, Instant)>,
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+/// Bind a UDP socket for an ingress thread
+fn bind_ingress_socket(bind_address: &str) -> io::Result<UdpSocket> {
+    let socket = UdpSocket::bind(bind_address)?;
+    socket.set_read_timeout(Some(INGRESS_POLL_INTERVAL))?;
+    Ok(socket)
+}
+
+impl DatagramIngress {
+    /// Receive datagrams sent to `bind_address`.  If the network isn't up yet the socket is
+    /// bound by the ingress thread once it is (and again whenever the network goes away).
+    pub fn bind(bind_address: &str) -> io::Result<Self> {
+        let context = format!("Datagram ingress error on {}", bind_address);
+        let mut ingress_socket = RebindingSocket::new(bind_address, bind_ingress_socket, &context)?;
+        let local_addr = match ingress_socket.bound() {
+            Some(socket) => socket.local_addr()?,
+            None => bind_address.parse()
+                .map_err(|_| io::Error::new(ErrorKind::AddrNotAvailable, format!("unable to bind {}", bind_address)))?,
+        };
+
+        let (datagrams_tx, datagrams_rx) = mpsc::channel();
+        let stop = Arc::new(AtomicBool::new(false));
+        let stop_ingress = stop.clone();
+        let handle = thread::Builder::new()
+            .name(format!("ingress {}", local_addr))
+            .spawn(move || {
+                let mut buffer = [0u8; 4096];
+                while !stop_ingress.load(Ordering::Relaxed) {
+                    let Some(socket) = ingress_socket.socket() else {
+                        thread::sleep(INGRESS_POLL_INTERVAL);
+                        continue;
+                    };
+                    match socket.recv(&mut buffer) {
+                        Ok(size) => if datagrams_tx.send((buffer[..size].to_vec(), Instant::now())).is_err() {
+                            return;
+                        },
+                        Err(err) if matches!(err.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => (),
+                        Err(err) => {
+                            ingress_socket.record_error("unable to receive", &err);
+                            thread::sleep(INGRESS_POLL_INTERVAL);
+                        },
+                    }
+                }
+            })?;
+
+        Ok(Self {
+            local_addr,
+            datagrams: datagrams_rx,
+            stop,
+            handle: Some(handle),
+        })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// The next datagram received (and when it arrived) if there is one
+    pub fn try_recv(&self) -> Option<(Vec<u8>, Instant)> {
+        self.datagrams.try_recv().ok()
+    }
+}
+
+impl Drop for DatagramIngress {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
@@ -0,0 +1,390 @@
+//!
+//! Fault Injection for Simulated Radio Links
+//!
+//! Wraps any RadioLink (normally the `VirtualRadio`) and injects faults into the Control
+//! Messages sent through it so the timeout checker and retry logic can be tested under
+//! adverse conditions.  Faults are described by a seeded scenario so every run of a
+//! scenario injects exactly the same faults.  Scenarios can be loaded from a TOML file of
+//! the form:
+//!
+//! ```toml
+//! seed = 42
+//!
+//! # Each phase applies from `start` (seconds since the link was created) until the next phase
+//! [[phases]]
+//! start = 0.0
+//! drop_probability = 0.05
+//! burst_probability = 0.01
+//! burst_length = 10
+//! latency_ms = 1.0
+//! latency_jitter_ms = 0.5
+//! corrupt_probability = 0.01
+//! ack_loss_probability = 0.05
+//!
+//! # Robot 2 stops responding 5 seconds in for 3 seconds
+//! [[silences]]
+//! robot_id = 2
+//! start = 5.0
+//! duration = 3.0
+//! ```
+//!
+
+use std::fs;
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use ncomm::publisher_subscriber::{Publish, Receive};
+
+use packed_struct::{PackedStruct, PackedStructSlice};
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use serde::Deserialize;
+
+use robojackets_robocup_rtp::{ControlMessage, RobotStatusMessage, Team, CONTROL_MESSAGE_SIZE};
+
+use rtic_nrf24l01::config::power_amplifier::PowerAmplifier;
+
+use crate::config::{ConfigError, RadioConfig, RobotTable};
+use crate::publishers::radio_link::RadioLink;
+use crate::radio_auth::ControlSigner;
+use crate::radio_verify::RadioRegisters;
+
+/// The faults injected during one phase of a scenario
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct FaultPhase {
+    /// When the phase starts (in seconds since the link was created)
+    pub start: f64,
+    /// The probability that a Control Message is dropped
+    pub drop_probability: f64,
+    /// The probability that a burst of Control Messages starts being dropped
+    pub burst_probability: f64,
+    /// The number of Control Messages dropped in a burst
+    pub burst_length: u32,
+    /// Extra latency added to every Control Message (in milliseconds)
+    pub latency_ms: f64,
+    /// Random latency of up to this many milliseconds added on top of `latency_ms`
+    pub latency_jitter_ms: f64,
+    /// The probability that a bit of a Control Message is flipped
+    pub corrupt_probability: f64,
+    /// The probability that a Control Message is delivered but its ack is lost
+    pub ack_loss_probability: f64,
+}
+
+/// A robot that stops responding for a while
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+pub struct RobotSilence {
+    pub robot_id: u8,
+    /// When the robot goes silent (in seconds since the link was created)
+    pub start: f64,
+    /// How long the robot stays silent (in seconds)
+    pub duration: f64,
+}
+
+impl RobotSilence {
+    fn contains(&self, robot_id: u8, elapsed: f64) -> bool {
+        self.robot_id == robot_id && elapsed >= self.start && elapsed < self.start + self.duration
+    }
+}
+
+/// A scripted, seeded set of faults
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct FaultScenario {
+    /// The seed of the random faults (a random seed is picked and logged when missing)
+    pub seed: Option<u64>,
+    pub phases: Vec<FaultPhase>,
+    pub silences: Vec<RobotSilence>,
+}
+
+impl FaultScenario {
+    /// A scenario that applies the same faults for its entire duration
+    pub fn constant(seed: u64, phase: FaultPhase) -> Self {
+        Self {
+            seed: Some(seed),
+            phases: vec![FaultPhase { start: 0.0, ..phase }],
+            silences: Vec::new(),
+        }
+    }
+
+    /// Load a scenario from a TOML file
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, ConfigError> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    /// Parse a scenario from a TOML string
+    pub fn parse(scenario: &str) -> Result<Self, ConfigError> {
+        let mut scenario: FaultScenario = toml::from_str(scenario)?;
+        scenario.validate()?;
+        scenario.phases.sort_by(|a, b| a.start.total_cmp(&b.start));
+        Ok(scenario)
+    }
+
+    /// Check that every probability is between 0 and 1 and every time is positive
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        for phase in self.phases.iter() {
+            let probabilities = [
+                ("drop_probability", phase.drop_probability),
+                ("burst_probability", phase.burst_probability),
+                ("corrupt_probability", phase.corrupt_probability),
+                ("ack_loss_probability", phase.ack_loss_probability),
+            ];
+            for (name, probability) in probabilities {
+                if !(0.0..=1.0).contains(&probability) {
+                    return Err(ConfigError::InvalidFaultScenario(format!("{} must be between 0 and 1 (got {})", name, probability)));
+                }
+            }
+
+            let times = [("start", phase.start), ("latency_ms", phase.latency_ms), ("latency_jitter_ms", phase.latency_jitter_ms)];
+            for (name, time) in times {
+                if !time.is_finite() || time < 0.0 {
+                    return Err(ConfigError::InvalidFaultScenario(format!("{} must be positive (got {})", name, time)));
+                }
+            }
+        }
+
+        for silence in self.silences.iter() {
+            if !silence.start.is_finite() || silence.start < 0.0 || !silence.duration.is_finite() || silence.duration < 0.0 {
+                return Err(ConfigError::InvalidFaultScenario(format!("the silence of robot {} must have a positive start and duration", silence.robot_id)));
+            }
+        }
+        Ok(())
+    }
+
+    /// The phase in effect `elapsed` seconds into the scenario
+    pub fn phase_at(&self, elapsed: f64) -> FaultPhase {
+        self.phases.iter()
+            .rev()
+            .find(|phase| phase.start <= elapsed)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Whether a robot is silent `elapsed` seconds into the scenario
+    pub fn is_silent(&self, robot_id: u8, elapsed: f64) -> bool {
+        self.silences.iter().any(|silence| silence.contains(robot_id, elapsed))
+    }
+}
+
+/// The number of faults injected by a Faulty Link
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FaultStats {
+    /// Control Messages sent through the link
+    pub sent: u64,
+    /// Control Messages dropped at random
+    pub dropped: u64,
+    /// Control Messages dropped in a burst
+    pub burst_dropped: u64,
+    /// Control Messages with a flipped bit
+    pub corrupted: u64,
+    /// Control Messages delivered whose ack was lost
+    pub acks_lost: u64,
+    /// Control Messages (and Robot Status Messages) dropped because the robot was silent
+    pub silenced: u64,
+}
+
+/// Radio Link that injects faults into another Radio Link
+pub struct FaultyLink<LINK: RadioLink> {
+    link: LINK,
+    scenario: FaultScenario,
+    seed: u64,
+    rng: StdRng,
+    start: Instant,
+    burst_remaining: u32,
+    send_status: bool,
+    stats: FaultStats,
+}
+
+impl<LINK: RadioLink> FaultyLink<LINK> {
+    pub fn new(link: LINK, scenario: FaultScenario) -> Self {
+        let seed = match scenario.seed {
+            Some(seed) => seed,
+            None => {
+                let seed = rand::random();
+                println!("Fault Scenario Seed: {}", seed);
+                seed
+            },
+        };
+
+        Self {
+            link,
+            scenario,
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+            start: Instant::now(),
+            burst_remaining: 0,
+            send_status: true,
+            stats: FaultStats::default(),
+        }
+    }
+
+    /// The seed of the random faults
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn stats(&self) -> FaultStats {
+        self.stats
+    }
+
+    pub fn link(&self) -> &LINK {
+        &self.link
+    }
+
+    /// The number of seconds since the scenario started
+    pub fn elapsed(&self) -> f64 {
+        self.start.elapsed().as_secs_f64()
+    }
+
+    /// Restart the scenario from the beginning (with the same seed)
+    pub fn restart(&mut self) {
+        self.rng = StdRng::seed_from_u64(self.seed);
+        self.start = Instant::now();
+        self.burst_remaining = 0;
+        self.stats = FaultStats::default();
+    }
+
+    /// Whether the next Control Message is lost (at random or in a burst)
+    fn lose_message(&mut self, phase: &FaultPhase) -> bool {
+        if self.burst_remaining > 0 {
+            self.burst_remaining -= 1;
+            self.stats.burst_dropped += 1;
+            return true;
+        }
+
+        if phase.burst_length > 0 && self.rng.gen_bool(phase.burst_probability) {
+            self.burst_remaining = phase.burst_length - 1;
+            self.stats.burst_dropped += 1;
+            return true;
+        }
+
+        if self.rng.gen_bool(phase.drop_probability) {
+            self.stats.dropped += 1;
+            return true;
+        }
+        false
+    }
+
+    /// Flip a bit of a Control Message (None if it no longer unpacks)
+    fn corrupt(control_message: ControlMessage, bit: usize) -> Option<ControlMessage> {
+        let mut packed = control_message.pack().ok()?;
+        packed[bit / 8] ^= 1 << (bit % 8);
+        ControlMessage::unpack_from_slice(&packed).ok()
+    }
+}
+
+impl<LINK: RadioLink> RadioLink for FaultyLink<LINK> {
+    fn initialize(&mut self, radio_config: &RadioConfig) -> bool {
+        self.link.initialize(radio_config)
+    }
+
+    fn set_channel(&mut self, channel: u8) {
+        self.link.set_channel(channel);
+    }
+
+    fn set_pa_level(&mut self, pa_level: PowerAmplifier) {
+        self.link.set_pa_level(pa_level);
+    }
+
+    fn set_robot_table(&mut self, team: Team, robot_table: &RobotTable) {
+        self.link.set_robot_table(team, robot_table);
+    }
+
+    fn set_signer(&mut self, signer: Option<ControlSigner>) {
+        self.link.set_signer(signer);
+    }
+
+    fn read_registers(&mut self) -> Option<RadioRegisters> {
+        self.link.read_registers()
+    }
+
+    fn corrupt_next_payload(&mut self, bit: usize) -> bool {
+        self.link.corrupt_next_payload(bit)
+    }
+
+    fn send_status(&self) -> bool {
+        self.send_status
+    }
+
+    fn acked_at(&self) -> Option<Instant> {
+        if self.send_status {
+            self.link.acked_at()
+        } else {
+            None
+        }
+    }
+
+    fn take_received(&mut self) -> Vec<RobotStatusMessage> {
+        let elapsed = self.elapsed();
+        let received = self.link.take_received();
+        let before = received.len();
+        let received: Vec<RobotStatusMessage> = received.into_iter()
+            .filter(|status| !self.scenario.is_silent(*status.robot_id, elapsed))
+            .collect();
+        self.stats.silenced += (before - received.len()) as u64;
+        received
+    }
+
+    fn wait_for_data(&mut self, timeout: Duration) -> bool {
+        self.link.wait_for_data(timeout)
+    }
+}
+
+impl<LINK: RadioLink> Publish<ControlMessage> for FaultyLink<LINK> {
+    fn send(&mut self, data: ControlMessage) {
+        self.stats.sent += 1;
+        self.send_status = false;
+
+        let elapsed = self.elapsed();
+        if self.scenario.is_silent(*data.robot_id, elapsed) {
+            self.stats.silenced += 1;
+            return;
+        }
+
+        let phase = self.scenario.phase_at(elapsed);
+        let jitter = if phase.latency_jitter_ms > 0.0 {
+            self.rng.gen_range(0.0..phase.latency_jitter_ms)
+        } else {
+            0.0
+        };
+        let latency = Duration::from_secs_f64((phase.latency_ms + jitter) / 1000.0);
+        if !latency.is_zero() {
+            thread::sleep(latency);
+        }
+
+        if self.lose_message(&phase) {
+            return;
+        }
+
+        let mut data = data;
+        if self.rng.gen_bool(phase.corrupt_probability) {
+            self.stats.corrupted += 1;
+            // The payload is corrupted on the wire (after it is signed) so authenticated
+            // Control Messages fail verification, falling back to corrupting the Control
+            // Message for links that can't
+            let bit = self.rng.gen_range(0..CONTROL_MESSAGE_SIZE * 8);
+            if !self.link.corrupt_next_payload(bit) {
+                match Self::corrupt(data, bit) {
+                    Some(corrupted) => data = corrupted,
+                    None => return,
+                }
+            }
+        }
+
+        self.link.send(data);
+        self.send_status = self.link.send_status();
+        if self.send_status && self.rng.gen_bool(phase.ack_loss_probability) {
+            self.stats.acks_lost += 1;
+            self.send_status = false;
+        }
+    }
+}
+
+impl<LINK: RadioLink> Receive for FaultyLink<LINK> {
+    fn update_data(&mut self) {
+        self.link.update_data();
+    }
+}
@@ -1,8 +1,12 @@
 //!
 //! nRF24L01+ Radio Publisher and Receiver
+//!
+//! When the radio's IRQ line is connected (see `set_irq`) the publisher subscriber waits
+//! on it for received Robot Status Messages instead of polling the radio over SPI.
 //! 
 
-use std::marker::{Send, PhantomData};
+use std::marker::PhantomData;
+use std::time::{Duration, Instant};
 
 use ncomm::publisher_subscriber::{Publish, Receive};
 
@@ -17,7 +21,21 @@ use embedded_hal::blocking::delay::{DelayMs, DelayUs};
 
 use robojackets_robocup_rtp::{ControlMessage, CONTROL_MESSAGE_SIZE};
 use robojackets_robocup_rtp::{RobotStatusMessage, ROBOT_STATUS_SIZE};
-use robojackets_robocup_rtp::{ROBOT_RADIO_ADDRESSES};
+use robojackets_robocup_rtp::{Team, TEAM};
+
+use rtic_nrf24l01::config::power_amplifier::PowerAmplifier;
+
+use crate::config::{team_from_bit, RadioConfig, RobotTable};
+use crate::publishers::radio_irq::RadioIrq;
+use crate::publishers::radio_link::{RadioLink, RobotAddresses};
+use crate::radio_auth::ControlSigner;
+use crate::radio_verify::RadioRegisters;
+
+/// How long the radio listens after it is first initialized (milliseconds)
+const STARTUP_SETTLE_DELAY_MS: u32 = 1_000;
+/// How long the radio listens after it is re-initialized (milliseconds).  The chip only
+/// needs 1.5ms to power up, so the radio loop isn't held up for the full startup delay.
+const REINIT_SETTLE_DELAY_MS: u32 = 5;
 
 pub struct NrfPublisherSubscriber<
     SPI: Transfer<u8, Error=SPIE> + Write<u8, Error=SPIE>,
@@ -30,7 +48,12 @@ pub struct NrfPublisherSubscriber<
     radio: Radio<CE, CSN, SPI, DELAY, GPIOE, SPIE>,
     spi: SPI,
     delay: DELAY,
+    irq: Option<Box<dyn RadioIrq>>,
+    initialized: bool,
+    signer: Option<ControlSigner>,
+    robot_addresses: RobotAddresses,
     pub send_status: bool,
+    acked_at: Option<Instant>,
     pub data: Vec<RobotStatusMessage>,
     phantom: PhantomData<ControlMessage>,
 }
@@ -41,14 +64,94 @@ impl<SPI, CSN, CE, DELAY, SPIE, GPIOE> NrfPublisherSubscriber<SPI, CSN, CE, DELA
     CE: OutputPin<Error=GPIOE>,
     DELAY: DelayMs<u32> + DelayUs<u32>,
 {
-    pub fn new(radio: Radio<CE, CSN, SPI, DELAY, GPIOE, SPIE>, spi: SPI, delay: DELAY) -> Self {
-        Self {
+    pub fn new(radio: Radio<CE, CSN, SPI, DELAY, GPIOE, SPIE>, spi: SPI, delay: DELAY, robot_table: &RobotTable) -> Self {
+        let mut nrf_publisher_subscriber = Self {
             radio,
             spi,
             delay,
+            irq: None,
+            initialized: false,
+            signer: None,
+            robot_addresses: RobotAddresses::default(),
             send_status: true,
+            acked_at: None,
             data: Vec::new(),
             phantom: PhantomData,
+        };
+        nrf_publisher_subscriber.set_robot_table(TEAM, robot_table);
+        nrf_publisher_subscriber
+    }
+
+    /// Wait on the radio's IRQ line for incoming data instead of polling the radio
+    pub fn set_irq(&mut self, irq: Box<dyn RadioIrq>) {
+        self.irq = Some(irq);
+    }
+}
+
+impl<SPI, CSN, CE, DELAY, SPIE, GPIOE> RadioLink for NrfPublisherSubscriber<SPI, CSN, CE, DELAY, SPIE, GPIOE> where
+    SPI: Transfer<u8, Error=SPIE> + Write<u8, Error=SPIE>,
+    CSN: OutputPin<Error=GPIOE>,
+    CE: OutputPin<Error=GPIOE>,
+    DELAY: DelayMs<u32> + DelayUs<u32>,
+{
+    fn initialize(&mut self, radio_config: &RadioConfig) -> bool {
+        if self.radio.begin(&mut self.spi, &mut self.delay).is_err() {
+            return false;
+        }
+        self.radio.set_pa_level(radio_config.pa_level, &mut self.spi, &mut self.delay);
+        self.radio.set_payload_size(CONTROL_MESSAGE_SIZE as u8, &mut self.spi, &mut self.delay);
+        self.radio.set_channel(radio_config.channel, &mut self.spi, &mut self.delay);
+        if let Some(address) = self.robot_addresses.first() {
+            self.radio.open_writing_pipe(address, &mut self.spi, &mut self.delay);
+        }
+        self.radio.open_reading_pipe(1, radio_config.base_station_address, &mut self.spi, &mut self.delay);
+        self.radio.start_listening(&mut self.spi, &mut self.delay);
+        if self.initialized {
+            self.delay.delay_ms(REINIT_SETTLE_DELAY_MS);
+        } else {
+            self.delay.delay_ms(STARTUP_SETTLE_DELAY_MS);
+        }
+        self.radio.stop_listening(&mut self.spi, &mut self.delay);
+        self.initialized = true;
+        true
+    }
+
+    fn set_channel(&mut self, channel: u8) {
+        self.radio.set_channel(channel, &mut self.spi, &mut self.delay);
+    }
+
+    fn set_pa_level(&mut self, pa_level: PowerAmplifier) {
+        self.radio.set_pa_level(pa_level, &mut self.spi, &mut self.delay);
+    }
+
+    fn set_robot_table(&mut self, team: Team, robot_table: &RobotTable) {
+        self.robot_addresses.set_team(team, robot_table);
+    }
+
+    fn set_signer(&mut self, signer: Option<ControlSigner>) {
+        self.signer = signer;
+    }
+
+    fn read_registers(&mut self) -> Option<RadioRegisters> {
+        Some(RadioRegisters::from_registers(self.radio.get_registers(&mut self.spi, &mut self.delay)))
+    }
+
+    fn send_status(&self) -> bool {
+        self.send_status
+    }
+
+    fn acked_at(&self) -> Option<Instant> {
+        self.acked_at
+    }
+
+    fn take_received(&mut self) -> Vec<RobotStatusMessage> {
+        self.data.drain(..).collect()
+    }
+
+    fn wait_for_data(&mut self, timeout: Duration) -> bool {
+        match self.irq.as_mut() {
+            Some(irq) => irq.wait(timeout),
+            None => true,
         }
     }
 }
@@ -61,19 +164,33 @@ impl<SPI, CSN, CE, DELAY, SPIE, GPIOE> Publish<ControlMessage> for NrfPublisherS
 {
     fn send(&mut self, data: ControlMessage) {
         let target_robot = *data.robot_id;
+        let target_address = match self.robot_addresses.get(team_from_bit(data.team), target_robot) {
+            Some(address) => address,
+            None => {
+                println!("No Radio Address for Robot {}", target_robot);
+                self.send_status = false;
+                self.acked_at = None;
+                return;
+            },
+        };
 
-        let packed_data = match data.pack() {
-            Ok(bytes) => bytes,
-            Err(err) => panic!("Unable to Pack Data: {:?}", err),
+        let packed_data = match self.signer.as_mut() {
+            Some(signer) => signer.sign(&data).to_vec(),
+            None => match data.pack() {
+                Ok(bytes) => bytes.to_vec(),
+                Err(err) => panic!("Unable to Pack Data: {:?}", err),
+            },
         };
 
         // Configure Radio
         self.radio.stop_listening(&mut self.spi, &mut self.delay);
-        self.radio.set_payload_size(CONTROL_MESSAGE_SIZE as u8, &mut self.spi, &mut self.delay);
-        self.radio.open_writing_pipe(ROBOT_RADIO_ADDRESSES[target_robot as usize], &mut self.spi, &mut self.delay);
+        self.radio.set_payload_size(packed_data.len() as u8, &mut self.spi, &mut self.delay);
+        self.radio.open_writing_pipe(target_address, &mut self.spi, &mut self.delay);
 
-        // Send Data
+        // Send Data (the driver polls STATUS until the ack arrives, so the ack is stamped as
+        // soon as the write returns, before the radio is set up to listen again)
         self.send_status = self.radio.write(&packed_data, &mut self.spi, &mut self.delay);
+        self.acked_at = self.send_status.then(Instant::now);
 
         // Get Ready For Listening
         self.radio.start_listening(&mut self.spi, &mut self.delay);
@@ -98,10 +215,3 @@ impl<SPI, CSN, CE, DELAY, SPIE, GPIOE> Receive for NrfPublisherSubscriber<SPI, C
         }
     }
 }
-
-unsafe impl<SPI, CSN, CE, DELAY, SPIE, GPIOE> Send for NrfPublisherSubscriber<SPI, CSN, CE, DELAY, SPIE, GPIOE> where
-    SPI: Transfer<u8, Error=SPIE> + Write<u8, Error=SPIE>,
-    CSN: OutputPin<Error=GPIOE>,
-    CE: OutputPin<Error=GPIOE>,
-    DELAY: DelayMs<u32> + DelayUs<u32>
-{}
\ No newline at end of file
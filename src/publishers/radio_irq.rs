@@ -0,0 +1,49 @@
+//!
+//! nRF24L01+ Interrupt Line
+//!
+//! The nRF24L01+ holds its IRQ line low while any unmasked interrupt flag in STATUS
+//! (TX_DS, MAX_RT or RX_DR) is set.  The driver polls STATUS itself while sending (and
+//! clears TX_DS and MAX_RT before returning), so the line is only waited on for RX_DR: it
+//! lets the base station sleep until a Robot Status Message is received instead of polling
+//! STATUS over SPI.
+//!
+
+use std::time::Duration;
+
+use rppal::gpio::{InputPin, Trigger};
+
+pub trait RadioIrq: Send {
+    /// Wait at most `timeout` for the IRQ line to be asserted, returning whether it was
+    fn wait(&mut self, timeout: Duration) -> bool;
+}
+
+/// IRQ line connected to a GPIO pin of the Raspberry Pi
+pub struct GpioIrq {
+    pin: InputPin,
+}
+
+impl GpioIrq {
+    /// Watch the pin for falling edges (IRQ is active low)
+    pub fn new(mut pin: InputPin) -> rppal::gpio::Result<Self> {
+        pin.set_interrupt(Trigger::FallingEdge)?;
+        Ok(Self { pin })
+    }
+}
+
+impl RadioIrq for GpioIrq {
+    fn wait(&mut self, timeout: Duration) -> bool {
+        // IRQ stays low until the flags are cleared so an interrupt raised before waiting
+        // started doesn't produce an edge
+        if self.pin.is_low() {
+            return true;
+        }
+
+        match self.pin.poll_interrupt(true, Some(timeout)) {
+            Ok(level) => level.is_some(),
+            Err(err) => {
+                println!("Unable to poll the radio IRQ: {:?}", err);
+                false
+            },
+        }
+    }
+}
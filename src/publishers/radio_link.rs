@@ -0,0 +1,139 @@
+//!
+//! The link between the base station and the robots
+//!
+//! The Radio Node sends Control Messages and receives Robot Status Messages through a
+//! RadioLink so it can run on the nRF24L01+ (`NrfPublisherSubscriber`) or against
+//! simulated robots (`VirtualRadio`).  In the base station the link lives on the radio
+//! thread (see `RadioThread`) so links don't have to be Send.
+//!
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use ncomm::publisher_subscriber::{Publish, Receive};
+
+use robojackets_robocup_rtp::{ControlMessage, RobotStatusMessage, Team};
+
+use rtic_nrf24l01::config::power_amplifier::PowerAmplifier;
+
+use crate::config::{RadioConfig, RobotTable};
+use crate::radio_auth::ControlSigner;
+use crate::radio_verify::RadioRegisters;
+
+/// The radio address of every enabled robot of each team
+#[derive(Clone, Debug, Default)]
+pub struct RobotAddresses {
+    addresses: HashMap<(u8, u8), [u8; 5]>,
+}
+
+impl RobotAddresses {
+    /// Replace the addresses of one team's robots with the enabled robots in the table
+    pub fn set_team(&mut self, team: Team, robot_table: &RobotTable) {
+        self.addresses.retain(|(robot_team, _), _| *robot_team != team as u8);
+        self.addresses.extend(robot_table.robots.iter()
+            .filter(|robot| robot.enabled)
+            .map(|robot| ((team as u8, robot.id), robot.radio_address)));
+    }
+
+    pub fn get(&self, team: Team, robot_id: u8) -> Option<[u8; 5]> {
+        self.addresses.get(&(team as u8, robot_id)).copied()
+    }
+
+    /// The address of the robot with the lowest id
+    pub fn first(&self) -> Option<[u8; 5]> {
+        self.addresses.iter()
+            .min_by_key(|((team, robot_id), _)| (*robot_id, *team))
+            .map(|(_, address)| *address)
+    }
+}
+
+/// The result of sending a Control Message and listening for the robot's response
+#[derive(Clone, Default)]
+pub struct RadioExchange {
+    /// When the robot acknowledged the Control Message (None if it didn't)
+    pub acked_at: Option<Instant>,
+    /// Every Robot Status Message received while listening and when it was received
+    pub received: Vec<(RobotStatusMessage, Instant)>,
+}
+
+impl RadioExchange {
+    pub fn acked(&self) -> bool {
+        self.acked_at.is_some()
+    }
+}
+
+pub trait RadioLink: Publish<ControlMessage> + Receive {
+    /// Initialize the radio and apply the radio configuration, returning false if the
+    /// radio couldn't be initialized
+    fn initialize(&mut self, radio_config: &RadioConfig) -> bool;
+
+    fn set_channel(&mut self, channel: u8);
+
+    fn set_pa_level(&mut self, pa_level: PowerAmplifier);
+
+    /// Update the radio addresses of a team's robots (disabled robots have no address)
+    fn set_robot_table(&mut self, team: Team, robot_table: &RobotTable);
+
+    /// Sign every Control Message sent from now on (None sends them unauthenticated)
+    fn set_signer(&mut self, signer: Option<ControlSigner>);
+
+    /// Read back the registers holding the radio's configuration (None if the link has no
+    /// registers or they can't be read)
+    fn read_registers(&mut self) -> Option<RadioRegisters> {
+        None
+    }
+
+    /// Flip bit `bit` (modulo the size of the payload) of the next payload sent, after it is
+    /// signed, as interference would on the air.  Returns false if the link can't corrupt
+    /// its payloads.
+    fn corrupt_next_payload(&mut self, _bit: usize) -> bool {
+        false
+    }
+
+    /// Whether the last Control Message sent was acknowledged by the robot
+    fn send_status(&self) -> bool;
+
+    /// When the last Control Message sent was acknowledged by the robot (None if it wasn't
+    /// or the link doesn't record it, in which case the ack is stamped when `send` returns)
+    fn acked_at(&self) -> Option<Instant> {
+        None
+    }
+
+    /// Take every Robot Status Message received since the last call
+    fn take_received(&mut self) -> Vec<RobotStatusMessage>;
+
+    /// Wait at most `timeout` for the link to have something to receive, returning false
+    /// if nothing arrived.  Links that can't wait return true immediately so the caller
+    /// keeps polling `update_data`.
+    fn wait_for_data(&mut self, _timeout: Duration) -> bool {
+        true
+    }
+
+    /// Send a Control Message and listen for at most `timeout` for Robot Status Messages,
+    /// stopping early once the robot the Control Message was sent to responds
+    fn exchange(&mut self, control_message: ControlMessage, timeout: Duration) -> RadioExchange {
+        let mut exchange = RadioExchange::default();
+        self.send(control_message);
+        if self.send_status() {
+            exchange.acked_at = Some(self.acked_at().unwrap_or_else(Instant::now));
+        }
+
+        let robot_id = *control_message.robot_id;
+        let team = control_message.team;
+        let start = Instant::now();
+        while let Some(remaining) = timeout.checked_sub(start.elapsed()) {
+            // The radio is still read once when the wait times out so a missed edge on the
+            // IRQ line doesn't lose the robot's status
+            let data_ready = self.wait_for_data(remaining);
+            self.update_data();
+            let received_at = Instant::now();
+            let received = self.take_received();
+            let responded = received.iter().any(|status| *status.robot_id == robot_id && status.team == team);
+            exchange.received.extend(received.into_iter().map(|status| (status, received_at)));
+            if responded || !data_ready {
+                break;
+            }
+        }
+        exchange
+    }
+}
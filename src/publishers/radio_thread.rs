@@ -0,0 +1,123 @@
+//!
+//! Radio Thread
+//!
+//! Owns the radio link on a dedicated thread (see `crate::actor`).  The Radio Node talks
+//! to it through a `RadioThread`, which forwards every call over a channel, so the radio
+//! hardware never has to move between threads.  Sending a Control Message and listening
+//! for the response (`exchange`) runs entirely on the radio thread so nothing the Radio
+//! Node does with its sockets can stretch the radio's timing.
+//!
+//! A `RadioThread` can be cloned to share the radio between Radio Nodes (one per team in
+//! a scrimmage, see `crate::one_radio::scrimmage_node`).  The radio thread runs until the
+//! last handle is dropped.
+//!
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use ncomm::publisher_subscriber::{Publish, Receive};
+
+use robojackets_robocup_rtp::{ControlMessage, RobotStatusMessage, Team};
+
+use rtic_nrf24l01::config::power_amplifier::PowerAmplifier;
+
+use crate::actor::Actor;
+use crate::config::{RadioConfig, RobotTable};
+use crate::publishers::radio_link::{RadioExchange, RadioLink};
+use crate::radio_auth::ControlSigner;
+use crate::radio_verify::RadioRegisters;
+
+/// Handle to a radio link running on the radio thread
+#[derive(Clone)]
+pub struct RadioThread {
+    actor: Arc<Actor<Box<dyn RadioLink>>>,
+    send_status: bool,
+    data: Vec<RobotStatusMessage>,
+}
+
+impl RadioThread {
+    /// Open the radio link on the radio thread, returning the error from `open` if the
+    /// link couldn't be opened
+    pub fn spawn<F>(open: F) -> Result<Self, String>
+    where
+        F: FnOnce() -> Result<Box<dyn RadioLink>, String> + Send + 'static,
+    {
+        Ok(Self {
+            actor: Arc::new(Actor::spawn("radio", open)?),
+            send_status: true,
+            data: Vec::new(),
+        })
+    }
+}
+
+impl RadioLink for RadioThread {
+    fn initialize(&mut self, radio_config: &RadioConfig) -> bool {
+        let radio_config = *radio_config;
+        self.actor.call(move |link| link.initialize(&radio_config)).unwrap_or(false)
+    }
+
+    fn set_channel(&mut self, channel: u8) {
+        self.actor.cast(move |link| link.set_channel(channel));
+    }
+
+    fn set_pa_level(&mut self, pa_level: PowerAmplifier) {
+        self.actor.cast(move |link| link.set_pa_level(pa_level));
+    }
+
+    fn set_robot_table(&mut self, team: Team, robot_table: &RobotTable) {
+        let robot_table = robot_table.clone();
+        self.actor.cast(move |link| link.set_robot_table(team, &robot_table));
+    }
+
+    fn set_signer(&mut self, signer: Option<ControlSigner>) {
+        self.actor.cast(move |link| link.set_signer(signer));
+    }
+
+    fn read_registers(&mut self) -> Option<RadioRegisters> {
+        self.actor.call(|link| link.read_registers()).flatten()
+    }
+
+    fn corrupt_next_payload(&mut self, bit: usize) -> bool {
+        self.actor.call(move |link| link.corrupt_next_payload(bit)).unwrap_or(false)
+    }
+
+    fn send_status(&self) -> bool {
+        self.send_status
+    }
+
+    fn take_received(&mut self) -> Vec<RobotStatusMessage> {
+        self.data.drain(..).collect()
+    }
+
+    fn wait_for_data(&mut self, timeout: Duration) -> bool {
+        self.actor.call(move |link| link.wait_for_data(timeout)).unwrap_or(false)
+    }
+
+    fn exchange(&mut self, control_message: ControlMessage, timeout: Duration) -> RadioExchange {
+        let exchange = self.actor.call(move |link| link.exchange(control_message, timeout))
+            .unwrap_or_default();
+        self.send_status = exchange.acked();
+        exchange
+    }
+}
+
+impl Publish<ControlMessage> for RadioThread {
+    fn send(&mut self, data: ControlMessage) {
+        self.send_status = self.actor.call(move |link| {
+            link.send(data);
+            link.send_status()
+        }).unwrap_or(false);
+    }
+}
+
+impl Receive for RadioThread {
+    fn update_data(&mut self) {
+        let received = self.actor.call(|link| {
+            link.update_data();
+            link.take_received()
+        });
+        if let Some(received) = received {
+            self.data.extend(received);
+        }
+    }
+}
@@ -0,0 +1,340 @@
+//!
+//! Virtual Radio Link over Local Sockets
+//!
+//! Carries raw nRF24L01+ payloads between the base station and simulated robots (see the
+//! `robot-sim` binary) over UDP or Unix datagram sockets so the whole base station can run
+//! on a development machine without a radio.
+//!
+//! Each datagram is a single frame:
+//!
+//! [channel: u8][address: 5 bytes][payload]
+//!
+//! Control Messages are sent to the robot's radio address, Robot Status Messages are sent
+//! to the base station address and an empty payload sent back to the robot's address is an
+//! acknowledgement (standing in for the nRF24L01+ auto acknowledgement).
+//!
+//! Addresses are either `host:port` (UDP) or `unix:/path/to/socket` (Unix datagram sockets).
+//!
+
+use std::io::{self, ErrorKind};
+use std::net::UdpSocket;
+#[cfg(unix)]
+use std::os::unix::net::UnixDatagram;
+#[cfg(unix)]
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use ncomm::publisher_subscriber::{Publish, Receive};
+
+use packed_struct::{PackedStruct, PackedStructSlice};
+
+use robojackets_robocup_rtp::{ControlMessage, RobotStatusMessage, Team, TEAM};
+use robojackets_robocup_rtp::{BASE_STATION_ADDRESS, ROBOT_STATUS_SIZE};
+
+use rtic_nrf24l01::config::power_amplifier::PowerAmplifier;
+
+use crate::config::{team_from_bit, RadioConfig, RobotTable};
+use crate::publishers::radio_link::{RadioLink, RobotAddresses};
+use crate::radio_auth::ControlSigner;
+
+/// The size of the frame header (channel and address)
+pub const VIRTUAL_FRAME_HEADER_SIZE: usize = 6;
+
+/// How long the Virtual Radio waits for a simulated robot to acknowledge a Control Message
+pub const DEFAULT_ACK_TIMEOUT: Duration = Duration::from_millis(2);
+
+/// A single payload sent over the virtual radio link
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VirtualFrame {
+    pub channel: u8,
+    pub address: [u8; 5],
+    pub payload: Vec<u8>,
+}
+
+impl VirtualFrame {
+    pub fn new(channel: u8, address: [u8; 5], payload: Vec<u8>) -> Self {
+        Self {
+            channel,
+            address,
+            payload,
+        }
+    }
+
+    /// An acknowledgement from the robot with the given address
+    pub fn ack(channel: u8, address: [u8; 5]) -> Self {
+        Self::new(channel, address, Vec::new())
+    }
+
+    pub fn is_ack(&self) -> bool {
+        self.payload.is_empty()
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut buffer = Vec::with_capacity(VIRTUAL_FRAME_HEADER_SIZE + self.payload.len());
+        buffer.push(self.channel);
+        buffer.extend_from_slice(&self.address);
+        buffer.extend_from_slice(&self.payload);
+        buffer
+    }
+
+    pub fn decode(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < VIRTUAL_FRAME_HEADER_SIZE {
+            return None;
+        }
+
+        Some(Self {
+            channel: bytes[0],
+            address: bytes[1..VIRTUAL_FRAME_HEADER_SIZE].try_into().ok()?,
+            payload: bytes[VIRTUAL_FRAME_HEADER_SIZE..].to_vec(),
+        })
+    }
+}
+
+enum VirtualSocket {
+    Udp { socket: UdpSocket, peer: String },
+    #[cfg(unix)]
+    Unix { socket: UnixDatagram, peer: PathBuf },
+}
+
+/// One end of a virtual radio link
+pub struct VirtualLink {
+    socket: VirtualSocket,
+}
+
+impl VirtualLink {
+    /// Bind to `bind_address` and send frames to `peer_address` (either both `host:port` or
+    /// both `unix:/path`)
+    pub fn open(bind_address: &str, peer_address: &str) -> io::Result<Self> {
+        #[cfg(unix)]
+        if let (Some(bind_path), Some(peer_path)) = (bind_address.strip_prefix("unix:"), peer_address.strip_prefix("unix:")) {
+            // Remove the socket left behind by a previous run
+            let _ = std::fs::remove_file(bind_path);
+            let socket = UnixDatagram::bind(bind_path)?;
+            return Ok(Self { socket: VirtualSocket::Unix { socket, peer: PathBuf::from(peer_path) } });
+        }
+
+        if bind_address.starts_with("unix:") || peer_address.starts_with("unix:") {
+            return Err(io::Error::new(ErrorKind::InvalidInput, "both ends of a unix virtual link must be unix sockets"));
+        }
+
+        let socket = UdpSocket::bind(bind_address)?;
+        Ok(Self { socket: VirtualSocket::Udp { socket, peer: peer_address.to_string() } })
+    }
+
+    pub fn send_frame(&self, frame: &VirtualFrame) -> io::Result<()> {
+        let datagram = frame.encode();
+        match &self.socket {
+            VirtualSocket::Udp { socket, peer } => socket.send_to(&datagram, peer.as_str()).map(|_| ()),
+            #[cfg(unix)]
+            VirtualSocket::Unix { socket, peer } => socket.send_to(&datagram, peer).map(|_| ()),
+        }
+    }
+
+    /// Receive a single frame, waiting at most `timeout` (Duration::ZERO doesn't wait).
+    /// Returns None if no frame arrived in time.
+    pub fn recv_frame(&self, timeout: Duration) -> io::Result<Option<VirtualFrame>> {
+        let mut buffer = [0u8; 64];
+        let received = match &self.socket {
+            VirtualSocket::Udp { socket, .. } => {
+                socket.set_nonblocking(timeout.is_zero())?;
+                if !timeout.is_zero() {
+                    socket.set_read_timeout(Some(timeout))?;
+                }
+                socket.recv(&mut buffer)
+            },
+            #[cfg(unix)]
+            VirtualSocket::Unix { socket, .. } => {
+                socket.set_nonblocking(timeout.is_zero())?;
+                if !timeout.is_zero() {
+                    socket.set_read_timeout(Some(timeout))?;
+                }
+                socket.recv(&mut buffer)
+            },
+        };
+
+        match received {
+            Ok(size) => Ok(VirtualFrame::decode(&buffer[..size])),
+            Err(err) if matches!(err.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+}
+
+/// Radio Link to simulated robots over a Virtual Link
+pub struct VirtualRadio {
+    link: VirtualLink,
+    channel: u8,
+    base_station_address: [u8; 5],
+    robot_addresses: RobotAddresses,
+    signer: Option<ControlSigner>,
+    ack_timeout: Duration,
+    corrupt_bit: Option<usize>,
+    send_status: bool,
+    acked_at: Option<Instant>,
+    data: Vec<RobotStatusMessage>,
+}
+
+impl VirtualRadio {
+    pub fn new(link: VirtualLink, robot_table: &RobotTable) -> Self {
+        let mut virtual_radio = Self {
+            link,
+            channel: crate::CHANNEL,
+            base_station_address: BASE_STATION_ADDRESS,
+            robot_addresses: RobotAddresses::default(),
+            signer: None,
+            ack_timeout: DEFAULT_ACK_TIMEOUT,
+            corrupt_bit: None,
+            send_status: true,
+            acked_at: None,
+            data: Vec::new(),
+        };
+        virtual_radio.set_robot_table(TEAM, robot_table);
+        virtual_radio
+    }
+
+    pub fn set_ack_timeout(&mut self, ack_timeout: Duration) {
+        self.ack_timeout = ack_timeout;
+    }
+
+    /// Handle a frame from the link, returning whether it acknowledged `awaiting_ack`
+    fn handle_frame(&mut self, frame: VirtualFrame, awaiting_ack: Option<[u8; 5]>) -> bool {
+        if frame.channel != self.channel {
+            return false;
+        }
+
+        if frame.is_ack() {
+            return awaiting_ack == Some(frame.address);
+        }
+
+        if frame.address == self.base_station_address && frame.payload.len() == ROBOT_STATUS_SIZE {
+            match RobotStatusMessage::unpack_from_slice(&frame.payload) {
+                Ok(status) => self.data.push(status),
+                Err(err) => println!("Unable to unpack Robot Status Message: {:?}", err),
+            }
+        }
+        false
+    }
+}
+
+impl RadioLink for VirtualRadio {
+    fn initialize(&mut self, radio_config: &RadioConfig) -> bool {
+        self.channel = radio_config.channel;
+        self.base_station_address = radio_config.base_station_address;
+        true
+    }
+
+    fn set_channel(&mut self, channel: u8) {
+        self.channel = channel;
+    }
+
+    fn set_pa_level(&mut self, _pa_level: PowerAmplifier) {}
+
+    fn set_robot_table(&mut self, team: Team, robot_table: &RobotTable) {
+        self.robot_addresses.set_team(team, robot_table);
+    }
+
+    fn set_signer(&mut self, signer: Option<ControlSigner>) {
+        self.signer = signer;
+    }
+
+    fn corrupt_next_payload(&mut self, bit: usize) -> bool {
+        self.corrupt_bit = Some(bit);
+        true
+    }
+
+    fn send_status(&self) -> bool {
+        self.send_status
+    }
+
+    fn acked_at(&self) -> Option<Instant> {
+        self.acked_at
+    }
+
+    fn take_received(&mut self) -> Vec<RobotStatusMessage> {
+        self.data.drain(..).collect()
+    }
+
+    fn wait_for_data(&mut self, timeout: Duration) -> bool {
+        match self.link.recv_frame(timeout) {
+            Ok(Some(frame)) => {
+                self.handle_frame(frame, None);
+                true
+            },
+            Ok(None) => false,
+            Err(err) => {
+                println!("Unable to receive from the virtual radio link: {:?}", err);
+                false
+            },
+        }
+    }
+}
+
+impl Publish<ControlMessage> for VirtualRadio {
+    fn send(&mut self, data: ControlMessage) {
+        self.acked_at = None;
+        let corrupt_bit = self.corrupt_bit.take();
+        let target_robot = *data.robot_id;
+        let target_address = match self.robot_addresses.get(team_from_bit(data.team), target_robot) {
+            Some(address) => address,
+            None => {
+                println!("No Radio Address for Robot {}", target_robot);
+                self.send_status = false;
+                return;
+            },
+        };
+
+        let mut packed_data = match self.signer.as_mut() {
+            Some(signer) => signer.sign(&data).to_vec(),
+            None => match data.pack() {
+                Ok(bytes) => bytes.to_vec(),
+                Err(err) => panic!("Unable to Pack Data: {:?}", err),
+            },
+        };
+        if let Some(bit) = corrupt_bit {
+            let bit = bit % (packed_data.len() * 8);
+            packed_data[bit / 8] ^= 1 << (bit % 8);
+        }
+
+        let frame = VirtualFrame::new(self.channel, target_address, packed_data);
+        if let Err(err) = self.link.send_frame(&frame) {
+            println!("Unable to send to the virtual radio link: {:?}", err);
+            self.send_status = false;
+            return;
+        }
+
+        // Wait for the simulated robot to acknowledge the Control Message
+        self.send_status = false;
+        let start = Instant::now();
+        while let Some(remaining) = self.ack_timeout.checked_sub(start.elapsed()) {
+            match self.link.recv_frame(remaining.max(Duration::from_micros(1))) {
+                Ok(Some(frame)) => if self.handle_frame(frame, Some(target_address)) {
+                    self.send_status = true;
+                    self.acked_at = Some(Instant::now());
+                    return;
+                },
+                Ok(None) => return,
+                Err(err) => {
+                    println!("Unable to receive from the virtual radio link: {:?}", err);
+                    return;
+                },
+            }
+        }
+    }
+}
+
+impl Receive for VirtualRadio {
+    fn update_data(&mut self) {
+        loop {
+            match self.link.recv_frame(Duration::ZERO) {
+                Ok(Some(frame)) => {
+                    self.handle_frame(frame, None);
+                },
+                Ok(None) => return,
+                Err(err) => {
+                    println!("Unable to receive from the virtual radio link: {:?}", err);
+                    return;
+                },
+            }
+        }
+    }
+}
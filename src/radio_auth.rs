@@ -0,0 +1,265 @@
+//!
+//! Authenticated Control Messages
+//!
+//! Anything on our channel that knows `ROBOT_RADIO_ADDRESSES` can command our robots.
+//! When a pre-shared key is configured the base station appends a rolling counter and a
+//! truncated HMAC-SHA256 tag to each Control Message:
+//!
+//! [packed Control Message][counter: u64 big endian][tag: 8 bytes]
+//!
+//! where the tag is the first 8 bytes of HMAC-SHA256(key, counter || packed Control Message).
+//! A robot drops a payload whose tag doesn't match (forged) or whose counter isn't larger
+//! than the last counter it accepted (replayed).  `ControlVerifier` is the reference
+//! implementation of the robot's side.
+//!
+//! The counter starts at the current time in microseconds so it keeps increasing across
+//! base station restarts.  The Pi has no real time clock though, so it can boot with a
+//! clock that is behind the last run.  With a counter file the signer saves a high-water
+//! mark (reserving `COUNTER_RESERVATION` counters at a time so the file is rarely written)
+//! and always starts above it.
+//!
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use hmac::{Hmac, Mac};
+
+use packed_struct::{PackedStruct, PackedStructSlice};
+
+use sha2::Sha256;
+
+use robojackets_robocup_rtp::{ControlMessage, CONTROL_MESSAGE_SIZE};
+
+use crate::config::ConfigError;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// The size of the pre-shared key (bytes)
+pub const AUTH_KEY_SIZE: usize = 32;
+/// The size of the rolling counter (bytes)
+pub const AUTH_COUNTER_SIZE: usize = 8;
+/// The size of the truncated tag (bytes)
+pub const AUTH_TAG_SIZE: usize = 8;
+/// The size of an authenticated Control Message payload (bytes)
+pub const AUTHENTICATED_CONTROL_SIZE: usize = CONTROL_MESSAGE_SIZE + AUTH_COUNTER_SIZE + AUTH_TAG_SIZE;
+
+/// How many counters are reserved each time the high-water mark is saved
+pub const COUNTER_RESERVATION: u64 = 1 << 20;
+
+// nRF24L01+ payloads are at most 32 bytes
+const _: () = assert!(AUTHENTICATED_CONTROL_SIZE <= 32);
+
+/// The pre-shared key used to authenticate Control Messages
+#[derive(Clone, PartialEq, Eq)]
+pub struct AuthKey([u8; AUTH_KEY_SIZE]);
+
+impl AuthKey {
+    pub fn new(key: [u8; AUTH_KEY_SIZE]) -> Self {
+        Self(key)
+    }
+
+    /// Read the key from a file containing it as hex
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, ConfigError> {
+        Self::from_hex(&fs::read_to_string(path)?)
+    }
+
+    /// Parse the key from hex (surrounding whitespace is ignored)
+    pub fn from_hex(hex: &str) -> Result<Self, ConfigError> {
+        let hex = hex.trim();
+        if hex.len() != AUTH_KEY_SIZE * 2 {
+            return Err(ConfigError::InvalidAuthKey(format!(
+                "expected {} hex digits but found {}", AUTH_KEY_SIZE * 2, hex.len()
+            )));
+        }
+
+        let mut key = [0u8; AUTH_KEY_SIZE];
+        for (byte, digits) in key.iter_mut().zip(hex.as_bytes().chunks(2)) {
+            let digits = std::str::from_utf8(digits)
+                .map_err(|_| ConfigError::InvalidAuthKey(String::from("the key isn't hex")))?;
+            *byte = u8::from_str_radix(digits, 16)
+                .map_err(|_| ConfigError::InvalidAuthKey(format!("\"{}\" isn't hex", digits)))?;
+        }
+        Ok(Self(key))
+    }
+
+    fn mac(&self, counter: u64, packed: &[u8]) -> HmacSha256 {
+        let mut mac = HmacSha256::new_from_slice(&self.0).expect("HMAC accepts keys of any size");
+        mac.update(&counter.to_be_bytes());
+        mac.update(packed);
+        mac
+    }
+}
+
+// Keep the key out of logs
+impl fmt::Debug for AuthKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "AuthKey(..)")
+    }
+}
+
+/// Signs the Control Messages sent by the base station
+#[derive(Clone, Debug)]
+pub struct ControlSigner {
+    key: AuthKey,
+    counter: u64,
+    counter_file: Option<PathBuf>,
+    reserved: u64,
+}
+
+impl ControlSigner {
+    /// Create a signer whose counter starts at the current time (in microseconds)
+    pub fn new(key: AuthKey) -> Self {
+        let counter = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|since_epoch| since_epoch.as_micros() as u64)
+            .unwrap_or(0);
+        Self::with_counter(key, counter)
+    }
+
+    pub fn with_counter(key: AuthKey, counter: u64) -> Self {
+        Self {
+            key,
+            counter,
+            counter_file: None,
+            reserved: u64::MAX,
+        }
+    }
+
+    /// Create a signer whose counter starts above both the current time and the high-water
+    /// mark saved in `counter_file` (which is created if it doesn't exist)
+    pub fn with_counter_file<P: AsRef<Path>>(key: AuthKey, counter_file: P) -> Result<Self, ConfigError> {
+        let counter_file = counter_file.as_ref().to_path_buf();
+        let saved = match fs::read_to_string(&counter_file) {
+            Ok(saved) => saved.trim().parse::<u64>()
+                .map_err(|_| ConfigError::InvalidAuthCounter(format!("\"{}\" isn't a number", saved.trim())))?,
+            Err(err) if err.kind() == ErrorKind::NotFound => 0,
+            Err(err) => return Err(err.into()),
+        };
+
+        let mut signer = Self::new(key);
+        if signer.counter < saved {
+            println!("The clock is behind the saved authentication counter, continuing from the counter");
+            signer.counter = saved;
+        }
+        signer.counter_file = Some(counter_file);
+        signer.reserve()?;
+        Ok(signer)
+    }
+
+    /// Save the high-water mark of the next `COUNTER_RESERVATION` counters (if saving fails
+    /// it is tried again once they have been used)
+    fn reserve(&mut self) -> io::Result<()> {
+        let Some(counter_file) = self.counter_file.as_ref() else { return Ok(()) };
+        self.reserved = self.counter.saturating_add(COUNTER_RESERVATION);
+
+        // Replace the file in one step so a crash can't leave it empty
+        let mut temporary = counter_file.clone().into_os_string();
+        temporary.push(".tmp");
+        fs::write(&temporary, format!("{}\n", self.reserved))?;
+        fs::rename(&temporary, counter_file)
+    }
+
+    /// The counter of the last Control Message signed
+    pub fn counter(&self) -> u64 {
+        self.counter
+    }
+
+    /// Sign a Control Message, returning the authenticated payload to send to the robot
+    pub fn sign(&mut self, control_message: &ControlMessage) -> [u8; AUTHENTICATED_CONTROL_SIZE] {
+        let packed = match control_message.pack() {
+            Ok(bytes) => bytes,
+            Err(err) => panic!("Unable to Pack Data: {:?}", err),
+        };
+        self.counter += 1;
+        if self.counter > self.reserved {
+            if let Err(err) = self.reserve() {
+                println!("Unable to save the authentication counter: {:?}", err);
+            }
+        }
+        let tag = self.key.mac(self.counter, &packed).finalize().into_bytes();
+
+        let mut payload = [0u8; AUTHENTICATED_CONTROL_SIZE];
+        payload[..CONTROL_MESSAGE_SIZE].copy_from_slice(&packed);
+        payload[CONTROL_MESSAGE_SIZE..CONTROL_MESSAGE_SIZE + AUTH_COUNTER_SIZE].copy_from_slice(&self.counter.to_be_bytes());
+        payload[CONTROL_MESSAGE_SIZE + AUTH_COUNTER_SIZE..].copy_from_slice(&tag[..AUTH_TAG_SIZE]);
+        payload
+    }
+}
+
+/// Why an authenticated Control Message was rejected
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AuthError {
+    /// The payload isn't the size of an authenticated Control Message
+    Length(usize),
+    /// The tag doesn't match the Control Message (it was forged or corrupted)
+    BadTag,
+    /// The counter isn't larger than the last one accepted for the robot
+    Replayed { counter: u64, last: u64 },
+    /// The Control Message couldn't be unpacked
+    Unpack,
+}
+
+impl fmt::Display for AuthError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AuthError::Length(length) => write!(f, "expected {} bytes but received {}", AUTHENTICATED_CONTROL_SIZE, length),
+            AuthError::BadTag => write!(f, "the tag doesn't match the control message"),
+            AuthError::Replayed { counter, last } => write!(f, "counter {} was replayed (last accepted {})", counter, last),
+            AuthError::Unpack => write!(f, "unable to unpack the control message"),
+        }
+    }
+}
+
+impl std::error::Error for AuthError {}
+
+/// Reference implementation of the robot's check of authenticated Control Messages
+#[derive(Clone, Debug)]
+pub struct ControlVerifier {
+    key: AuthKey,
+    last_counters: HashMap<u8, u64>,
+}
+
+impl ControlVerifier {
+    pub fn new(key: AuthKey) -> Self {
+        Self {
+            key,
+            last_counters: HashMap::new(),
+        }
+    }
+
+    /// The counter of the last Control Message accepted for a robot
+    pub fn last_counter(&self, robot_id: u8) -> Option<u64> {
+        self.last_counters.get(&robot_id).copied()
+    }
+
+    /// Check an authenticated payload, returning the Control Message if it is genuine and
+    /// hasn't been seen before
+    pub fn verify(&mut self, payload: &[u8]) -> Result<ControlMessage, AuthError> {
+        if payload.len() != AUTHENTICATED_CONTROL_SIZE {
+            return Err(AuthError::Length(payload.len()));
+        }
+
+        let (packed, rest) = payload.split_at(CONTROL_MESSAGE_SIZE);
+        let (counter, tag) = rest.split_at(AUTH_COUNTER_SIZE);
+        let mut counter_bytes = [0u8; AUTH_COUNTER_SIZE];
+        counter_bytes.copy_from_slice(counter);
+        let counter = u64::from_be_bytes(counter_bytes);
+
+        // Constant time comparison of the tag
+        self.key.mac(counter, packed).verify_truncated_left(tag).map_err(|_| AuthError::BadTag)?;
+
+        let control_message = ControlMessage::unpack_from_slice(packed).map_err(|_| AuthError::Unpack)?;
+        let robot_id = *control_message.robot_id;
+        if let Some(last) = self.last_counter(robot_id) {
+            if counter <= last {
+                return Err(AuthError::Replayed { counter, last });
+            }
+        }
+        self.last_counters.insert(robot_id, counter);
+        Ok(control_message)
+    }
+}
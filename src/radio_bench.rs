@@ -0,0 +1,480 @@
+//!
+//! Radio Benchmark Matrix
+//!
+//! Sends Control Messages to a single robot with every combination of PA level and
+//! retransmit settings, recording the ack rate, the number of retransmits (from OBSERVE_TX)
+//! and the round trip time (from the start of the transmission until the robot's status is
+//! received).  The report can be written as CSV or JSON so venues can be compared.
+//!
+//! Only the base station's radio is reconfigured, so only the settings the robot doesn't
+//! have to follow are swept.  The channel and data rate are fixed for the whole run and must
+//! match the robot's firmware (a robot on another channel or data rate never acks).
+//!
+//! The Control Messages sent are all zero velocity so the robot doesn't move.
+//!
+
+use std::cell::RefCell;
+use std::fmt::Write as FmtWrite;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use embedded_hal::blocking::delay::{DelayMs, DelayUs};
+use embedded_hal::blocking::spi::{Transfer, Write};
+use embedded_hal::digital::v2::OutputPin;
+
+use packed_struct::PackedStruct;
+
+use serde::Serialize;
+
+use robojackets_robocup_rtp::control_message::{ControlMessageBuilder, CONTROL_MESSAGE_SIZE};
+use robojackets_robocup_rtp::robot_status_message::ROBOT_STATUS_SIZE;
+use robojackets_robocup_rtp::{BASE_STATION_ADDRESS, TEAM};
+
+use rtic_nrf24l01::config::power_amplifier::PowerAmplifier;
+use rtic_nrf24l01::Radio;
+
+use crate::config::{pa_level_name, parse_pa_level, MAX_CHANNEL};
+use crate::latency::LatencyHistogram;
+use crate::nrf_registers::*;
+
+/// The air data rate of the radio
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DataRate {
+    Kbps250,
+    Mbps1,
+    Mbps2,
+}
+
+impl DataRate {
+    pub fn parse(data_rate: &str) -> Option<Self> {
+        match data_rate.to_lowercase().as_str() {
+            "250kbps" => Some(DataRate::Kbps250),
+            "1mbps" => Some(DataRate::Mbps1),
+            "2mbps" => Some(DataRate::Mbps2),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            DataRate::Kbps250 => "250kbps",
+            DataRate::Mbps1 => "1mbps",
+            DataRate::Mbps2 => "2mbps",
+        }
+    }
+
+    /// The RF_DR_LOW and RF_DR_HIGH bits of RF_SETUP
+    pub fn rf_setup_bits(&self) -> u8 {
+        match self {
+            DataRate::Kbps250 => RF_DR_LOW,
+            DataRate::Mbps1 => 0,
+            DataRate::Mbps2 => RF_DR_HIGH,
+        }
+    }
+}
+
+/// The automatic retransmit settings of the radio
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RetransmitSettings {
+    /// The delay between retransmits (250-4000us in steps of 250us)
+    pub delay_us: u16,
+    /// The maximum number of retransmits (0-15)
+    pub count: u8,
+}
+
+impl RetransmitSettings {
+    /// Parse retransmit settings of the form `delay_us:count` (e.g. `500:15`)
+    pub fn parse(retransmits: &str) -> Option<Self> {
+        let (delay_us, count) = retransmits.split_once(':')?;
+        let retransmits = Self {
+            delay_us: delay_us.trim().parse().ok()?,
+            count: count.trim().parse().ok()?,
+        };
+        retransmits.is_valid().then_some(retransmits)
+    }
+
+    pub fn is_valid(&self) -> bool {
+        (250..=4000).contains(&self.delay_us) && self.delay_us.is_multiple_of(250) && self.count <= 15
+    }
+
+    /// The value of SETUP_RETR
+    pub fn setup_retr(&self) -> u8 {
+        (((self.delay_us / 250 - 1) as u8) << 4) | self.count
+    }
+}
+
+/// One combination of radio settings
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BenchSettings {
+    pub pa_level: PowerAmplifier,
+    pub channel: u8,
+    pub data_rate: DataRate,
+    pub retransmits: RetransmitSettings,
+}
+
+/// The radio settings to sweep
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BenchMatrix {
+    pub pa_levels: Vec<PowerAmplifier>,
+    pub retransmits: Vec<RetransmitSettings>,
+    /// The robot's channel
+    pub channel: u8,
+    /// The robot's data rate
+    pub data_rate: DataRate,
+}
+
+impl BenchMatrix {
+    /// Parse the swept settings from comma separated names (e.g. `low,high` and
+    /// `500:15,1500:5`) and the robot's channel and data rate (e.g. `1mbps`)
+    pub fn parse(pa_levels: &str, retransmits: &str, channel: u8, data_rate: &str) -> Result<Self, String> {
+        fn parse_list<T>(list: &str, kind: &str, parse: impl Fn(&str) -> Option<T>) -> Result<Vec<T>, String> {
+            list.split(',')
+                .map(|item| parse(item.trim()).ok_or_else(|| format!("invalid {}: {}", kind, item)))
+                .collect()
+        }
+
+        if channel > MAX_CHANNEL {
+            return Err(format!("invalid channel: {}", channel));
+        }
+
+        Ok(Self {
+            pa_levels: parse_list(pa_levels, "PA level", parse_pa_level)?,
+            retransmits: parse_list(retransmits, "retransmit setting", RetransmitSettings::parse)?,
+            channel,
+            data_rate: DataRate::parse(data_rate.trim()).ok_or_else(|| format!("invalid data rate: {}", data_rate))?,
+        })
+    }
+
+    /// Every combination of the settings
+    pub fn combinations(&self) -> Vec<BenchSettings> {
+        let mut combinations = Vec::new();
+        for pa_level in self.pa_levels.iter() {
+            for retransmits in self.retransmits.iter() {
+                combinations.push(BenchSettings {
+                    pa_level: *pa_level,
+                    channel: self.channel,
+                    data_rate: self.data_rate,
+                    retransmits: *retransmits,
+                });
+            }
+        }
+        combinations
+    }
+}
+
+/// The results of benchmarking one combination of settings
+#[derive(Clone, Debug)]
+pub struct BenchResult {
+    pub settings: BenchSettings,
+    pub sent: u64,
+    pub acked: u64,
+    /// The total number of retransmits (of acknowledged and lost Control Messages)
+    pub retransmits: u64,
+    pub max_retransmits: u8,
+    /// The number of Robot Status Messages received
+    pub responses: u64,
+    /// From the start of the transmission until the Robot Status Message is received
+    pub round_trip: LatencyHistogram,
+}
+
+impl BenchResult {
+    pub fn new(settings: BenchSettings) -> Self {
+        Self {
+            settings,
+            sent: 0,
+            acked: 0,
+            retransmits: 0,
+            max_retransmits: 0,
+            responses: 0,
+            round_trip: LatencyHistogram::default(),
+        }
+    }
+
+    pub fn ack_rate(&self) -> f64 {
+        if self.sent == 0 {
+            return 0.0;
+        }
+        self.acked as f64 / self.sent as f64
+    }
+
+    pub fn mean_retransmits(&self) -> f64 {
+        if self.sent == 0 {
+            return 0.0;
+        }
+        self.retransmits as f64 / self.sent as f64
+    }
+}
+
+/// A single row of the report
+#[derive(Clone, Debug, Serialize)]
+struct BenchRow {
+    pa_level: &'static str,
+    channel: u8,
+    data_rate: &'static str,
+    retransmit_delay_us: u16,
+    retransmit_count: u8,
+    sent: u64,
+    acked: u64,
+    ack_rate: f64,
+    mean_retransmits: f64,
+    max_retransmits: u8,
+    responses: u64,
+    rtt_mean_us: Option<u128>,
+    rtt_p50_us: Option<u128>,
+    rtt_p95_us: Option<u128>,
+    rtt_max_us: Option<u128>,
+}
+
+impl From<&BenchResult> for BenchRow {
+    fn from(result: &BenchResult) -> Self {
+        let received = result.round_trip.count() > 0;
+        Self {
+            pa_level: pa_level_name(result.settings.pa_level),
+            channel: result.settings.channel,
+            data_rate: result.settings.data_rate.name(),
+            retransmit_delay_us: result.settings.retransmits.delay_us,
+            retransmit_count: result.settings.retransmits.count,
+            sent: result.sent,
+            acked: result.acked,
+            ack_rate: result.ack_rate(),
+            mean_retransmits: result.mean_retransmits(),
+            max_retransmits: result.max_retransmits,
+            responses: result.responses,
+            rtt_mean_us: result.round_trip.mean().map(|rtt| rtt.as_micros()),
+            rtt_p50_us: result.round_trip.percentile(50.0).map(|rtt| rtt.as_micros()),
+            rtt_p95_us: result.round_trip.percentile(95.0).map(|rtt| rtt.as_micros()),
+            rtt_max_us: received.then(|| result.round_trip.max().as_micros()),
+        }
+    }
+}
+
+/// The results of a whole benchmark matrix
+#[derive(Clone, Debug, Default)]
+pub struct BenchReport {
+    pub robot_id: u8,
+    pub results: Vec<BenchResult>,
+}
+
+impl BenchReport {
+    pub fn new(robot_id: u8) -> Self {
+        Self {
+            robot_id,
+            results: Vec::new(),
+        }
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("robot_id,pa_level,channel,data_rate,retransmit_delay_us,retransmit_count,sent,acked,ack_rate,mean_retransmits,max_retransmits,responses,rtt_mean_us,rtt_p50_us,rtt_p95_us,rtt_max_us\n");
+        for result in self.results.iter() {
+            let row = BenchRow::from(result);
+            let micros = |rtt: Option<u128>| rtt.map(|rtt| rtt.to_string()).unwrap_or_default();
+            let _ = writeln!(
+                csv,
+                "{},{},{},{},{},{},{},{},{:.4},{:.3},{},{},{},{},{},{}",
+                self.robot_id,
+                row.pa_level,
+                row.channel,
+                row.data_rate,
+                row.retransmit_delay_us,
+                row.retransmit_count,
+                row.sent,
+                row.acked,
+                row.ack_rate,
+                row.mean_retransmits,
+                row.max_retransmits,
+                row.responses,
+                micros(row.rtt_mean_us),
+                micros(row.rtt_p50_us),
+                micros(row.rtt_p95_us),
+                micros(row.rtt_max_us),
+            );
+        }
+        csv
+    }
+
+    pub fn to_json(&self) -> String {
+        let rows: Vec<BenchRow> = self.results.iter().map(BenchRow::from).collect();
+        serde_json::json!({
+            "robot_id": self.robot_id,
+            "results": rows,
+        }).to_string()
+    }
+
+    /// Write the report as JSON (for `.json` files) or CSV (everything else)
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("json") => fs::write(path, self.to_json()),
+            _ => fs::write(path, self.to_csv()),
+        }
+    }
+}
+
+/// An output pin shared by the radio driver and the benchmark (so the benchmark can access
+/// registers the driver doesn't expose)
+pub struct SharedPin<P> {
+    pin: Rc<RefCell<P>>,
+}
+
+impl<P> SharedPin<P> {
+    pub fn new(pin: P) -> Self {
+        Self { pin: Rc::new(RefCell::new(pin)) }
+    }
+}
+
+impl<P> Clone for SharedPin<P> {
+    fn clone(&self) -> Self {
+        Self { pin: self.pin.clone() }
+    }
+}
+
+impl<P: OutputPin> OutputPin for SharedPin<P> {
+    type Error = P::Error;
+
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.pin.borrow_mut().set_low()
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        self.pin.borrow_mut().set_high()
+    }
+}
+
+/// Benchmarks the radio against a single robot
+pub struct RadioBench<SPI, CSN, CE, DELAY, SPIE, GPIOE> where
+    SPI: Transfer<u8, Error=SPIE> + Write<u8, Error=SPIE>,
+    CSN: OutputPin<Error=GPIOE>,
+    CE: OutputPin<Error=GPIOE>,
+    DELAY: DelayMs<u32> + DelayUs<u32>,
+{
+    radio: Radio<CE, SharedPin<CSN>, SPI, DELAY, GPIOE, SPIE>,
+    csn: SharedPin<CSN>,
+    spi: SPI,
+    delay: DELAY,
+}
+
+impl<SPI, CSN, CE, DELAY, SPIE, GPIOE> RadioBench<SPI, CSN, CE, DELAY, SPIE, GPIOE> where
+    SPI: Transfer<u8, Error=SPIE> + Write<u8, Error=SPIE>,
+    CSN: OutputPin<Error=GPIOE>,
+    CE: OutputPin<Error=GPIOE>,
+    DELAY: DelayMs<u32> + DelayUs<u32>,
+{
+    pub fn new(ce: CE, csn: CSN, spi: SPI, delay: DELAY) -> Self {
+        let csn = SharedPin::new(csn);
+        Self {
+            radio: Radio::new(ce, csn.clone()),
+            csn,
+            spi,
+            delay,
+        }
+    }
+
+    /// Initialize the radio, returning false if it couldn't be initialized
+    pub fn begin(&mut self) -> bool {
+        if self.radio.begin(&mut self.spi, &mut self.delay).is_err() {
+            return false;
+        }
+        self.radio.open_reading_pipe(1, BASE_STATION_ADDRESS, &mut self.spi, &mut self.delay);
+        self.radio.stop_listening(&mut self.spi, &mut self.delay);
+        true
+    }
+
+    pub fn read_register(&mut self, register: u8) -> u8 {
+        let mut buffer = [R_REGISTER | register, NOP];
+        let _ = self.csn.set_low();
+        let value = match self.spi.transfer(&mut buffer) {
+            Ok(buffer) => buffer[1],
+            Err(_) => 0,
+        };
+        let _ = self.csn.set_high();
+        value
+    }
+
+    pub fn write_register(&mut self, register: u8, value: u8) {
+        let _ = self.csn.set_low();
+        let _ = self.spi.write(&[W_REGISTER | register, value]);
+        let _ = self.csn.set_high();
+    }
+
+    /// Apply a combination of settings and send to `robot_address`
+    pub fn configure(&mut self, settings: &BenchSettings, robot_address: [u8; 5]) {
+        self.radio.stop_listening(&mut self.spi, &mut self.delay);
+        self.radio.set_pa_level(settings.pa_level, &mut self.spi, &mut self.delay);
+        self.radio.set_channel(settings.channel, &mut self.spi, &mut self.delay);
+
+        let rf_setup = self.read_register(RF_SETUP) & !(RF_DR_LOW | RF_DR_HIGH);
+        self.write_register(RF_SETUP, rf_setup | settings.data_rate.rf_setup_bits());
+        self.write_register(SETUP_RETR, settings.retransmits.setup_retr());
+
+        self.radio.set_payload_size(CONTROL_MESSAGE_SIZE as u8, &mut self.spi, &mut self.delay);
+        self.radio.open_writing_pipe(robot_address, &mut self.spi, &mut self.delay);
+    }
+
+    /// Send `messages` Control Messages to a robot with the given settings, waiting up to
+    /// `response_timeout` for the robot's status after each one
+    pub fn run(
+        &mut self,
+        settings: &BenchSettings,
+        robot_id: u8,
+        robot_address: [u8; 5],
+        messages: usize,
+        interval: Duration,
+        response_timeout: Duration,
+    ) -> BenchResult {
+        self.configure(settings, robot_address);
+
+        let control_message = ControlMessageBuilder::new()
+            .team(TEAM)
+            .robot_id(robot_id)
+            .build();
+        let packed_data = control_message.pack().expect("Unable to pack Control Message");
+
+        let mut result = BenchResult::new(*settings);
+        for _ in 0..messages {
+            let start = Instant::now();
+            let acked = self.radio.write(&packed_data, &mut self.spi, &mut self.delay);
+            let retransmits = self.read_register(OBSERVE_TX) & 0x0F;
+            self.radio.flush_tx(&mut self.spi, &mut self.delay);
+
+            result.sent += 1;
+            result.retransmits += retransmits as u64;
+            result.max_retransmits = result.max_retransmits.max(retransmits);
+            if acked {
+                result.acked += 1;
+                if self.await_response(response_timeout) {
+                    result.responses += 1;
+                    result.round_trip.record(start.elapsed());
+                }
+            }
+
+            self.delay.delay_ms(interval.as_millis() as u32);
+        }
+        result
+    }
+
+    /// Wait for a Robot Status Message, returning whether one was received in time
+    fn await_response(&mut self, timeout: Duration) -> bool {
+        self.radio.set_payload_size(ROBOT_STATUS_SIZE as u8, &mut self.spi, &mut self.delay);
+        self.radio.start_listening(&mut self.spi, &mut self.delay);
+
+        let start = Instant::now();
+        let mut received = false;
+        while start.elapsed() < timeout {
+            if self.radio.packet_ready(&mut self.spi, &mut self.delay) {
+                let mut buffer = [0u8; ROBOT_STATUS_SIZE];
+                self.radio.read(&mut buffer, &mut self.spi, &mut self.delay);
+                received = true;
+                break;
+            }
+            self.delay.delay_us(50);
+        }
+
+        self.radio.stop_listening(&mut self.spi, &mut self.delay);
+        self.radio.flush_rx(&mut self.spi, &mut self.delay);
+        self.radio.set_payload_size(CONTROL_MESSAGE_SIZE as u8, &mut self.spi, &mut self.delay);
+        received
+    }
+}
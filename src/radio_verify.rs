@@ -0,0 +1,147 @@
+//!
+//! Radio Configuration Verification
+//!
+//! The radio driver writes the configuration without checking the chip took it, so a
+//! loose SPI wire or a counterfeit chip can leave the base station on the wrong channel
+//! (or powered down) without any error.  After the radio is initialized its configuration
+//! registers (CONFIG through RF_SETUP, as read by the driver's `get_registers`) are decoded
+//! and compared field by field with the intended `RadioConfig`.  The driver doesn't read
+//! back the pipe addresses, so the base station address isn't verified.
+//!
+
+use std::fmt;
+
+use rtic_nrf24l01::config::power_amplifier::PowerAmplifier;
+
+use crate::config::{pa_level_name, RadioConfig};
+use crate::nrf_registers::*;
+use crate::publishers::radio_link::RadioLink;
+
+/// A snapshot of the registers holding the radio's configuration
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RadioRegisters {
+    pub config: u8,
+    pub en_aa: u8,
+    pub en_rxaddr: u8,
+    pub setup_aw: u8,
+    pub rf_ch: u8,
+    pub rf_setup: u8,
+}
+
+impl RadioRegisters {
+    /// Decode the first registers of the radio (CONFIG is at index 0)
+    pub fn from_registers(registers: [u8; 8]) -> Self {
+        Self {
+            config: registers[CONFIG as usize],
+            en_aa: registers[EN_AA as usize],
+            en_rxaddr: registers[EN_RXADDR as usize],
+            setup_aw: registers[SETUP_AW as usize],
+            rf_ch: registers[RF_CH as usize],
+            rf_setup: registers[RF_SETUP as usize],
+        }
+    }
+
+    pub fn powered_up(&self) -> bool {
+        self.config & PWR_UP != 0
+    }
+
+    pub fn channel(&self) -> u8 {
+        self.rf_ch
+    }
+
+    pub fn pa_level(&self) -> PowerAmplifier {
+        match (self.rf_setup & RF_PWR) >> 1 {
+            0 => PowerAmplifier::PAMin,
+            1 => PowerAmplifier::PALow,
+            2 => PowerAmplifier::PAHigh,
+            _ => PowerAmplifier::PAMax,
+        }
+    }
+
+    /// The width of the radio addresses (bytes, 0 if the width is invalid)
+    pub fn address_width(&self) -> u8 {
+        match self.setup_aw & 0b11 {
+            0 => 0,
+            width => width + 2,
+        }
+    }
+
+    /// Whether a pipe (0-5) is enabled
+    pub fn pipe_enabled(&self, pipe: u8) -> bool {
+        self.en_rxaddr & (1 << pipe) != 0
+    }
+
+    /// Whether a pipe (0-5) acknowledges the payloads it receives
+    pub fn auto_ack(&self, pipe: u8) -> bool {
+        self.en_aa & (1 << pipe) != 0
+    }
+
+    /// Compare the registers with the intended configuration, returning every field that
+    /// doesn't match
+    pub fn verify(&self, radio_config: &RadioConfig) -> Vec<RegisterMismatch> {
+        let mut mismatches = Vec::new();
+        let mut check = |field: &'static str, expected: String, actual: String| {
+            if expected != actual {
+                mismatches.push(RegisterMismatch { field, expected, actual });
+            }
+        };
+
+        check("powered_up", true.to_string(), self.powered_up().to_string());
+        check("channel", radio_config.channel.to_string(), self.channel().to_string());
+        check("pa_level", pa_level_name(radio_config.pa_level).to_string(), pa_level_name(self.pa_level()).to_string());
+        check("address_width", 5.to_string(), self.address_width().to_string());
+        check("pipe_1_enabled", true.to_string(), self.pipe_enabled(1).to_string());
+        check("pipe_1_auto_ack", true.to_string(), self.auto_ack(1).to_string());
+        mismatches
+    }
+}
+
+impl fmt::Display for RadioRegisters {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "powered_up={} channel={} pa_level={} address_width={} pipe_1_enabled={} pipe_1_auto_ack={}",
+            self.powered_up(),
+            self.channel(),
+            pa_level_name(self.pa_level()),
+            self.address_width(),
+            self.pipe_enabled(1),
+            self.auto_ack(1),
+        )
+    }
+}
+
+/// A field of the radio configuration the chip doesn't hold
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RegisterMismatch {
+    pub field: &'static str,
+    pub expected: String,
+    pub actual: String,
+}
+
+impl fmt::Display for RegisterMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} is {} (expected {})", self.field, self.actual, self.expected)
+    }
+}
+
+/// Read back the radio's registers and report every field that doesn't match the intended
+/// configuration (None if the link has no registers, e.g. the Virtual Radio, or they
+/// can't be read)
+pub fn verify_radio<LINK: RadioLink + ?Sized>(radio_link: &mut LINK, radio_config: &RadioConfig) -> Option<Vec<RegisterMismatch>> {
+    let Some(registers) = radio_link.read_registers() else {
+        println!("Unable to Read the Radio's Registers, the Radio Configuration isn't Verified");
+        return None;
+    };
+
+    let mismatches = registers.verify(radio_config);
+    if mismatches.is_empty() {
+        println!("Radio Configuration Verified");
+    } else {
+        println!("Radio Configuration Mismatch ({})", registers);
+        for mismatch in mismatches.iter() {
+            println!("  {}", mismatch);
+        }
+    }
+    Some(mismatches)
+}
@@ -0,0 +1,113 @@
+//!
+//! Logical to Physical Robot Id Mapping
+//!
+//! The Field Computer always addresses robots by their logical id.  When a broken robot
+//! is swapped for a spare the logical id can be pointed at a different physical robot
+//! so outgoing Control Messages are sent to the spare and its Robot Status Messages are
+//! reported under the logical id.
+//!
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::time::SystemTime;
+
+/// A change to the robot mapping (kept for auditing)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MappingChange {
+    pub logical_id: u8,
+    pub previous_physical_id: u8,
+    pub physical_id: u8,
+    pub changed_at: SystemTime,
+}
+
+impl fmt::Display for MappingChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let changed_at = self.changed_at
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        write!(
+            f,
+            "[{}] Robot {} remapped from physical robot {} to physical robot {}",
+            changed_at,
+            self.logical_id,
+            self.previous_physical_id,
+            self.physical_id,
+        )
+    }
+}
+
+/// Mapping from logical robot ids (used by the Field Computer) to physical robot ids
+/// (used by the radio).  Robots without an entry map to themselves.
+#[derive(Clone, Debug, Default)]
+pub struct RobotIdMap {
+    logical_to_physical: BTreeMap<u8, u8>,
+    history: Vec<MappingChange>,
+}
+
+impl RobotIdMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The physical robot a logical robot id is sent to
+    pub fn physical(&self, logical_id: u8) -> u8 {
+        self.logical_to_physical.get(&logical_id).copied().unwrap_or(logical_id)
+    }
+
+    /// The logical robot id a physical robot reports as (None if the physical robot
+    /// has been swapped out and isn't mapped to any logical id)
+    pub fn logical(&self, physical_id: u8) -> Option<u8> {
+        if let Some((logical_id, _)) = self.logical_to_physical.iter().find(|(_, physical)| **physical == physical_id) {
+            return Some(*logical_id);
+        }
+
+        if self.logical_to_physical.contains_key(&physical_id) {
+            None
+        } else {
+            Some(physical_id)
+        }
+    }
+
+    /// Point a logical robot id at a physical robot.  Fails if the physical robot has
+    /// already been remapped to another logical robot id.
+    pub fn set(&mut self, logical_id: u8, physical_id: u8) -> Result<MappingChange, String> {
+        let in_use_by = self.logical_to_physical.iter()
+            .find(|(other, physical)| **physical == physical_id && **other != logical_id);
+        if let Some((other, _)) = in_use_by {
+            return Err(format!("physical robot {} is already in use by robot {}", physical_id, other));
+        }
+
+        let previous_physical_id = self.physical(logical_id);
+        if physical_id == logical_id {
+            self.logical_to_physical.remove(&logical_id);
+        } else {
+            self.logical_to_physical.insert(logical_id, physical_id);
+        }
+
+        let change = MappingChange {
+            logical_id,
+            previous_physical_id,
+            physical_id,
+            changed_at: SystemTime::now(),
+        };
+        println!("{}", change);
+        self.history.push(change);
+        Ok(change)
+    }
+
+    /// Point a logical robot id back at the physical robot with the same id
+    pub fn reset(&mut self, logical_id: u8) -> Result<MappingChange, String> {
+        self.set(logical_id, logical_id)
+    }
+
+    /// Every logical robot id that doesn't map to itself
+    pub fn mappings(&self) -> Vec<(u8, u8)> {
+        self.logical_to_physical.iter().map(|(logical, physical)| (*logical, *physical)).collect()
+    }
+
+    /// Every change made to the mapping (oldest first)
+    pub fn history(&self) -> &[MappingChange] {
+        &self.history
+    }
+}
@@ -0,0 +1,143 @@
+//!
+//! Simulated Robots
+//!
+//! Pretends to be one or more robots on the other end of a Virtual Link (see
+//! `crate::publishers::virtual_radio`).  Each simulated robot acknowledges the Control
+//! Messages sent to its radio address and responds with a Robot Status Message, exactly
+//! like the robot firmware does over the nRF24L01+.
+//!
+//! With a verifier (see `crate::radio_auth`) the simulated robots only accept authenticated
+//! Control Messages and silently drop forged or replayed ones.
+//!
+
+use std::io;
+use std::time::Duration;
+
+use packed_struct::{PackedStruct, PackedStructSlice};
+
+use robojackets_robocup_rtp::{ControlMessage, RobotStatusMessageBuilder, Team};
+use robojackets_robocup_rtp::{BASE_STATION_ADDRESS, CONTROL_MESSAGE_SIZE};
+
+use crate::config::team_from_bit;
+use crate::publishers::virtual_radio::{VirtualFrame, VirtualLink};
+use crate::radio_auth::ControlVerifier;
+
+/// A single simulated robot
+#[derive(Clone, Debug)]
+pub struct SimulatedRobot {
+    pub robot_id: u8,
+    pub team: Team,
+    pub radio_address: [u8; 5],
+    pub battery_voltage: u8,
+    pub last_command: Option<ControlMessage>,
+    pub commands_received: u64,
+}
+
+impl SimulatedRobot {
+    pub fn new(robot_id: u8, team: Team, radio_address: [u8; 5]) -> Self {
+        Self {
+            robot_id,
+            team,
+            radio_address,
+            battery_voltage: 12,
+            last_command: None,
+            commands_received: 0,
+        }
+    }
+}
+
+/// Simulated robots listening on a Virtual Link
+pub struct RobotSimulator {
+    link: VirtualLink,
+    channel: u8,
+    base_station_address: [u8; 5],
+    robots: Vec<SimulatedRobot>,
+    verifier: Option<ControlVerifier>,
+    rejected: u64,
+}
+
+impl RobotSimulator {
+    pub fn new(link: VirtualLink, channel: u8, robots: Vec<SimulatedRobot>) -> Self {
+        Self {
+            link,
+            channel,
+            base_station_address: BASE_STATION_ADDRESS,
+            robots,
+            verifier: None,
+            rejected: 0,
+        }
+    }
+
+    /// Respond to a base station listening on another address (see `crate::config::AddressSet`)
+    pub fn set_base_station_address(&mut self, base_station_address: [u8; 5]) {
+        self.base_station_address = base_station_address;
+    }
+
+    /// Only accept Control Messages authenticated for the verifier's key
+    pub fn set_verifier(&mut self, verifier: ControlVerifier) {
+        self.verifier = Some(verifier);
+    }
+
+    /// The number of Control Messages rejected by the verifier
+    pub fn rejected(&self) -> u64 {
+        self.rejected
+    }
+
+    pub fn robots(&self) -> &[SimulatedRobot] {
+        &self.robots
+    }
+
+    pub fn robot(&self, robot_id: u8) -> Option<&SimulatedRobot> {
+        self.robots.iter().find(|robot| robot.robot_id == robot_id)
+    }
+
+    /// Handle a single Control Message (waiting at most `timeout` for it to arrive), returning
+    /// the id of the robot that responded
+    pub fn poll(&mut self, timeout: Duration) -> io::Result<Option<u8>> {
+        let Some(frame) = self.link.recv_frame(timeout)? else { return Ok(None) };
+        if frame.channel != self.channel || !self.robots.iter().any(|robot| robot.radio_address == frame.address) {
+            return Ok(None);
+        }
+
+        let control_message = match self.verifier.as_mut() {
+            Some(verifier) => match verifier.verify(&frame.payload) {
+                Ok(control_message) => control_message,
+                Err(err) => {
+                    println!("Rejected Control Message: {}", err);
+                    self.rejected += 1;
+                    return Ok(None);
+                },
+            },
+            None if frame.payload.len() == CONTROL_MESSAGE_SIZE => match ControlMessage::unpack_from_slice(&frame.payload) {
+                Ok(control_message) => control_message,
+                Err(_) => return Ok(None),
+            },
+            None => return Ok(None),
+        };
+
+        let Some(robot) = self.robots.iter_mut().find(|robot| {
+            robot.radio_address == frame.address
+                && robot.robot_id == *control_message.robot_id
+                && robot.team == team_from_bit(control_message.team)
+        }) else {
+            return Ok(None);
+        };
+        robot.last_command = Some(control_message);
+        robot.commands_received += 1;
+
+        let status = RobotStatusMessageBuilder::new()
+            .team(robot.team)
+            .robot_id(robot.robot_id)
+            .kick_healthy(true)
+            .fpga_status(true)
+            .battery_voltage(robot.battery_voltage)
+            .build();
+        let status = status.pack().expect("Unable to pack Robot Status Message");
+        let robot_id = robot.robot_id;
+        let radio_address = robot.radio_address;
+
+        self.link.send_frame(&VirtualFrame::ack(self.channel, radio_address))?;
+        self.link.send_frame(&VirtualFrame::new(self.channel, self.base_station_address, status.to_vec()))?;
+        Ok(Some(robot_id))
+    }
+}
@@ -0,0 +1,153 @@
+//!
+//! The Status Cache keeps the latest RobotStatusMessage received from each robot
+//! (along with the time it was received) so that clients that start late or restart
+//! can request a full snapshot instead of waiting for the next status packet.
+//!
+//! Snapshot Protocol:
+//! (client -> 0.0.0.0:8003) - Any datagram is treated as a snapshot request
+//! (0.0.0.0:8003 -> client) - [count: u8] followed by `count` entries of
+//!     [received_at: u64 (ms since UNIX epoch, big endian)][packed RobotStatusMessage]
+//!
+
+use std::collections::BTreeMap;
+use std::io::ErrorKind;
+use std::net::{SocketAddr, UdpSocket};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use packed_struct::{PackedStruct, PackedStructSlice};
+
+use robojackets_robocup_rtp::robot_status_message::{RobotStatusMessage, ROBOT_STATUS_SIZE};
+
+use crate::net_errors::RebindingSocket;
+use crate::publishers::datagram::bind_socket;
+
+/// The size (in bytes) of a single robot entry in a snapshot response
+pub const SNAPSHOT_ENTRY_SIZE: usize = 8 + ROBOT_STATUS_SIZE;
+
+/// The latest status of a robot along with the time it was received
+#[derive(Clone, Copy, Debug)]
+pub struct CachedRobotStatus {
+    pub status: RobotStatusMessage,
+    pub received_at: SystemTime,
+}
+
+/// Cache of the latest status received from each robot (keyed by robot id)
+#[derive(Default)]
+pub struct RobotStatusCache {
+    statuses: BTreeMap<u8, CachedRobotStatus>,
+}
+
+impl RobotStatusCache {
+    pub fn new() -> Self {
+        Self { statuses: BTreeMap::new() }
+    }
+
+    /// Store a status message that was just received
+    pub fn update(&mut self, status: RobotStatusMessage) {
+        self.update_at(status, SystemTime::now());
+    }
+
+    /// Store a status message that was received at `received_at`
+    pub fn update_at(&mut self, status: RobotStatusMessage, received_at: SystemTime) {
+        self.statuses.insert(*status.robot_id, CachedRobotStatus { status, received_at });
+    }
+
+    /// Get the latest status for a given robot
+    pub fn get(&self, robot_id: u8) -> Option<&CachedRobotStatus> {
+        self.statuses.get(&robot_id)
+    }
+
+    /// Get the latest status of every robot (ordered by robot id)
+    pub fn snapshot(&self) -> Vec<CachedRobotStatus> {
+        self.statuses.values().copied().collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.statuses.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.statuses.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.statuses.clear();
+    }
+
+    /// Encode the snapshot of every robot into a single datagram
+    pub fn encode_snapshot(&self) -> Vec<u8> {
+        let mut buffer = Vec::with_capacity(1 + self.statuses.len() * SNAPSHOT_ENTRY_SIZE);
+        buffer.push(self.statuses.len() as u8);
+        for cached in self.statuses.values() {
+            let received_at = cached.received_at
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis() as u64;
+            let packed_status = match cached.status.pack() {
+                Ok(bytes) => bytes,
+                Err(err) => panic!("Unable to Pack Data: {:?}", err),
+            };
+            buffer.extend_from_slice(&received_at.to_be_bytes());
+            buffer.extend_from_slice(&packed_status);
+        }
+        buffer
+    }
+}
+
+/// Decode a snapshot datagram produced by `RobotStatusCache::encode_snapshot`
+pub fn decode_snapshot(bytes: &[u8]) -> Option<Vec<CachedRobotStatus>> {
+    let (count, entries) = bytes.split_first()?;
+    if entries.len() != *count as usize * SNAPSHOT_ENTRY_SIZE {
+        return None;
+    }
+
+    let mut snapshot = Vec::with_capacity(*count as usize);
+    for entry in entries.chunks_exact(SNAPSHOT_ENTRY_SIZE) {
+        let mut received_at = [0u8; 8];
+        received_at.copy_from_slice(&entry[..8]);
+        let received_at = UNIX_EPOCH + Duration::from_millis(u64::from_be_bytes(received_at));
+        let status = RobotStatusMessage::unpack_from_slice(&entry[8..]).ok()?;
+        snapshot.push(CachedRobotStatus { status, received_at });
+    }
+    Some(snapshot)
+}
+
+/// Non-blocking UDP server that answers snapshot requests from a RobotStatusCache
+pub struct StatusSnapshotServer {
+    socket: RebindingSocket<UdpSocket>,
+}
+
+impl StatusSnapshotServer {
+    pub fn new(bind_address: &str) -> Self {
+        let socket = RebindingSocket::new(bind_address, bind_socket, "Status snapshot server error")
+            .expect("Unable to bind the status snapshot socket");
+        Self { socket }
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.socket.bound()
+            .expect("The status snapshot socket isn't bound")
+            .local_addr()
+            .expect("Unable to get the status snapshot address")
+    }
+
+    /// Answer every pending snapshot request with the current contents of the cache
+    pub fn poll(&mut self, cache: &RobotStatusCache) {
+        let mut buffer = [0u8; 64];
+        loop {
+            let Some(socket) = self.socket.socket() else { return };
+            match socket.recv_from(&mut buffer) {
+                Ok((_, client)) => {
+                    if let Err(err) = socket.send_to(&cache.encode_snapshot(), client) {
+                        self.socket.record_error(&format!("unable to send snapshot to {}", client), &err);
+                    }
+                },
+                Err(err) if err.kind() == ErrorKind::WouldBlock => return,
+                Err(err) => {
+                    self.socket.record_error("unable to receive snapshot request", &err);
+                    return;
+                },
+            }
+        }
+    }
+}
@@ -4,7 +4,17 @@
 //! will continually send it a request to wake up.
 //! 
 
-use ncomm::{publisher_subscriber::{local::{MappedLocalSubscriber, LocalPublisher}, udp::UdpPublisher, Publish, Receive}, node::Node};
+use ncomm::{publisher_subscriber::{local::{MappedLocalSubscriber, LocalPublisher, LocalSubscriber}, Publish, Receive, Subscribe}, node::Node};
+
+use crate::alive_robots::{AliveRobots, AliveRobotsEncoding};
+use crate::publishers::datagram::{DatagramPublisher, MulticastOptions};
+
+/// Settings of the Timeout Checker that can be changed while it is running
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TimeoutSettings {
+    pub num_robots: u8,
+    pub timeout: u128,
+}
 
 /// The Timeout Checker will receive the timestamps for last send from the RobotRelayNode
 /// and compute (every 100 ms) whether or not a robot should be considered dead.  Then, if
@@ -13,35 +23,80 @@ use ncomm::{publisher_subscriber::{local::{MappedLocalSubscriber, LocalPublisher
 pub struct TimeoutCheckerNode<'a> {
     num_robots: u8,
     timeout_duration: u128,
-    alive_robots: Vec<bool>,
+    alive_robots: AliveRobots,
+    alive_robots_encoding: AliveRobotsEncoding,
     receive_message_subscriber: MappedLocalSubscriber<u8, u8>,
-    alive_robots_publisher: UdpPublisher<'a, u16, 2>,
-    alive_robots_intra_publisher: LocalPublisher<u16>,
+    alive_robots_publisher: DatagramPublisher<'a>,
+    alive_robots_intra_publisher: LocalPublisher<AliveRobots>,
+    timeout_settings_subscriber: Option<LocalSubscriber<TimeoutSettings>>,
 }
 
 impl<'a> TimeoutCheckerNode<'a> {
     pub fn new(
         num_robots: u8,
         timeout: u128,
+        alive_robots_encoding: AliveRobotsEncoding,
         alive_robots_bind_address: &'a str,
-        alive_robots_send_address: &'a str,
+        alive_robots_send_addresses: Vec<&'a str>,
         receive_message_subscriber: MappedLocalSubscriber<u8, u8>
     ) -> Self {
-        let alive_robots_publisher = UdpPublisher::new(
+        let alive_robots_publisher = DatagramPublisher::new(
             alive_robots_bind_address,
-            vec![alive_robots_send_address]);
-        let alive_robots = Vec::with_capacity(num_robots as usize);
+            alive_robots_send_addresses);
+        let alive_robots = AliveRobots::new(num_robots);
         let alive_robots_intra_publisher = LocalPublisher::new();
 
         Self {
             num_robots,
             timeout_duration: timeout,
             alive_robots,
+            alive_robots_encoding,
             receive_message_subscriber,
             alive_robots_publisher,
             alive_robots_intra_publisher,
+            timeout_settings_subscriber: None,
+        }
+    }
+
+    /// Set the ttl and interface used when sending alive robots to multicast groups
+    pub fn set_multicast(&mut self, options: &MulticastOptions) {
+        if let Err(err) = self.alive_robots_publisher.set_multicast(options) {
+            println!("Unable to set Alive Robots Multicast Options: {:?}", err);
         }
     }
+
+    /// Only send alive robots over a specific network interface (e.g. "eth0")
+    pub fn set_bind_interface(&mut self, interface: &str) {
+        if let Err(err) = self.alive_robots_publisher.set_bind_interface(interface) {
+            println!("Unable to bind Alive Robots to {}: {:?}", interface, err);
+        }
+    }
+
+    /// Receive changes to the number of robots and timeout while running
+    pub fn add_timeout_settings_subscriber(&mut self, subscriber: LocalSubscriber<TimeoutSettings>) {
+        self.timeout_settings_subscriber = Some(subscriber);
+    }
+
+    /// Apply the latest timeout settings (if they have changed)
+    fn apply_timeout_settings(&mut self) {
+        let Some(subscriber) = self.timeout_settings_subscriber.as_mut() else { return };
+        subscriber.update_data();
+        let Some(settings) = subscriber.data else { return };
+
+        if settings.num_robots != self.num_robots {
+            let mut alive_robots = AliveRobots::new(settings.num_robots);
+            for robot_id in self.alive_robots.alive_ids() {
+                alive_robots.set_alive(robot_id, true);
+            }
+            self.alive_robots = alive_robots;
+            self.num_robots = settings.num_robots;
+        }
+        self.timeout_duration = settings.timeout;
+    }
+
+    pub fn create_alive_robots_subscriber(&mut self) -> LocalSubscriber<AliveRobots> {
+        self.alive_robots_intra_publisher.create_subscriber()
+    }
 }
 impl<'a> Node for TimeoutCheckerNode<'a> {
     fn name(&self) -> String { String::from("Timeout Checker Node") }
@@ -49,37 +104,33 @@ impl<'a> Node for TimeoutCheckerNode<'a> {
     fn get_update_delay(&self) -> u128 { self.timeout_duration }
 
     fn start(&mut self) {
-        for _ in 0..self.num_robots {
-            self.alive_robots.push(true);
+        for robot_id in 0..self.num_robots {
+            self.alive_robots.set_alive(robot_id, true);
         }
     }
 
     fn update(&mut self) {
+        self.apply_timeout_settings();
         self.receive_message_subscriber.update_data();
 
         // Update Alive Robots
-        let mut alive_robots = 0u16;
         for i in 0..self.num_robots {
-            self.alive_robots[i as usize] = false;
-            if let Some(_) = self.receive_message_subscriber.data.get(&i) {
-                self.alive_robots[i as usize] = true;
-                alive_robots |= 1 << i;
-                self.receive_message_subscriber.data.remove(&i);
-            }
+            let alive = self.receive_message_subscriber.data.remove(&i).is_some();
+            self.alive_robots.set_alive(i, alive);
         }
 
-        println!("Alive Robots: {:#018b}", alive_robots);
+        println!("Alive Robots: {:?}", self.alive_robots.alive_ids());
 
         // Send Updated Alive Robots List
-        self.alive_robots_publisher.send(alive_robots);
-        self.alive_robots_intra_publisher.send(alive_robots);
+        self.alive_robots_publisher.send(self.alive_robots.encode(self.alive_robots_encoding));
+        self.alive_robots_intra_publisher.send(self.alive_robots.clone());
     }
 
     fn shutdown(&mut self) {
-        self.alive_robots_publisher.send(0);
+        self.alive_robots_publisher.send(AliveRobots::new(self.num_robots).encode(self.alive_robots_encoding));
     }
 
     fn debug(&self) -> String {
-        format!("{}: {:?}", self.name(), self.alive_robots)
+        format!("{}: {:?}", self.name(), self.alive_robots.alive_ids())
     }
 }
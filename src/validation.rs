@@ -0,0 +1,182 @@
+//!
+//! Validation of the Control Messages received from the Field Computer.
+//!
+//! Every Control Message is checked before it reaches the radio.  Messages that
+//! can't be sent safely (unknown robot, wrong team or excessive velocities) are rejected
+//! while dribbler and kick values that are out of range are clamped.
+//!
+//! Velocities arrive already quantised to integers (thousandths of a meter or radian per
+//! second), so they can't be NaN or infinite by the time they are validated.
+//!
+
+use std::collections::HashMap;
+
+use robojackets_robocup_rtp::control_message::ControlMessage;
+use robojackets_robocup_rtp::Team;
+
+use crate::config::{team_from_bit, ConfigError, RobotTable};
+
+/// The reason a Control Message was rejected (or clamped)
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum RejectionReason {
+    /// The robot id is not in play or has no radio address
+    InvalidRobotId,
+    /// The robot has been disabled in the robot table
+    RobotDisabled,
+    /// The message was addressed to the other team
+    WrongTeam,
+    /// One of the body velocities exceeds the configured maximum
+    ExcessiveVelocity,
+    /// The dribbler speed was out of range and has been clamped
+    DribblerOutOfRange,
+    /// The kick strength was out of range and has been clamped
+    KickOutOfRange,
+}
+
+/// The default maximum linear velocity (4 m/s in the raw units of the packed message)
+pub const DEFAULT_MAX_LINEAR_VELOCITY: f32 = 4_000.0;
+/// The default maximum angular velocity (12 rad/s in the raw units of the packed message)
+pub const DEFAULT_MAX_ANGULAR_VELOCITY: f32 = 12_000.0;
+
+/// The limits a Control Message must be within (velocities are in the raw units of
+/// the packed message).  The robot firmware's accepted dribbler and kick ranges aren't
+/// documented, so by default the dribbler speed and kick strength may take any value
+/// their fields can hold (a full `i8` and `u8`) and are only clamped when narrower
+/// limits are configured (see --max-dribbler-speed and --max-kick-strength).
+#[derive(Clone, Copy, Debug)]
+pub struct ValidationLimits {
+    pub max_linear_velocity: f32,
+    pub max_angular_velocity: f32,
+    pub min_dribbler_speed: i8,
+    pub max_dribbler_speed: i8,
+    pub max_kick_strength: u8,
+}
+
+impl Default for ValidationLimits {
+    fn default() -> Self {
+        Self {
+            max_linear_velocity: DEFAULT_MAX_LINEAR_VELOCITY,
+            max_angular_velocity: DEFAULT_MAX_ANGULAR_VELOCITY,
+            min_dribbler_speed: i8::MIN,
+            max_dribbler_speed: i8::MAX,
+            max_kick_strength: u8::MAX,
+        }
+    }
+}
+
+impl ValidationLimits {
+    /// Check that the limits can be applied (a dribbler range can't be empty and the
+    /// velocity limits have to be positive)
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.min_dribbler_speed > self.max_dribbler_speed {
+            return Err(ConfigError::InvalidValidationLimits(format!(
+                "the minimum dribbler speed {} is above the maximum {}",
+                self.min_dribbler_speed,
+                self.max_dribbler_speed,
+            )));
+        }
+        if !(self.max_linear_velocity > 0.0 && self.max_angular_velocity > 0.0) {
+            return Err(ConfigError::InvalidValidationLimits(String::from("the velocity limits have to be positive")));
+        }
+        Ok(())
+    }
+}
+
+/// Validates Control Messages and keeps count of the number of messages rejected or
+/// clamped for each reason.
+pub struct ControlMessageValidator {
+    team: Team,
+    num_robots: u8,
+    robot_table: RobotTable,
+    limits: ValidationLimits,
+    counts: HashMap<RejectionReason, u64>,
+}
+
+impl ControlMessageValidator {
+    pub fn new(team: Team, num_robots: u8, robot_table: RobotTable, limits: ValidationLimits) -> Self {
+        Self {
+            team,
+            num_robots,
+            robot_table,
+            limits,
+            counts: HashMap::new(),
+        }
+    }
+
+    pub fn set_limits(&mut self, limits: ValidationLimits) {
+        self.limits = limits;
+    }
+
+    pub fn set_num_robots(&mut self, num_robots: u8) {
+        self.num_robots = num_robots;
+    }
+
+    pub fn set_robot_table(&mut self, robot_table: RobotTable) {
+        self.robot_table = robot_table;
+    }
+
+    /// Validate a Control Message, returning the (possibly clamped) message that is safe
+    /// to send or the reason it was rejected.
+    pub fn validate(&mut self, message: ControlMessage) -> Result<ControlMessage, RejectionReason> {
+        self.validate_mapped(message, *message.robot_id)
+    }
+
+    /// Validate a Control Message for a logical robot that will be sent to the physical
+    /// robot `physical_robot_id`.
+    pub fn validate_mapped(&mut self, mut message: ControlMessage, physical_robot_id: u8) -> Result<ControlMessage, RejectionReason> {
+        let robot_id = *message.robot_id;
+        if robot_id >= self.num_robots {
+            return Err(self.count(RejectionReason::InvalidRobotId));
+        }
+        match self.robot_table.get(physical_robot_id) {
+            None => return Err(self.count(RejectionReason::InvalidRobotId)),
+            Some(robot) if !robot.enabled => return Err(self.count(RejectionReason::RobotDisabled)),
+            Some(_) => (),
+        }
+
+        if team_from_bit(message.team) != self.team {
+            return Err(self.count(RejectionReason::WrongTeam));
+        }
+
+        let body_x = *message.body_x as f32;
+        let body_y = *message.body_y as f32;
+        let body_w = *message.body_w as f32;
+        if body_x.abs() > self.limits.max_linear_velocity ||
+            body_y.abs() > self.limits.max_linear_velocity ||
+            body_w.abs() > self.limits.max_angular_velocity {
+            return Err(self.count(RejectionReason::ExcessiveVelocity));
+        }
+
+        let dribbler_speed = *message.dribbler_speed;
+        if dribbler_speed < self.limits.min_dribbler_speed || dribbler_speed > self.limits.max_dribbler_speed {
+            self.count(RejectionReason::DribblerOutOfRange);
+            message.dribbler_speed = dribbler_speed
+                .clamp(self.limits.min_dribbler_speed, self.limits.max_dribbler_speed)
+                .into();
+        }
+
+        let kick_strength = *message.kick_strength;
+        if kick_strength > self.limits.max_kick_strength {
+            self.count(RejectionReason::KickOutOfRange);
+            message.kick_strength = self.limits.max_kick_strength.into();
+        }
+
+        Ok(message)
+    }
+
+    /// Record that a message was rejected (or clamped) for the given reason
+    pub fn count(&mut self, reason: RejectionReason) -> RejectionReason {
+        *self.counts.entry(reason).or_insert(0) += 1;
+        reason
+    }
+
+    /// The number of messages rejected (or clamped) for a given reason
+    pub fn rejections(&self, reason: RejectionReason) -> u64 {
+        self.counts.get(&reason).copied().unwrap_or(0)
+    }
+
+    /// The number of messages rejected (or clamped) for each reason
+    pub fn rejection_counts(&self) -> &HashMap<RejectionReason, u64> {
+        &self.counts
+    }
+}
@@ -0,0 +1,49 @@
+//!
+//! Test parsing the runtime configuration commands of the control API
+//!
+
+use robocup_base_station::config::{pa_level_name, RadioConfig};
+use robocup_base_station::control_api::{ConfigSetting, ControlCommand};
+
+#[test]
+/// Configuration changes should be parsed and validated
+fn test_parse_set_commands() {
+    assert!(matches!(ControlCommand::parse("set channel 106"), Ok(ControlCommand::Set(ConfigSetting::Channel(106)))));
+    assert!(matches!(ControlCommand::parse("set robots 11"), Ok(ControlCommand::Set(ConfigSetting::Robots(11)))));
+    assert!(matches!(ControlCommand::parse("set timeout 250"), Ok(ControlCommand::Set(ConfigSetting::Timeout(250)))));
+    assert!(matches!(ControlCommand::parse("set heartbeat_timeout 100"), Ok(ControlCommand::Set(ConfigSetting::HeartbeatTimeout(100)))));
+
+    match ControlCommand::parse("set pa_level high") {
+        Ok(ControlCommand::Set(ConfigSetting::PaLevel(pa_level))) => assert_eq!(pa_level_name(pa_level), "high"),
+        _ => panic!("Unable to parse pa level"),
+    }
+
+    assert!(ControlCommand::parse("set pa_level loud").is_err());
+    assert!(ControlCommand::parse("set robots 300").is_err());
+    assert!(ControlCommand::parse("set volume 11").is_err());
+
+    // A timeout of 0 would have the Timeout Checker spin
+    assert!(ControlCommand::parse("set timeout 0").is_err());
+    assert!(ControlCommand::parse("set timeout 5").is_err());
+    assert!(ControlCommand::parse("set heartbeat_timeout 0").is_err());
+    assert_eq!(ControlCommand::parse("set timeout 10"), Ok(ControlCommand::Set(ConfigSetting::Timeout(10))));
+}
+
+#[test]
+/// Robot and radio commands should be parsed
+fn test_parse_robot_commands() {
+    assert!(matches!(ControlCommand::parse("get"), Ok(ControlCommand::Get)));
+    assert!(matches!(ControlCommand::parse("robots"), Ok(ControlCommand::Robots)));
+    assert!(matches!(ControlCommand::parse("enable 3"), Ok(ControlCommand::Enable { robot_id: 3 })));
+    assert!(matches!(ControlCommand::parse("disable 4"), Ok(ControlCommand::Disable { robot_id: 4 })));
+    assert!(matches!(ControlCommand::parse("reinit"), Ok(ControlCommand::Reinit)));
+    assert!(matches!(ControlCommand::parse("link"), Ok(ControlCommand::Link)));
+    assert!(ControlCommand::parse("disable").is_err());
+}
+
+#[test]
+/// Channels outside of the nRF24L01+ range should be rejected
+fn test_validate_radio_channel() {
+    assert!(RadioConfig { channel: 125, ..Default::default() }.validate().is_ok());
+    assert!(RadioConfig { channel: 126, ..Default::default() }.validate().is_err());
+}
@@ -0,0 +1,173 @@
+//!
+//! Test batched and sequenced Control Messages from the Field Computer
+//!
+
+use std::net::UdpSocket;
+use std::thread::sleep;
+use std::time::Duration;
+
+use ncomm::publisher_subscriber::Receive;
+
+use packed_struct::PackedStruct;
+
+use robocup_base_station::control_batch::{ControlBatch, SequencedControlMessage, CONTROL_BATCH_HEADER_SIZE};
+use robocup_base_station::publishers::datagram::{Datagram, SequencedRobotStatus};
+use robocup_base_station::publishers::control_subscriber::ControlMessageSubscriber;
+
+use robojackets_robocup_rtp::control_message::{ControlMessage, ControlMessageBuilder};
+use robojackets_robocup_rtp::robot_status_message::RobotStatusMessageBuilder;
+use robojackets_robocup_rtp::Team;
+
+fn control_message(robot_id: u8, body_x: f32) -> ControlMessage {
+    ControlMessageBuilder::new()
+        .team(Team::Blue)
+        .robot_id(robot_id)
+        .body_x(body_x)
+        .build()
+}
+
+#[test]
+/// Batches should survive being encoded and decoded
+fn test_encode_decode_batch() {
+    let batch = ControlBatch::new(42, (0..6).map(|robot_id| control_message(robot_id, 1.0)).collect());
+    let encoded = batch.encode();
+    assert!(ControlBatch::is_batch(&encoded));
+
+    let decoded = ControlBatch::decode(&encoded).unwrap();
+    assert_eq!(decoded.frame, 42);
+    assert_eq!(decoded.control_messages.len(), 6);
+    for (robot_id, control_message) in decoded.control_messages.iter().enumerate() {
+        assert_eq!(*control_message.robot_id, robot_id as u8);
+    }
+
+    // A single Control Message is never a batch and truncated batches are rejected
+    assert!(!ControlBatch::is_batch(&control_message(0, 0.0).pack().unwrap()));
+    assert!(ControlBatch::decode(&encoded[..encoded.len() - 1]).is_none());
+    assert_eq!(ControlBatch::new(0, Vec::new()).encode().len(), CONTROL_BATCH_HEADER_SIZE);
+}
+
+/// Send datagrams to the subscriber and wait for them to arrive
+fn send_all(subscriber: &mut ControlMessageSubscriber, datagrams: &[Vec<u8>]) {
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    for datagram in datagrams {
+        socket.send_to(datagram, subscriber.local_addr()).unwrap();
+    }
+    sleep(Duration::from_millis(50));
+    subscriber.update_data();
+}
+
+#[test]
+/// A batch should replace every Control Message received before it
+fn test_batch_replaces_single_messages() {
+    let mut subscriber = ControlMessageSubscriber::new("127.0.0.1:0");
+
+    send_all(&mut subscriber, &[
+        control_message(0, 1.0).pack().unwrap().to_vec(),
+        control_message(1, 1.0).pack().unwrap().to_vec(),
+        control_message(2, 1.0).pack().unwrap().to_vec(),
+    ]);
+    assert_eq!(subscriber.data.len(), 3);
+    assert_eq!(subscriber.last_frame(), None);
+
+    send_all(&mut subscriber, &[
+        ControlBatch::new(10, vec![control_message(0, 2.0), control_message(1, 2.0)]).encode(),
+    ]);
+    assert_eq!(subscriber.last_frame(), Some(10));
+    assert_eq!(subscriber.data.len(), 2);
+    assert!(!subscriber.data.contains_key(&2));
+    // Robots missing from the batch don't keep the time of their last message
+    assert!(subscriber.received_at(0).is_some());
+    assert_eq!(subscriber.received_at(2), None);
+
+    // Single Control Messages keep working after a batch
+    send_all(&mut subscriber, &[control_message(2, 3.0).pack().unwrap().to_vec()]);
+    assert_eq!(subscriber.data.len(), 3);
+}
+
+#[test]
+/// Batches older than the latest frame should be dropped
+fn test_stale_batches() {
+    let mut subscriber = ControlMessageSubscriber::new("127.0.0.1:0");
+
+    send_all(&mut subscriber, &[
+        ControlBatch::new(100, vec![control_message(0, 1.0), control_message(1, 1.0)]).encode(),
+        ControlBatch::new(99, vec![control_message(0, 1.0)]).encode(),
+        ControlBatch::new(100, vec![control_message(0, 1.0)]).encode(),
+    ]);
+    assert_eq!(subscriber.last_frame(), Some(100));
+    assert_eq!(subscriber.data.len(), 2);
+    assert_eq!(subscriber.batches_received(), 3);
+    assert_eq!(subscriber.stale_batches(), 2);
+
+    // A Field Computer that restarts from frame 0 should be followed
+    send_all(&mut subscriber, &[ControlBatch::new(0, vec![control_message(3, 1.0)]).encode()]);
+    assert_eq!(subscriber.last_frame(), Some(0));
+    assert_eq!(subscriber.data.len(), 1);
+
+    // Frame numbers wrap around
+    send_all(&mut subscriber, &[
+        ControlBatch::new(u32::MAX - 100, Vec::new()).encode(),
+        ControlBatch::new(u32::MAX, Vec::new()).encode(),
+        ControlBatch::new(1, vec![control_message(4, 1.0)]).encode(),
+    ]);
+    assert_eq!(subscriber.last_frame(), Some(1));
+    assert!(subscriber.data.contains_key(&4));
+}
+
+fn sequenced(sequence: u32, robot_id: u8) -> Vec<u8> {
+    SequencedControlMessage::new(sequence, control_message(robot_id, 1.0)).encode()
+}
+
+#[test]
+/// Sequenced Control Messages should survive being encoded and decoded
+fn test_encode_decode_sequenced() {
+    let encoded = sequenced(7, 3);
+    assert!(SequencedControlMessage::is_sequenced(&encoded));
+    assert!(!ControlBatch::is_batch(&encoded));
+
+    let decoded = SequencedControlMessage::decode(&encoded).unwrap();
+    assert_eq!(decoded.sequence, 7);
+    assert_eq!(*decoded.control_message.robot_id, 3);
+}
+
+#[test]
+/// Stale and duplicate sequenced Control Messages should be dropped per robot
+fn test_sequenced_control_messages() {
+    let mut subscriber = ControlMessageSubscriber::new("127.0.0.1:0");
+
+    send_all(&mut subscriber, &[sequenced(1, 0), sequenced(1, 1), sequenced(2, 0)]);
+    assert_eq!(subscriber.sequence(0), Some(2));
+    assert_eq!(subscriber.sequence(1), Some(1));
+
+    // Robot 0's sequence 1 is stale and sequence 2 is a duplicate
+    send_all(&mut subscriber, &[sequenced(1, 0), sequenced(2, 0)]);
+    assert_eq!(subscriber.sequence(0), Some(2));
+    assert_eq!(subscriber.stale_commands(), 1);
+    assert_eq!(subscriber.duplicate_commands(), 1);
+
+    // Gaps in the sequence are counted as lost commands
+    send_all(&mut subscriber, &[sequenced(6, 0)]);
+    assert_eq!(subscriber.sequence(0), Some(6));
+    assert_eq!(subscriber.lost_commands(), 3);
+
+    // A restarted Field Computer should be followed
+    send_all(&mut subscriber, &[sequenced(1_000, 1), sequenced(0, 1)]);
+    assert_eq!(subscriber.sequence(1), Some(0));
+
+    // Unsequenced Control Messages keep working and have no sequence
+    send_all(&mut subscriber, &[control_message(0, 2.0).pack().unwrap().to_vec()]);
+    assert_eq!(subscriber.sequence(0), None);
+    assert_eq!(subscriber.data.len(), 2);
+}
+
+#[test]
+/// Robot statuses should be followed by the last applied sequence number
+fn test_sequenced_robot_status() {
+    let status = RobotStatusMessageBuilder::new().team(Team::Blue).robot_id(1).build();
+    let plain = status.to_datagram();
+    let sequenced = SequencedRobotStatus { status, sequence: 0x01020304 }.to_datagram();
+
+    assert_eq!(sequenced.len(), plain.len() + 4);
+    assert_eq!(&sequenced[..plain.len()], &plain[..]);
+    assert_eq!(&sequenced[plain.len()..], &[1, 2, 3, 4]);
+}
@@ -0,0 +1,117 @@
+//!
+//! Test sending datagrams to multiple destinations and multicast groups
+//!
+
+use std::io;
+use std::net::{Ipv4Addr, UdpSocket};
+use std::thread;
+use std::time::Duration;
+
+use ncomm::publisher_subscriber::Publish;
+
+use packed_struct::PackedStructSlice;
+
+use robocup_base_station::net_errors::{ErrorLog, RebindingSocket, ENODEV, REBIND_INTERVAL};
+use robocup_base_station::publishers::datagram::{bind_socket, DatagramIngress, DatagramPublisher, MulticastOptions};
+
+use robojackets_robocup_rtp::robot_status_message::{RobotStatusMessage, RobotStatusMessageBuilder};
+use robojackets_robocup_rtp::Team;
+
+#[test]
+/// Every destination should receive every robot status
+fn test_multiple_destinations() {
+    let receivers: Vec<UdpSocket> = (0..3).map(|_| {
+        let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+        receiver.set_read_timeout(Some(Duration::from_secs(1))).unwrap();
+        receiver
+    }).collect();
+    let addresses: Vec<String> = receivers.iter()
+        .map(|receiver| receiver.local_addr().unwrap().to_string())
+        .collect();
+
+    let mut publisher = DatagramPublisher::new(
+        "127.0.0.1:0",
+        addresses.iter().map(String::as_str).collect(),
+    );
+    assert_eq!(publisher.addresses().len(), 3);
+
+    let status = RobotStatusMessageBuilder::new().team(Team::Blue).robot_id(2).battery_voltage(9).build();
+    publisher.send(status);
+
+    for receiver in receivers.iter() {
+        let mut buffer = [0u8; 64];
+        let size = receiver.recv(&mut buffer).unwrap();
+        let received = RobotStatusMessage::unpack_from_slice(&buffer[..size]).unwrap();
+        assert_eq!(*received.robot_id, 2);
+        assert_eq!(*received.battery_voltage, 9);
+    }
+}
+
+#[test]
+/// Multicast options should be applied to the publisher's socket
+fn test_multicast_options() {
+    let mut publisher = DatagramPublisher::new("127.0.0.1:0", vec!["239.0.0.1:8001"]);
+    assert!(publisher.set_multicast(&MulticastOptions::default()).is_ok());
+    assert!(publisher.set_multicast(&MulticastOptions { ttl: 4, interface: Some(Ipv4Addr::LOCALHOST) }).is_ok());
+}
+
+#[test]
+/// Repeated errors should be counted but only logged once per interval
+fn test_error_log() {
+    let mut errors = ErrorLog::with_interval("test", Duration::from_secs(3600));
+    assert!(errors.record("network unreachable"));
+    for _ in 0..4 {
+        assert!(!errors.record("network unreachable"));
+    }
+    assert_eq!(errors.count(), 5);
+
+    let mut errors = ErrorLog::with_interval("test", Duration::ZERO);
+    assert!(errors.record("network unreachable"));
+    assert!(errors.record("network unreachable"));
+}
+
+#[test]
+/// Sends that fail should be counted without interrupting the caller, and a bind address
+/// that doesn't exist yet shouldn't stop the publisher from being created
+fn test_send_errors() {
+    // Sockets bound to loopback can't reach other networks
+    let mut publisher = DatagramPublisher::new("127.0.0.1:0", vec!["192.0.2.1:8001"]);
+    let status = RobotStatusMessageBuilder::new().team(Team::Blue).robot_id(2).build();
+    for _ in 0..3 {
+        publisher.send(status);
+    }
+    assert_eq!(publisher.send_errors(), 3);
+    assert_eq!(publisher.rebinds(), 0);
+
+    // 192.0.2.1 (TEST-NET-1) isn't assigned to any interface
+    let mut publisher = DatagramPublisher::new("192.0.2.1:0", vec!["127.0.0.1:8001"]);
+    assert!(publisher.local_addr().is_none());
+    assert!(publisher.set_multicast(&MulticastOptions::default()).is_ok());
+    publisher.send(status);
+    assert!(publisher.send_errors() >= 1);
+    assert!(publisher.local_addr().is_none());
+}
+
+#[test]
+/// Sockets whose bind address isn't available yet should be bound later instead of
+/// stopping the base station from starting
+fn test_deferred_bind() {
+    // 192.0.2.1 (TEST-NET-1) isn't assigned to any interface
+    let ingress = DatagramIngress::bind("192.0.2.1:8000").unwrap();
+    assert!(ingress.try_recv().is_none());
+
+    let mut socket = RebindingSocket::new("192.0.2.1:0", bind_socket, "Test socket error").unwrap();
+    assert!(socket.socket().is_none());
+    assert_eq!(socket.errors(), 1);
+
+    // A socket whose network goes away is dropped and bound again after REBIND_INTERVAL
+    let mut socket = RebindingSocket::new("127.0.0.1:0", bind_socket, "Test socket error").unwrap();
+    socket.record_error("unable to receive", &io::Error::from_raw_os_error(ENODEV));
+    assert!(socket.socket().is_none());
+    thread::sleep(REBIND_INTERVAL);
+    assert!(socket.socket().is_some());
+
+    // Other bind errors still fail straight away
+    let taken = socket.bound().unwrap().local_addr().unwrap().to_string();
+    assert!(RebindingSocket::new(&taken, bind_socket, "Test socket error").is_err());
+}
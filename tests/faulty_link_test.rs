@@ -0,0 +1,194 @@
+//!
+//! Test the fault injection of the Faulty Link
+//!
+
+use ncomm::publisher_subscriber::{Publish, Receive};
+
+use packed_struct::PackedStruct;
+
+use robocup_base_station::config::{ConfigError, RadioConfig, RobotTable};
+use robocup_base_station::publishers::faulty_link::{FaultPhase, FaultScenario, FaultyLink};
+use robocup_base_station::publishers::radio_link::RadioLink;
+use robocup_base_station::radio_auth::ControlSigner;
+
+use robojackets_robocup_rtp::control_message::{ControlMessage, ControlMessageBuilder};
+use robojackets_robocup_rtp::robot_status_message::{RobotStatusMessage, RobotStatusMessageBuilder};
+use robojackets_robocup_rtp::Team;
+
+use rtic_nrf24l01::config::power_amplifier::PowerAmplifier;
+
+/// Radio Link where every robot acknowledges and responds to every Control Message
+#[derive(Default)]
+struct PerfectLink {
+    sent: Vec<ControlMessage>,
+    received: Vec<RobotStatusMessage>,
+}
+
+impl RadioLink for PerfectLink {
+    fn initialize(&mut self, _radio_config: &RadioConfig) -> bool { true }
+    fn set_channel(&mut self, _channel: u8) {}
+    fn set_pa_level(&mut self, _pa_level: PowerAmplifier) {}
+    fn set_robot_table(&mut self, _team: Team, _robot_table: &RobotTable) {}
+    fn set_signer(&mut self, _signer: Option<ControlSigner>) {}
+    fn send_status(&self) -> bool { true }
+
+    fn take_received(&mut self) -> Vec<RobotStatusMessage> {
+        self.received.drain(..).collect()
+    }
+}
+
+impl Publish<ControlMessage> for PerfectLink {
+    fn send(&mut self, data: ControlMessage) {
+        self.received.push(RobotStatusMessageBuilder::new().team(Team::Blue).robot_id(*data.robot_id).build());
+        self.sent.push(data);
+    }
+}
+
+impl Receive for PerfectLink {
+    fn update_data(&mut self) {}
+}
+
+fn control_message(robot_id: u8) -> ControlMessage {
+    ControlMessageBuilder::new().team(Team::Blue).robot_id(robot_id).body_x(1.0).build()
+}
+
+/// Send `count` Control Messages to robot 0 and return whether each was acknowledged
+fn send_all(link: &mut FaultyLink<PerfectLink>, count: usize) -> Vec<bool> {
+    (0..count).map(|_| {
+        link.send(control_message(0));
+        link.send_status()
+    }).collect()
+}
+
+#[test]
+/// Scenarios should be loaded from TOML and apply each phase until the next one starts
+fn test_parse_scenario() {
+    let scenario = FaultScenario::parse(r#"
+        seed = 7
+
+        [[phases]]
+        start = 10.0
+        drop_probability = 0.5
+
+        [[phases]]
+        start = 0.0
+        latency_ms = 2.0
+
+        [[silences]]
+        robot_id = 3
+        start = 1.0
+        duration = 2.0
+    "#).unwrap();
+
+    assert_eq!(scenario.seed, Some(7));
+    assert_eq!(scenario.phase_at(5.0).latency_ms, 2.0);
+    assert_eq!(scenario.phase_at(5.0).drop_probability, 0.0);
+    assert_eq!(scenario.phase_at(12.0).drop_probability, 0.5);
+    assert!(!scenario.is_silent(3, 0.5));
+    assert!(scenario.is_silent(3, 2.5));
+    assert!(!scenario.is_silent(2, 2.5));
+    assert!(!scenario.is_silent(3, 3.5));
+
+    let invalid = FaultScenario::parse("[[phases]]\ndrop_probability = 1.5\n");
+    assert!(matches!(invalid, Err(ConfigError::InvalidFaultScenario(_))));
+}
+
+#[test]
+/// The same seed should inject exactly the same faults
+fn test_seeded_faults_are_reproducible() {
+    let phase = FaultPhase { drop_probability: 0.3, ack_loss_probability: 0.2, ..Default::default() };
+    let mut first = FaultyLink::new(PerfectLink::default(), FaultScenario::constant(42, phase));
+    let mut second = FaultyLink::new(PerfectLink::default(), FaultScenario::constant(42, phase));
+
+    let first_statuses = send_all(&mut first, 200);
+    assert_eq!(first_statuses, send_all(&mut second, 200));
+    assert_eq!(first.stats(), second.stats());
+    assert!(first.stats().dropped > 0);
+    assert!(first.stats().acks_lost > 0);
+
+    first.restart();
+    assert_eq!(send_all(&mut first, 200), first_statuses);
+}
+
+#[test]
+/// Dropped Control Messages never reach the robot, lost acks do
+fn test_drops_and_lost_acks() {
+    let drop_all = FaultPhase { drop_probability: 1.0, ..Default::default() };
+    let mut link = FaultyLink::new(PerfectLink::default(), FaultScenario::constant(1, drop_all));
+    assert!(send_all(&mut link, 10).iter().all(|acked| !acked));
+    assert!(link.link().sent.is_empty());
+    assert_eq!(link.stats().dropped, 10);
+
+    let lose_acks = FaultPhase { ack_loss_probability: 1.0, ..Default::default() };
+    let mut link = FaultyLink::new(PerfectLink::default(), FaultScenario::constant(1, lose_acks));
+    assert!(send_all(&mut link, 10).iter().all(|acked| !acked));
+    assert_eq!(link.link().sent.len(), 10);
+    assert_eq!(link.take_received().len(), 10);
+    assert_eq!(link.stats().acks_lost, 10);
+}
+
+#[test]
+/// Once a burst starts, the following Control Messages should be dropped too
+fn test_burst_loss() {
+    let burst = FaultPhase { burst_probability: 0.05, burst_length: 8, ..Default::default() };
+    let mut link = FaultyLink::new(PerfectLink::default(), FaultScenario::constant(3, burst));
+    let statuses = send_all(&mut link, 2000);
+
+    let mut run = 0;
+    let mut runs = Vec::new();
+    for acked in statuses {
+        if acked {
+            if run > 0 {
+                runs.push(run);
+            }
+            run = 0;
+        } else {
+            run += 1;
+        }
+    }
+
+    assert!(!runs.is_empty());
+    assert!(runs.iter().all(|run| run % 8 == 0));
+    assert_eq!(link.stats().dropped, 0);
+}
+
+#[test]
+/// Corrupted Control Messages should differ from the original by a single bit
+fn test_corruption() {
+    let corrupt_all = FaultPhase { corrupt_probability: 1.0, ..Default::default() };
+    let mut link = FaultyLink::new(PerfectLink::default(), FaultScenario::constant(9, corrupt_all));
+    send_all(&mut link, 50);
+
+    assert_eq!(link.stats().corrupted, 50);
+    let original = control_message(0).pack().unwrap();
+    for corrupted in link.link().sent.iter() {
+        let corrupted = corrupted.pack().unwrap();
+        let flipped: u32 = original.iter().zip(corrupted.iter()).map(|(a, b)| (a ^ b).count_ones()).sum();
+        assert_eq!(flipped, 1);
+    }
+}
+
+#[test]
+/// Silent robots should neither receive Control Messages nor send statuses
+fn test_silent_robots() {
+    let scenario = FaultScenario::parse(r#"
+        seed = 5
+
+        [[silences]]
+        robot_id = 1
+        start = 0.0
+        duration = 60.0
+    "#).unwrap();
+    let mut link = FaultyLink::new(PerfectLink::default(), scenario);
+
+    link.send(control_message(1));
+    assert!(!link.send_status());
+    link.send(control_message(0));
+    assert!(link.send_status());
+
+    assert_eq!(link.link().sent.len(), 1);
+    let received = link.take_received();
+    assert_eq!(received.len(), 1);
+    assert_eq!(*received[0].robot_id, 0);
+    assert_eq!(link.stats().silenced, 1);
+}
@@ -0,0 +1,42 @@
+//!
+//! Test detecting when the Field Computer stops sending heartbeats
+//!
+
+use std::net::UdpSocket;
+use std::thread;
+use std::time::Duration;
+
+use robocup_base_station::field_link::{FieldLinkMonitor, LinkEvent};
+
+#[test]
+/// The link should be restored by heartbeats and lost once they stop
+fn test_field_link_lost_and_restored() {
+    let mut field_link_monitor = FieldLinkMonitor::new("127.0.0.1:0", Duration::from_millis(50));
+    let field_computer = UdpSocket::bind("127.0.0.1:0").unwrap();
+    field_computer.set_read_timeout(Some(Duration::from_millis(500))).unwrap();
+
+    assert_eq!(field_link_monitor.poll(), None);
+    assert!(!field_link_monitor.is_connected());
+
+    // The first heartbeat brings the link up and is echoed back
+    field_computer.send_to(&[1, 2, 3, 4], field_link_monitor.local_addr()).unwrap();
+    thread::sleep(Duration::from_millis(10));
+    assert!(matches!(field_link_monitor.poll(), Some(LinkEvent::Restored { down_for: None, .. })));
+    assert!(field_link_monitor.is_connected());
+
+    let mut buffer = [0u8; 16];
+    let (size, _) = field_computer.recv_from(&mut buffer).unwrap();
+    assert_eq!(&buffer[..size], &[1, 2, 3, 4]);
+
+    // Without heartbeats the link is lost after the timeout
+    thread::sleep(Duration::from_millis(80));
+    assert!(matches!(field_link_monitor.poll(), Some(LinkEvent::Lost { .. })));
+    assert!(!field_link_monitor.is_connected());
+    assert_eq!(field_link_monitor.poll(), None);
+
+    // Heartbeats resuming restores the link
+    field_computer.send_to(&[5], field_link_monitor.local_addr()).unwrap();
+    thread::sleep(Duration::from_millis(10));
+    assert!(matches!(field_link_monitor.poll(), Some(LinkEvent::Restored { down_for: Some(_), .. })));
+    assert_eq!(field_link_monitor.heartbeats_received(), 2);
+}
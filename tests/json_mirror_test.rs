@@ -0,0 +1,138 @@
+//!
+//! Test the JSON mirror of the Field Computer streams
+//!
+
+use std::io::{BufRead, BufReader};
+use std::net::{TcpStream, UdpSocket};
+use std::time::{Duration, SystemTime};
+
+use ncomm::publisher_subscriber::Publish;
+
+use robocup_base_station::alive_robots::AliveRobots;
+use robocup_base_station::field_link::LinkEvent;
+use robocup_base_station::json_mirror::{JsonMirror, JsonStreamServer, MirrorEvent};
+
+use robojackets_robocup_rtp::robot_status_message::RobotStatusMessageBuilder;
+use robojackets_robocup_rtp::Team;
+
+use serde_json::Value;
+
+#[test]
+/// Robot statuses should be mirrored with their decoded fields and timestamp
+fn test_robot_status_json() {
+    let status = RobotStatusMessageBuilder::new()
+        .team(Team::Blue)
+        .robot_id(3)
+        .kick_healthy(true)
+        .battery_voltage(12)
+        .build();
+    let received_at = SystemTime::UNIX_EPOCH + Duration::from_millis(1_234);
+
+    let line = MirrorEvent::robot_status(&status, received_at).to_json_line();
+    assert!(line.ends_with('\n'));
+
+    let json: Value = serde_json::from_str(&line).unwrap();
+    assert_eq!(json["type"], "robot_status");
+    assert_eq!(json["timestamp_ms"], 1_234);
+    assert_eq!(json["team"], "blue");
+    assert_eq!(json["robot_id"], 3);
+    assert_eq!(json["kick_healthy"], true);
+    assert_eq!(json["battery_voltage"], 12);
+
+    let status = RobotStatusMessageBuilder::new().team(Team::Yellow).robot_id(3).build();
+    let json: Value = serde_json::from_str(&MirrorEvent::robot_status(&status, received_at).to_json_line()).unwrap();
+    assert_eq!(json["team"], "yellow");
+}
+
+#[test]
+/// Alive robots and link events should be mirrored
+fn test_alive_robots_and_link_event_json() {
+    let mut alive_robots = AliveRobots::new(6);
+    alive_robots.set_alive(0, true);
+    alive_robots.set_alive(4, true);
+
+    let json: Value = serde_json::from_str(&MirrorEvent::alive_robots(&alive_robots, SystemTime::now()).to_json_line()).unwrap();
+    assert_eq!(json["type"], "alive_robots");
+    assert_eq!(json["num_robots"], 6);
+    assert_eq!(json["alive"], serde_json::json!([0, 4]));
+
+    let restored = LinkEvent::Restored { at: SystemTime::UNIX_EPOCH, down_for: Some(Duration::from_millis(250)) };
+    let json: Value = serde_json::from_str(&MirrorEvent::link_event(&restored).to_json_line()).unwrap();
+    assert_eq!(json["type"], "link_restored");
+    assert_eq!(json["down_for_ms"], 250);
+
+    let lost = LinkEvent::Lost { at: SystemTime::UNIX_EPOCH };
+    let json: Value = serde_json::from_str(&MirrorEvent::link_event(&lost).to_json_line()).unwrap();
+    assert_eq!(json["type"], "link_lost");
+}
+
+#[test]
+/// Every line should be sent as its own datagram over UDP
+fn test_udp_json_mirror() {
+    let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+    receiver.set_read_timeout(Some(Duration::from_secs(1))).unwrap();
+    let receiver_address = receiver.local_addr().unwrap().to_string();
+
+    let mut json_mirror = JsonMirror::udp("127.0.0.1:0", vec![&receiver_address]);
+    json_mirror.send(MirrorEvent::link_event(&LinkEvent::Lost { at: SystemTime::now() }));
+
+    let mut buffer = [0u8; 256];
+    let size = receiver.recv(&mut buffer).unwrap();
+    let json: Value = serde_json::from_slice(&buffer[..size]).unwrap();
+    assert_eq!(json["type"], "link_lost");
+}
+
+#[test]
+/// Every connected client should receive every line over TCP
+fn test_tcp_json_mirror() {
+    let mut server = JsonStreamServer::new("127.0.0.1:0");
+    let client = TcpStream::connect(server.local_addr()).unwrap();
+    client.set_read_timeout(Some(Duration::from_secs(1))).unwrap();
+
+    // Wait for the client to be accepted
+    for _ in 0..100 {
+        server.accept_clients();
+        if server.num_clients() == 1 {
+            break;
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    assert_eq!(server.num_clients(), 1);
+
+    let mut json_mirror = JsonMirror::Tcp(server);
+    json_mirror.send(MirrorEvent::alive_robots(&AliveRobots::new(2), SystemTime::now()));
+    json_mirror.send(MirrorEvent::link_event(&LinkEvent::Lost { at: SystemTime::now() }));
+
+    let mut lines = BufReader::new(client).lines();
+    let first: Value = serde_json::from_str(&lines.next().unwrap().unwrap()).unwrap();
+    let second: Value = serde_json::from_str(&lines.next().unwrap().unwrap()).unwrap();
+    assert_eq!(first["type"], "alive_robots");
+    assert_eq!(second["type"], "link_lost");
+}
+
+#[test]
+/// A client that stops reading should be disconnected instead of blocking the mirror
+fn test_tcp_json_mirror_stalled_client() {
+    let mut server = JsonStreamServer::new("127.0.0.1:0");
+    let _client = TcpStream::connect(server.local_addr()).unwrap();
+    for _ in 0..100 {
+        server.accept_clients();
+        if server.num_clients() == 1 {
+            break;
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    assert_eq!(server.num_clients(), 1);
+
+    // The client never reads, so the socket buffers fill and the lines back up
+    let line = MirrorEvent::alive_robots(&AliveRobots::new(16), SystemTime::now()).to_json_line();
+    let start = std::time::Instant::now();
+    for _ in 0..100_000 {
+        server.write_line(&line);
+        if server.num_clients() == 0 {
+            break;
+        }
+    }
+    assert_eq!(server.num_clients(), 0);
+    assert!(start.elapsed() < Duration::from_secs(5));
+}
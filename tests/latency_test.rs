@@ -0,0 +1,98 @@
+//!
+//! Test the end-to-end latency instrumentation
+//!
+
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use robocup_base_station::control_api::ControlCommand;
+use robocup_base_station::latency::{export_path, LatencyHistogram, LatencyStage, LatencyTracker};
+
+#[test]
+/// Percentiles should be rounded up to the end of their bucket
+fn test_histogram_percentiles() {
+    let mut histogram = LatencyHistogram::new(Duration::from_micros(100), 10);
+    assert_eq!(histogram.percentile(50.0), None);
+
+    for latency in 1..=100u64 {
+        histogram.record(Duration::from_micros(latency * 9));
+    }
+    histogram.record(Duration::from_millis(5));
+
+    assert_eq!(histogram.count(), 101);
+    assert_eq!(histogram.overflow(), 1);
+    assert_eq!(histogram.max(), Duration::from_millis(5));
+    assert_eq!(histogram.percentile(50.0), Some(Duration::from_micros(500)));
+    assert_eq!(histogram.percentile(95.0), Some(Duration::from_micros(900)));
+    assert_eq!(histogram.percentile(100.0), Some(Duration::from_millis(5)));
+}
+
+#[test]
+/// Each stage should be measured from the timestamps of a command
+fn test_tracker_stages() {
+    let mut tracker = LatencyTracker::new();
+    let received_at = Instant::now();
+    let transmit_at = received_at + Duration::from_micros(200);
+    let ack_at = transmit_at + Duration::from_micros(700);
+    let status_at = ack_at + Duration::from_micros(1_000);
+
+    tracker.transmit_started(3, Some(received_at), transmit_at);
+    tracker.acked(3, ack_at);
+    tracker.status_received(3, status_at);
+
+    let p50 = |stage| tracker.histogram(3, stage).unwrap().percentile(50.0).unwrap();
+    assert_eq!(p50(LatencyStage::Queue), Duration::from_micros(250));
+    assert_eq!(p50(LatencyStage::Radio), Duration::from_micros(750));
+    assert_eq!(p50(LatencyStage::Response), Duration::from_micros(1_050));
+    assert_eq!(p50(LatencyStage::RoundTrip), Duration::from_micros(1_750));
+    assert_eq!(p50(LatencyStage::EndToEnd), Duration::from_micros(1_950));
+
+    // Re-sending the same command shouldn't measure the queue or end to end latency again
+    tracker.transmit_started(3, Some(received_at), status_at);
+    tracker.status_received(3, status_at + Duration::from_micros(500));
+    assert_eq!(tracker.histogram(3, LatencyStage::Queue).unwrap().count(), 1);
+    assert_eq!(tracker.histogram(3, LatencyStage::EndToEnd).unwrap().count(), 1);
+    assert_eq!(tracker.histogram(3, LatencyStage::RoundTrip).unwrap().count(), 2);
+}
+
+#[test]
+/// Histograms should be exportable as CSV and summarized for the control API
+fn test_export() {
+    let mut tracker = LatencyTracker::new();
+    let transmit_at = Instant::now();
+    tracker.transmit_started(0, None, transmit_at);
+    tracker.status_received(0, transmit_at + Duration::from_micros(120));
+
+    let csv = tracker.to_csv();
+    let mut lines = csv.lines();
+    assert_eq!(lines.next(), Some("robot_id,stage,count,mean_us,p50_us,p95_us,p99_us,max_us,overflow"));
+    assert_eq!(lines.next(), Some("0,round_trip,1,120,150,150,150,120,0"));
+    assert_eq!(lines.next(), None);
+
+    assert_eq!(tracker.summary(LatencyStage::RoundTrip), "0:n=1,p50=150,p95=150,p99=150");
+    assert_eq!(tracker.summary(LatencyStage::EndToEnd), "");
+}
+
+#[test]
+/// Latency commands should be parsed
+fn test_parse_latency_commands() {
+    assert!(matches!(ControlCommand::parse("latency"), Ok(ControlCommand::Latency { stage: LatencyStage::EndToEnd })));
+    assert!(matches!(ControlCommand::parse("latency radio"), Ok(ControlCommand::Latency { stage: LatencyStage::Radio })));
+    assert!(matches!(ControlCommand::parse("latency reset"), Ok(ControlCommand::LatencyReset)));
+    match ControlCommand::parse("latency export /tmp/latency.csv") {
+        Ok(ControlCommand::LatencyExport { path }) => assert_eq!(path, "/tmp/latency.csv"),
+        _ => panic!("Unable to parse latency export"),
+    }
+    assert!(ControlCommand::parse("latency warp").is_err());
+}
+
+#[test]
+/// Exports requested over the control API should stay in the export directory
+fn test_export_path() {
+    let export_dir = Path::new("/var/log/base-station");
+    assert_eq!(export_path(export_dir, "latency.csv"), Ok(export_dir.join("latency.csv")));
+    assert!(export_path(export_dir, "/etc/passwd").is_err());
+    assert!(export_path(export_dir, "../latency.csv").is_err());
+    assert!(export_path(export_dir, "logs/latency.csv").is_err());
+    assert!(export_path(export_dir, "").is_err());
+}
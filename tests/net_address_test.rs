@@ -0,0 +1,74 @@
+//!
+//! Test hostname, IPv4 and IPv6 addresses
+//!
+
+use std::net::{SocketAddr, UdpSocket};
+use std::time::Duration;
+
+use ncomm::publisher_subscriber::Publish;
+
+use robocup_base_station::net_address::{socket_address, usable_address};
+use robocup_base_station::publishers::datagram::DatagramPublisher;
+
+#[test]
+/// IPv6 addresses should be wrapped in brackets
+fn test_socket_address() {
+    assert_eq!(socket_address("10.42.0.1", 8001), "10.42.0.1:8001");
+    assert_eq!(socket_address("field-computer.local", 8001), "field-computer.local:8001");
+    assert_eq!(socket_address("::", 8000), "[::]:8000");
+    assert_eq!(socket_address("fe80::1", 8001), "[fe80::1]:8001");
+    assert_eq!(socket_address("[fe80::1]", 8001), "[fe80::1]:8001");
+}
+
+#[test]
+/// IPv6 sockets should be able to reach IPv4 addresses
+fn test_usable_address() {
+    let v4: SocketAddr = "10.42.0.1:8001".parse().unwrap();
+    let v6: SocketAddr = "[fe80::1]:8001".parse().unwrap();
+    let local_v4: SocketAddr = "0.0.0.0:0".parse().unwrap();
+    let local_v6: SocketAddr = "[::]:0".parse().unwrap();
+
+    assert_eq!(usable_address(&[v6, v4], &local_v4), Some(v4));
+    assert_eq!(usable_address(&[v6], &local_v4), None);
+    assert_eq!(usable_address(&[v4, v6], &local_v6), Some(v6));
+    assert_eq!(usable_address(&[v4], &local_v6), Some("[::ffff:10.42.0.1]:8001".parse().unwrap()));
+}
+
+#[test]
+/// Destinations given as hostnames should be resolved
+fn test_send_to_hostname() {
+    let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+    receiver.set_read_timeout(Some(Duration::from_secs(1))).unwrap();
+    let receiver_address = socket_address("localhost", receiver.local_addr().unwrap().port());
+
+    let mut publisher = DatagramPublisher::new("127.0.0.1:0", vec![&receiver_address]);
+
+    // The hostname is resolved in the background, so the first datagrams may be dropped
+    publisher.send(vec![1u8, 2, 3]);
+    for _ in 0..100 {
+        if !publisher.poll_resolve() {
+            break;
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    publisher.send(vec![1u8, 2, 3]);
+
+    let mut buffer = [0u8; 8];
+    let size = receiver.recv(&mut buffer).unwrap();
+    assert_eq!(&buffer[..size], &[1, 2, 3]);
+}
+
+#[test]
+/// Datagrams should be sent over IPv6
+fn test_send_over_ipv6() {
+    let Ok(receiver) = UdpSocket::bind("[::1]:0") else { return };
+    receiver.set_read_timeout(Some(Duration::from_secs(1))).unwrap();
+    let receiver_address = socket_address("::1", receiver.local_addr().unwrap().port());
+
+    let mut publisher = DatagramPublisher::new("[::1]:0", vec![&receiver_address]);
+    publisher.send(vec![4u8, 5]);
+
+    let mut buffer = [0u8; 8];
+    let size = receiver.recv(&mut buffer).unwrap();
+    assert_eq!(&buffer[..size], &[4, 5]);
+}
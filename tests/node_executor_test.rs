@@ -0,0 +1,73 @@
+//!
+//! Test running nodes until ctrl-c and shutting them down
+//!
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use ncomm::node::Node;
+
+use robocup_base_station::node_executor::run_nodes;
+
+#[derive(Default)]
+struct Calls {
+    start: AtomicUsize,
+    update: AtomicUsize,
+    shutdown: AtomicUsize,
+}
+
+/// A node that counts every call the executor makes
+struct CountingNode {
+    update_delay: u128,
+    calls: Arc<Calls>,
+}
+
+impl Node for CountingNode {
+    fn name(&self) -> String { String::from("Counting Node") }
+
+    fn start(&mut self) {
+        self.calls.start.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn update(&mut self) {
+        self.calls.update.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn get_update_delay(&self) -> u128 {
+        self.update_delay
+    }
+
+    fn shutdown(&mut self) {
+        self.calls.shutdown.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn debug(&self) -> String { self.name() }
+}
+
+#[test]
+/// Every node should be started, updated and, once interrupted, shut down exactly once
+fn test_run_nodes_shuts_down() {
+    let fast_calls = Arc::new(Calls::default());
+    let slow_calls = Arc::new(Calls::default());
+    let mut fast_node = CountingNode { update_delay: 1, calls: fast_calls.clone() };
+    let mut slow_node = CountingNode { update_delay: 20, calls: slow_calls.clone() };
+
+    let (interrupt_tx, interrupt_rx) = mpsc::channel();
+    let interrupter = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(100));
+        interrupt_tx.send(true).unwrap();
+    });
+    run_nodes(&mut [&mut fast_node, &mut slow_node], interrupt_rx);
+    interrupter.join().unwrap();
+
+    for calls in [&fast_calls, &slow_calls] {
+        assert_eq!(calls.start.load(Ordering::Relaxed), 1);
+        assert!(calls.update.load(Ordering::Relaxed) >= 1);
+        assert_eq!(calls.shutdown.load(Ordering::Relaxed), 1);
+    }
+    // Each node is updated at its own rate
+    assert!(fast_calls.update.load(Ordering::Relaxed) > slow_calls.update.load(Ordering::Relaxed));
+}
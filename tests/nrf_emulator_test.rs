@@ -0,0 +1,330 @@
+//!
+//! Test the nRF24L01+ emulator and the radio configuration it ends up with
+//!
+
+use std::convert::Infallible;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use embedded_hal::blocking::spi::{Transfer, Write};
+use embedded_hal::digital::v2::OutputPin;
+
+use ncomm::publisher_subscriber::{Publish, Receive};
+
+use packed_struct::PackedStruct;
+
+use robocup_base_station::config::{AddressSet, RadioConfig, RobotTable};
+use robocup_base_station::nrf_emulator::*;
+use robocup_base_station::publishers::nrf_pubsub::NrfPublisherSubscriber;
+use robocup_base_station::publishers::radio_irq::RadioIrq;
+use robocup_base_station::publishers::radio_link::RadioLink;
+use robocup_base_station::radio_verify::verify_radio;
+
+use robojackets_robocup_rtp::control_message::{ControlMessageBuilder, CONTROL_MESSAGE_SIZE};
+use robojackets_robocup_rtp::robot_status_message::{RobotStatusMessageBuilder, ROBOT_STATUS_SIZE};
+use robojackets_robocup_rtp::{Team, BASE_STATION_ADDRESS};
+
+use rtic_nrf24l01::config::power_amplifier::PowerAmplifier;
+use rtic_nrf24l01::Radio;
+
+type EmulatedRadio = NrfPublisherSubscriber<EmulatedSpi, EmulatedPin, EmulatedPin, EmulatedDelay, Infallible, Infallible>;
+
+fn emulated_radio(emulator: &NrfEmulator, robot_table: &RobotTable) -> EmulatedRadio {
+    NrfPublisherSubscriber::new(
+        Radio::new(emulator.ce(), emulator.csn()),
+        emulator.spi(),
+        emulator.delay(),
+        robot_table,
+    )
+}
+
+/// Issue a single SPI command, returning the bytes clocked out
+fn command(emulator: &NrfEmulator, bytes: &[u8]) -> Vec<u8> {
+    let mut csn = emulator.csn();
+    let mut spi = emulator.spi();
+    let mut bytes = bytes.to_vec();
+    csn.set_low().unwrap();
+    spi.transfer(&mut bytes).unwrap();
+    csn.set_high().unwrap();
+    bytes
+}
+
+#[test]
+/// Registers should start at their reset values and be readable and writable over SPI
+fn test_registers() {
+    let emulator = NrfEmulator::new();
+    assert_eq!(emulator.register(CONFIG), 0x08);
+    assert_eq!(emulator.channel(), 2);
+    assert_eq!(emulator.pipe_address(0), [0xE7; 5]);
+    assert_eq!(emulator.pipe_address(2), [0xC3, 0xC2, 0xC2, 0xC2, 0xC2]);
+
+    command(&emulator, &[W_REGISTER | RF_CH, 106]);
+    assert_eq!(command(&emulator, &[R_REGISTER | RF_CH, NOP]), vec![0x0E, 106]);
+    assert_eq!(emulator.channel(), 106);
+
+    command(&emulator, &[W_REGISTER | TX_ADDR, 1, 2, 3, 4, 5]);
+    assert_eq!(command(&emulator, &[R_REGISTER | TX_ADDR, NOP, NOP, NOP, NOP, NOP]), vec![0x0E, 1, 2, 3, 4, 5]);
+
+    // The command and its data can be split across SPI calls
+    let mut csn = emulator.csn();
+    let mut spi = emulator.spi();
+    csn.set_low().unwrap();
+    spi.write(&[W_REGISTER | RF_SETUP]).unwrap();
+    spi.write(&[0x05]).unwrap();
+    csn.set_high().unwrap();
+    assert_eq!(emulator.pa_level(), PowerAmplifier::PAHigh);
+
+    // Using the bus without selecting the chip does nothing
+    spi.write(&[W_REGISTER | RF_CH, 5]).unwrap();
+    assert_eq!(emulator.channel(), 106);
+    assert_eq!(emulator.spi_errors(), 1);
+}
+
+#[test]
+/// Payloads should be transmitted while CE is high in PTX mode and set TX_DS or MAX_RT
+fn test_transmit() {
+    let emulator = NrfEmulator::new();
+    let mut ce = emulator.ce();
+    command(&emulator, &[W_REGISTER | CONFIG, 0x0E]);
+    command(&emulator, &[W_TX_PAYLOAD, 1, 2, 3]);
+    assert_eq!(emulator.register(FIFO_STATUS) & 0x10, 0);
+    assert!(emulator.take_transmissions().is_empty());
+
+    ce.set_high().unwrap();
+    ce.set_low().unwrap();
+    let transmissions = emulator.take_transmissions();
+    assert_eq!(transmissions.len(), 1);
+    assert_eq!(transmissions[0].payload, vec![1, 2, 3]);
+    assert!(transmissions[0].acked);
+    assert_eq!(command(&emulator, &[NOP])[0] & TX_DS, TX_DS);
+
+    // Interrupt flags are cleared by writing 1 to them
+    command(&emulator, &[W_REGISTER | STATUS, TX_DS]);
+    assert_eq!(emulator.register(STATUS) & TX_DS, 0);
+
+    // Unacknowledged payloads stay in the TX FIFO
+    emulator.set_ack(false);
+    command(&emulator, &[W_TX_PAYLOAD, 4, 5, 6]);
+    ce.set_high().unwrap();
+    assert_eq!(emulator.register(STATUS) & MAX_RT, MAX_RT);
+    assert_eq!(emulator.tx_fifo_len(), 1);
+    command(&emulator, &[FLUSH_TX]);
+    assert_eq!(emulator.tx_fifo_len(), 0);
+}
+
+#[test]
+/// Payloads should only be received on enabled pipes while listening
+fn test_receive() {
+    let emulator = NrfEmulator::new();
+    let mut ce = emulator.ce();
+    command(&emulator, &[W_REGISTER | (RX_PW_P0 + 1), 3]);
+    assert!(!emulator.receive([0xC2; 5], &[1, 2, 3]));
+
+    command(&emulator, &[W_REGISTER | CONFIG, 0x0F]);
+    ce.set_high().unwrap();
+    assert!(emulator.is_listening());
+    assert!(!emulator.receive([0xAA; 5], &[1, 2, 3]));
+    assert!(emulator.receive([0xC2; 5], &[1, 2, 3]));
+
+    let status = command(&emulator, &[NOP])[0];
+    assert_eq!(status & RX_DR, RX_DR);
+    assert_eq!((status >> 1) & 0b111, 1);
+    assert_eq!(command(&emulator, &[R_RX_PAYLOAD, NOP, NOP, NOP]), vec![status, 1, 2, 3]);
+    assert_eq!(emulator.register(FIFO_STATUS) & 0x01, 0x01);
+}
+
+#[test]
+/// Initializing the radio should leave it configured as intended
+fn test_initialize_configuration() {
+    let emulator = NrfEmulator::new();
+    let robot_table = RobotTable::from_default_addresses();
+    let mut radio = emulated_radio(&emulator, &robot_table);
+
+    let radio_config = RadioConfig { channel: 106, pa_level: PowerAmplifier::PAMax, ..Default::default() };
+    assert!(radio.initialize(&radio_config));
+
+    assert!(emulator.is_powered_up());
+    assert_eq!(emulator.channel(), 106);
+    assert_eq!(emulator.pa_level(), PowerAmplifier::PAMax);
+    assert!(emulator.pipe_enabled(1));
+    assert_eq!(emulator.pipe_address(1), BASE_STATION_ADDRESS);
+    assert_eq!(emulator.tx_address(), robot_table.radio_address(0).unwrap());
+    assert_eq!(emulator.payload_size(1), CONTROL_MESSAGE_SIZE as u8);
+    assert!(!emulator.ce_high());
+    assert!(emulator.csn_high());
+    assert_eq!(emulator.spi_errors(), 0);
+
+    radio.set_channel(90);
+    radio.set_pa_level(PowerAmplifier::PAMin);
+    assert_eq!(emulator.channel(), 90);
+    assert_eq!(emulator.pa_level(), PowerAmplifier::PAMin);
+}
+
+#[test]
+/// The radio should listen on and send to the addresses of the configured site
+fn test_site_addresses() {
+    let emulator = NrfEmulator::new();
+    let address_set = AddressSet::new(Team::Yellow, 3);
+    let robot_table = address_set.robot_table();
+    let mut radio = emulated_radio(&emulator, &robot_table);
+    radio.set_robot_table(Team::Yellow, &robot_table);
+
+    let radio_config = RadioConfig { base_station_address: address_set.base_station_address(), ..Default::default() };
+    assert!(radio.initialize(&radio_config));
+    assert_eq!(emulator.pipe_address(1), address_set.base_station_address());
+
+    radio.send(ControlMessageBuilder::new().team(Team::Yellow).robot_id(2).build());
+    assert!(radio.send_status());
+    assert_eq!(emulator.tx_address(), robot_table.radio_address(2).unwrap());
+    assert_ne!(emulator.tx_address(), RobotTable::from_default_addresses().radio_address(2).unwrap());
+}
+
+#[test]
+/// Sending a Control Message should transmit it to the robot and leave the radio listening
+/// for the robot's status
+fn test_send_and_listen() {
+    let emulator = NrfEmulator::new();
+    let robot_table = RobotTable::from_default_addresses();
+    let mut radio = emulated_radio(&emulator, &robot_table);
+    assert!(radio.initialize(&RadioConfig::default()));
+    emulator.take_transmissions();
+
+    let control_message = ControlMessageBuilder::new().team(Team::Blue).robot_id(1).body_x(2.0).build();
+    radio.send(control_message);
+    assert!(radio.send_status());
+
+    let transmissions = emulator.take_transmissions();
+    assert_eq!(transmissions.len(), 1);
+    assert_eq!(transmissions[0].address, robot_table.radio_address(1).unwrap());
+    assert_eq!(transmissions[0].channel, RadioConfig::default().channel);
+    assert_eq!(transmissions[0].payload, control_message.pack().unwrap().to_vec());
+    assert!(emulator.is_listening());
+    assert_eq!(emulator.payload_size(1), ROBOT_STATUS_SIZE as u8);
+
+    let status = RobotStatusMessageBuilder::new().team(Team::Blue).robot_id(1).battery_voltage(11).build();
+    assert!(emulator.receive(BASE_STATION_ADDRESS, &status.pack().unwrap()));
+    radio.update_data();
+    let received = radio.take_received();
+    assert_eq!(received.len(), 1);
+    assert_eq!(*received[0].robot_id, 1);
+    assert_eq!(emulator.rx_fifo_len(), 0);
+
+    // Robots that don't acknowledge the Control Message fail the send
+    emulator.set_ack(false);
+    radio.send(control_message);
+    assert!(!radio.send_status());
+    assert_eq!(emulator.spi_errors(), 0);
+}
+
+#[test]
+/// The IRQ line should be asserted while an unmasked interrupt flag is set
+fn test_irq_line() {
+    let emulator = NrfEmulator::new();
+    let mut irq = emulator.irq();
+    let mut ce = emulator.ce();
+    command(&emulator, &[W_REGISTER | (RX_PW_P0 + 1), 3]);
+    command(&emulator, &[W_REGISTER | CONFIG, 0x0F]);
+    ce.set_high().unwrap();
+    assert!(!irq.wait(Duration::from_millis(1)));
+
+    assert!(emulator.receive([0xC2; 5], &[1, 2, 3]));
+    assert!(emulator.irq_asserted());
+    assert!(irq.wait(Duration::ZERO));
+
+    // Masked flags stay off the IRQ line
+    command(&emulator, &[W_REGISTER | CONFIG, 0x0F | MASK_RX_DR]);
+    assert!(!emulator.irq_asserted());
+    command(&emulator, &[W_REGISTER | CONFIG, 0x0F]);
+    command(&emulator, &[W_REGISTER | STATUS, RX_DR]);
+    assert!(!emulator.irq_asserted());
+}
+
+#[test]
+/// The publisher subscriber should sleep on the IRQ line until a Robot Status Message
+/// arrives
+fn test_wait_for_data_on_irq() {
+    let emulator = NrfEmulator::new();
+    let robot_table = RobotTable::from_default_addresses();
+    let mut radio = emulated_radio(&emulator, &robot_table);
+    radio.set_irq(Box::new(emulator.irq()));
+    assert!(radio.initialize(&RadioConfig::default()));
+
+    radio.send(ControlMessageBuilder::new().team(Team::Blue).robot_id(1).build());
+    assert!(radio.send_status());
+    assert!(!radio.wait_for_data(Duration::from_millis(2)));
+
+    let robot = emulator.clone();
+    let status = RobotStatusMessageBuilder::new().team(Team::Blue).robot_id(1).build().pack().unwrap();
+    let handle = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(5));
+        robot.receive(BASE_STATION_ADDRESS, &status)
+    });
+
+    let start = Instant::now();
+    assert!(radio.wait_for_data(Duration::from_secs(1)));
+    assert!(start.elapsed() < Duration::from_secs(1));
+    assert!(handle.join().unwrap());
+    radio.update_data();
+    assert_eq!(radio.take_received().len(), 1);
+
+    // Reading the payload clears RX_DR and releases the line
+    assert!(!emulator.irq_asserted());
+}
+
+/// An IRQ line that is never asserted (e.g. a missed edge)
+struct MissedIrq;
+
+impl RadioIrq for MissedIrq {
+    fn wait(&mut self, timeout: Duration) -> bool {
+        thread::sleep(timeout);
+        false
+    }
+}
+
+#[test]
+/// A response that is waiting in the radio should still be read when the wait on the IRQ
+/// line times out
+fn test_exchange_polls_after_irq_timeout() {
+    let emulator = NrfEmulator::new();
+    let robot_table = RobotTable::from_default_addresses();
+    let mut radio = emulated_radio(&emulator, &robot_table);
+    radio.set_irq(Box::new(MissedIrq));
+    assert!(radio.initialize(&RadioConfig::default()));
+
+    let status = RobotStatusMessageBuilder::new().team(Team::Blue).robot_id(1).build().pack().unwrap().to_vec();
+    emulator.set_responder(Some(Box::new(move |_| Some((BASE_STATION_ADDRESS, status.clone())))));
+
+    let exchange = radio.exchange(ControlMessageBuilder::new().team(Team::Blue).robot_id(1).build(), Duration::from_millis(5));
+    assert!(exchange.acked_at.is_some());
+    assert_eq!(exchange.received.len(), 1);
+}
+
+#[test]
+/// The configuration read back after initializing should match the intended configuration
+/// and every field the chip didn't take should be reported
+fn test_verify_configuration() {
+    let emulator = NrfEmulator::new();
+    let robot_table = RobotTable::from_default_addresses();
+    let radio_config = RadioConfig {
+        channel: 90,
+        pa_level: PowerAmplifier::PAHigh,
+        ..RadioConfig::default()
+    };
+    let mut radio = emulated_radio(&emulator, &robot_table);
+    assert!(radio.initialize(&radio_config));
+
+    let registers = radio.read_registers().unwrap();
+    assert_eq!(registers.channel(), 90);
+    assert_eq!(registers.pa_level(), PowerAmplifier::PAHigh);
+    assert_eq!(registers.address_width(), 5);
+    assert!(registers.pipe_enabled(1));
+    assert_eq!(verify_radio(&mut radio, &radio_config), Some(Vec::new()));
+
+    // Tamper with the channel and disable the listening pipe
+    command(&emulator, &[W_REGISTER | RF_CH, 12]);
+    command(&emulator, &[W_REGISTER | EN_RXADDR, 0x01]);
+    let mismatches = verify_radio(&mut radio, &radio_config).unwrap();
+    let fields: Vec<&str> = mismatches.iter().map(|mismatch| mismatch.field).collect();
+    assert_eq!(fields, vec!["channel", "pipe_1_enabled"]);
+    assert_eq!(mismatches[0].to_string(), "channel is 12 (expected 90)");
+}
@@ -0,0 +1,175 @@
+//!
+//! Test the authentication of Control Messages
+//!
+
+use std::fs;
+use std::net::UdpSocket;
+use std::thread;
+use std::time::Duration;
+
+use ncomm::publisher_subscriber::Publish;
+
+use robocup_base_station::config::{ConfigError, RobotTable};
+use robocup_base_station::publishers::faulty_link::{FaultPhase, FaultScenario, FaultyLink};
+use robocup_base_station::publishers::radio_link::RadioLink;
+use robocup_base_station::publishers::virtual_radio::{VirtualLink, VirtualRadio};
+use robocup_base_station::radio_auth::{AuthError, AuthKey, ControlSigner, ControlVerifier, AUTHENTICATED_CONTROL_SIZE, COUNTER_RESERVATION};
+use robocup_base_station::robot_sim::{RobotSimulator, SimulatedRobot};
+
+use robojackets_robocup_rtp::control_message::ControlMessageBuilder;
+use robojackets_robocup_rtp::{Team, CONTROL_MESSAGE_SIZE};
+
+const KEY_HEX: &str = "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f";
+
+#[test]
+/// Keys should be parsed from exactly 64 hex digits
+fn test_parse_key() {
+    let key = AuthKey::from_hex(&format!("  {}\n", KEY_HEX)).unwrap();
+    let mut bytes = [0u8; 32];
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = i as u8;
+    }
+    assert_eq!(key, AuthKey::new(bytes));
+    assert_eq!(format!("{:?}", key), "AuthKey(..)");
+
+    assert!(matches!(AuthKey::from_hex("0011"), Err(ConfigError::InvalidAuthKey(_))));
+    assert!(matches!(AuthKey::from_hex(&KEY_HEX.replace('0', "g")), Err(ConfigError::InvalidAuthKey(_))));
+}
+
+#[test]
+/// The verifier should accept signed Control Messages once and reject forged ones
+fn test_sign_and_verify() {
+    let key = AuthKey::from_hex(KEY_HEX).unwrap();
+    let mut signer = ControlSigner::with_counter(key.clone(), 100);
+    let mut verifier = ControlVerifier::new(key);
+
+    let control_message = ControlMessageBuilder::new().team(Team::Blue).robot_id(2).body_x(1.0).build();
+    let payload = signer.sign(&control_message);
+    assert_eq!(signer.counter(), 101);
+    let verified = verifier.verify(&payload).unwrap();
+    assert_eq!(*verified.robot_id, 2);
+    assert_eq!(verifier.last_counter(2), Some(101));
+
+    // Replayed
+    assert!(matches!(verifier.verify(&payload), Err(AuthError::Replayed { counter: 101, last: 101 })));
+
+    // Tampered with Control Message, counter or tag
+    for byte in [0, CONTROL_MESSAGE_SIZE, AUTHENTICATED_CONTROL_SIZE - 1] {
+        let mut forged = signer.sign(&control_message);
+        forged[byte] ^= 0x01;
+        assert!(matches!(verifier.verify(&forged), Err(AuthError::BadTag)));
+    }
+
+    // Signed with another key
+    let mut other = ControlSigner::with_counter(AuthKey::new([7u8; 32]), 1_000);
+    assert!(matches!(verifier.verify(&other.sign(&control_message)), Err(AuthError::BadTag)));
+
+    // Unauthenticated
+    assert!(matches!(verifier.verify(&payload[..CONTROL_MESSAGE_SIZE]), Err(AuthError::Length(_))));
+
+    // Counters are tracked per robot so each robot only needs to remember its own
+    let payload = signer.sign(&control_message);
+    let other_robot = ControlMessageBuilder::new().team(Team::Blue).robot_id(3).build();
+    assert!(verifier.verify(&signer.sign(&other_robot)).is_ok());
+    assert!(verifier.verify(&payload).is_ok());
+}
+
+fn free_udp_address() -> String {
+    UdpSocket::bind("127.0.0.1:0").unwrap().local_addr().unwrap().to_string()
+}
+
+#[test]
+/// Simulated robots should only respond to the base station that has the key
+fn test_authenticated_virtual_radio() {
+    let key = AuthKey::from_hex(KEY_HEX).unwrap();
+    let robot_table = RobotTable::from_default_addresses();
+    let robot = robot_table.get(0).unwrap();
+
+    let base_station_address = free_udp_address();
+    let simulator_address = free_udp_address();
+    let robot_link = VirtualLink::open(&simulator_address, &base_station_address).unwrap();
+    let base_station_link = VirtualLink::open(&base_station_address, &simulator_address).unwrap();
+
+    let mut simulator = RobotSimulator::new(robot_link, robocup_base_station::CHANNEL, vec![SimulatedRobot::new(0, Team::Blue, robot.radio_address)]);
+    simulator.set_verifier(ControlVerifier::new(key.clone()));
+    let mut radio = VirtualRadio::new(base_station_link, &robot_table);
+    radio.set_ack_timeout(Duration::from_millis(200));
+
+    let control_message = ControlMessageBuilder::new().team(Team::Blue).robot_id(0).build();
+    let sim = thread::spawn(move || {
+        let first = simulator.poll(Duration::from_secs(1)).unwrap();
+        let second = simulator.poll(Duration::from_secs(1)).unwrap();
+        (first, second, simulator.rejected())
+    });
+
+    // Unauthenticated Control Messages are dropped
+    radio.send(control_message);
+    assert!(!radio.send_status());
+
+    radio.set_signer(Some(ControlSigner::new(key)));
+    radio.send(control_message);
+    assert!(radio.send_status());
+
+    assert_eq!(sim.join().unwrap(), (None, Some(0), 1));
+}
+
+#[test]
+/// The counter should start above the saved high-water mark even if the clock is behind it
+fn test_counter_file() {
+    let key = AuthKey::from_hex(KEY_HEX).unwrap();
+    let counter_file = std::env::temp_dir().join(format!("radio-auth-{}.counter", std::process::id()));
+    let _ = fs::remove_file(&counter_file);
+
+    // A new file reserves counters above the current time
+    let signer = ControlSigner::with_counter_file(key.clone(), &counter_file).unwrap();
+    let saved: u64 = fs::read_to_string(&counter_file).unwrap().trim().parse().unwrap();
+    assert_eq!(saved, signer.counter() + COUNTER_RESERVATION);
+
+    // A clock that is behind the saved counter continues from the counter
+    let ahead = u64::MAX / 2;
+    fs::write(&counter_file, ahead.to_string()).unwrap();
+    let mut restarted = ControlSigner::with_counter_file(key.clone(), &counter_file).unwrap();
+    let control_message = ControlMessageBuilder::new().team(Team::Blue).robot_id(0).build();
+    restarted.sign(&control_message);
+    assert_eq!(restarted.counter(), ahead + 1);
+    let saved: u64 = fs::read_to_string(&counter_file).unwrap().trim().parse().unwrap();
+    assert_eq!(saved, ahead + COUNTER_RESERVATION);
+
+    fs::write(&counter_file, "soon").unwrap();
+    assert!(matches!(ControlSigner::with_counter_file(key, &counter_file), Err(ConfigError::InvalidAuthCounter(_))));
+    fs::remove_file(&counter_file).unwrap();
+}
+
+#[test]
+/// Payloads corrupted on the air should fail verification instead of being signed after
+/// they were corrupted
+fn test_corrupted_payloads_are_rejected() {
+    let key = AuthKey::from_hex(KEY_HEX).unwrap();
+    let robot_table = RobotTable::from_default_addresses();
+    let robot = robot_table.get(0).unwrap();
+
+    let base_station_address = free_udp_address();
+    let simulator_address = free_udp_address();
+    let robot_link = VirtualLink::open(&simulator_address, &base_station_address).unwrap();
+    let base_station_link = VirtualLink::open(&base_station_address, &simulator_address).unwrap();
+
+    let mut simulator = RobotSimulator::new(robot_link, robocup_base_station::CHANNEL, vec![SimulatedRobot::new(0, Team::Blue, robot.radio_address)]);
+    simulator.set_verifier(ControlVerifier::new(key.clone()));
+    let mut radio = VirtualRadio::new(base_station_link, &robot_table);
+    radio.set_ack_timeout(Duration::from_millis(200));
+    radio.set_signer(Some(ControlSigner::new(key)));
+    let corrupt_all = FaultPhase { corrupt_probability: 1.0, ..Default::default() };
+    let mut link = FaultyLink::new(radio, FaultScenario::constant(5, corrupt_all));
+
+    let sim = thread::spawn(move || {
+        let responses: Vec<Option<u8>> = (0..3).map(|_| simulator.poll(Duration::from_secs(1)).unwrap()).collect();
+        (responses, simulator.rejected())
+    });
+
+    for _ in 0..3 {
+        link.send(ControlMessageBuilder::new().team(Team::Blue).robot_id(0).build());
+        assert!(!link.send_status());
+    }
+    assert_eq!(link.stats().corrupted, 3);
+    assert_eq!(sim.join().unwrap(), (vec![None, None, None], 3));
+}
@@ -0,0 +1,108 @@
+//!
+//! Test the radio benchmark matrix against the nRF24L01+ emulator
+//!
+
+use std::time::Duration;
+
+use packed_struct::PackedStruct;
+
+use robocup_base_station::config::RobotTable;
+use robocup_base_station::nrf_emulator::*;
+use robocup_base_station::radio_bench::{BenchMatrix, BenchReport, BenchSettings, DataRate, RadioBench, RetransmitSettings};
+
+use robojackets_robocup_rtp::robot_status_message::RobotStatusMessageBuilder;
+use robojackets_robocup_rtp::{Team, BASE_STATION_ADDRESS};
+
+use rtic_nrf24l01::config::power_amplifier::PowerAmplifier;
+
+fn settings(data_rate: DataRate, retransmits: &str) -> BenchSettings {
+    BenchSettings {
+        pa_level: PowerAmplifier::PAHigh,
+        channel: 110,
+        data_rate,
+        retransmits: RetransmitSettings::parse(retransmits).unwrap(),
+    }
+}
+
+#[test]
+/// Every combination of the settings should be benchmarked
+fn test_parse_matrix() {
+    let matrix = BenchMatrix::parse("low, max", "500:15,1500:5", 110, "2mbps").unwrap();
+    assert_eq!(matrix.pa_levels, vec![PowerAmplifier::PALow, PowerAmplifier::PAMax]);
+    assert_eq!(matrix.combinations().len(), 2 * 2);
+    assert!(matrix.combinations().iter().all(|settings| settings.channel == 110 && settings.data_rate == DataRate::Mbps2));
+
+    assert!(BenchMatrix::parse("loud", "500:15", 100, "1mbps").is_err());
+    assert!(BenchMatrix::parse("low", "500:15", 126, "1mbps").is_err());
+    assert!(BenchMatrix::parse("low", "500:15", 100, "3mbps").is_err());
+    assert!(BenchMatrix::parse("low", "300:15", 100, "1mbps").is_err());
+    assert!(BenchMatrix::parse("low", "500:16", 100, "1mbps").is_err());
+
+    assert_eq!(RetransmitSettings::parse("500:15").unwrap().setup_retr(), 0x1F);
+    assert_eq!(RetransmitSettings::parse("4000:0").unwrap().setup_retr(), 0xF0);
+}
+
+#[test]
+/// The radio should be configured with each combination and record retransmits and round
+/// trips
+fn test_run_against_emulator() {
+    let emulator = NrfEmulator::new();
+    let robot_address = RobotTable::from_default_addresses().radio_address(1).unwrap();
+    let mut bench = RadioBench::new(emulator.ce(), emulator.csn(), emulator.spi(), emulator.delay());
+    assert!(bench.begin());
+
+    let status = RobotStatusMessageBuilder::new().team(Team::Blue).robot_id(1).build().pack().unwrap().to_vec();
+    emulator.set_responder(Some(Box::new(move |_| Some((BASE_STATION_ADDRESS, status.clone())))));
+    emulator.set_retransmits(3);
+
+    let result = bench.run(&settings(DataRate::Mbps2, "500:15"), 1, robot_address, 20, Duration::ZERO, Duration::from_millis(5));
+    assert_eq!(emulator.channel(), 110);
+    assert_eq!(emulator.pa_level(), PowerAmplifier::PAHigh);
+    assert_eq!(emulator.register(RF_SETUP) & (RF_DR_LOW | RF_DR_HIGH), RF_DR_HIGH);
+    assert_eq!(emulator.register(SETUP_RETR), 0x1F);
+    assert_eq!(emulator.tx_address(), robot_address);
+
+    assert_eq!(result.sent, 20);
+    assert_eq!(result.acked, 20);
+    assert_eq!(result.ack_rate(), 1.0);
+    assert_eq!(result.retransmits, 60);
+    assert_eq!(result.max_retransmits, 3);
+    assert_eq!(result.responses, 20);
+    assert_eq!(result.round_trip.count(), 20);
+
+    // Robots that need more retransmits than allowed never acknowledge
+    let result = bench.run(&settings(DataRate::Kbps250, "250:2"), 1, robot_address, 10, Duration::ZERO, Duration::from_millis(1));
+    assert_eq!(emulator.register(RF_SETUP) & (RF_DR_LOW | RF_DR_HIGH), RF_DR_LOW);
+    assert_eq!(result.acked, 0);
+    assert_eq!(result.max_retransmits, 2);
+    assert_eq!(result.responses, 0);
+    assert_eq!(emulator.spi_errors(), 0);
+}
+
+#[test]
+/// Reports should have one row per combination in CSV and JSON
+fn test_report() {
+    let emulator = NrfEmulator::new();
+    let robot_address = RobotTable::from_default_addresses().radio_address(0).unwrap();
+    let mut bench = RadioBench::new(emulator.ce(), emulator.csn(), emulator.spi(), emulator.delay());
+    assert!(bench.begin());
+
+    let mut report = BenchReport::new(0);
+    for data_rate in [DataRate::Mbps1, DataRate::Mbps2] {
+        report.results.push(bench.run(&settings(data_rate, "500:15"), 0, robot_address, 5, Duration::ZERO, Duration::ZERO));
+    }
+
+    let csv = report.to_csv();
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines.len(), 3);
+    assert!(lines[0].starts_with("robot_id,pa_level,channel,data_rate"));
+    assert!(lines[1].starts_with("0,high,110,1mbps,500,15,5,5,1.0000"));
+    assert!(lines[2].starts_with("0,high,110,2mbps,500,15,5,5,1.0000"));
+
+    let json: serde_json::Value = serde_json::from_str(&report.to_json()).unwrap();
+    assert_eq!(json["robot_id"], 0);
+    assert_eq!(json["results"].as_array().unwrap().len(), 2);
+    assert_eq!(json["results"][1]["data_rate"], "2mbps");
+    assert_eq!(json["results"][0]["acked"], 5);
+    assert!(json["results"][0]["rtt_p50_us"].is_null());
+}
@@ -0,0 +1,82 @@
+//!
+//! Test the actors and the radio thread
+//!
+
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::thread;
+use std::time::Duration;
+
+use ncomm::publisher_subscriber::Receive;
+
+use packed_struct::PackedStruct;
+
+use robocup_base_station::actor::Actor;
+use robocup_base_station::config::{RadioConfig, RobotTable};
+use robocup_base_station::nrf_emulator::NrfEmulator;
+use robocup_base_station::publishers::nrf_pubsub::NrfPublisherSubscriber;
+use robocup_base_station::publishers::radio_link::RadioLink;
+use robocup_base_station::publishers::radio_thread::RadioThread;
+
+use robojackets_robocup_rtp::control_message::ControlMessageBuilder;
+use robojackets_robocup_rtp::robot_status_message::RobotStatusMessageBuilder;
+use robojackets_robocup_rtp::{Team, BASE_STATION_ADDRESS};
+
+use rtic_nrf24l01::Radio;
+
+#[test]
+/// Actors should run jobs in order on their own thread (with state that isn't Send)
+fn test_actor() {
+    let actor: Actor<Rc<RefCell<Vec<u8>>>> = Actor::spawn("test", || Ok(Rc::new(RefCell::new(Vec::new())))).unwrap();
+    let caller = thread::current().id();
+    assert_eq!(actor.call(move |_| thread::current().id() != caller), Some(true));
+
+    for value in 0..10 {
+        assert!(actor.cast(move |state| state.borrow_mut().push(value)));
+    }
+    assert_eq!(actor.call(|state| state.borrow().clone()), Some((0..10).collect()));
+
+    let failed = Actor::<u8>::spawn("failing", || Err(String::from("no radio")));
+    assert_eq!(failed.err(), Some(String::from("no radio")));
+}
+
+#[test]
+/// The radio thread should own the radio and run each exchange on its thread
+fn test_radio_thread_exchange() {
+    let emulator = NrfEmulator::new();
+    let robot_table = RobotTable::from_default_addresses();
+
+    let status = RobotStatusMessageBuilder::new().team(Team::Blue).robot_id(1).battery_voltage(11).build();
+    let response = status.pack().unwrap().to_vec();
+    emulator.set_responder(Some(Box::new(move |_| Some((BASE_STATION_ADDRESS, response.clone())))));
+
+    let radio_emulator = emulator.clone();
+    let radio_robot_table = robot_table.clone();
+    let mut radio = RadioThread::spawn(move || {
+        let radio = Radio::new(radio_emulator.ce(), radio_emulator.csn());
+        let link = NrfPublisherSubscriber::new(radio, radio_emulator.spi(), radio_emulator.delay(), &radio_robot_table);
+        Ok(Box::new(link) as Box<dyn RadioLink>)
+    }).unwrap();
+    assert!(radio.initialize(&RadioConfig::default()));
+    emulator.take_transmissions();
+
+    let control_message = ControlMessageBuilder::new().team(Team::Blue).robot_id(1).build();
+    let exchange = radio.exchange(control_message, Duration::from_millis(10));
+    assert!(exchange.acked());
+    assert!(radio.send_status());
+    assert_eq!(exchange.received.len(), 1);
+    assert_eq!(*exchange.received[0].0.robot_id, 1);
+    assert_eq!(emulator.take_transmissions().len(), 1);
+
+    // Unacknowledged Control Messages get no response
+    emulator.set_ack(false);
+    let exchange = radio.exchange(control_message, Duration::from_millis(2));
+    assert!(!exchange.acked());
+    assert!(!radio.send_status());
+    assert!(exchange.received.is_empty());
+
+    // Jobs run in order so the channel is set by the time the radio has been polled
+    radio.set_channel(90);
+    radio.update_data();
+    assert_eq!(emulator.channel(), 90);
+}
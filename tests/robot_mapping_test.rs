@@ -0,0 +1,74 @@
+//!
+//! Test remapping logical robot ids to physical robots and the control API commands
+//!
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpStream;
+use std::thread;
+use std::time::Duration;
+
+use robocup_base_station::control_api::{ControlCommand, ControlServer};
+use robocup_base_station::robot_mapping::RobotIdMap;
+
+#[test]
+/// Remapped robots should be addressed and reported under their new ids
+fn test_remap_robot() {
+    let mut robot_id_map = RobotIdMap::new();
+    assert_eq!(robot_id_map.physical(2), 2);
+    assert_eq!(robot_id_map.logical(2), Some(2));
+
+    robot_id_map.set(2, 7).unwrap();
+    assert_eq!(robot_id_map.physical(2), 7);
+    assert_eq!(robot_id_map.logical(7), Some(2));
+    // The broken robot is no longer in play
+    assert_eq!(robot_id_map.logical(2), None);
+    assert_eq!(robot_id_map.mappings(), vec![(2, 7)]);
+
+    // The spare can't be used by two robots at once
+    assert!(robot_id_map.set(3, 7).is_err());
+
+    robot_id_map.reset(2).unwrap();
+    assert_eq!(robot_id_map.physical(2), 2);
+    assert_eq!(robot_id_map.logical(2), Some(2));
+    assert!(robot_id_map.mappings().is_empty());
+
+    let history = robot_id_map.history();
+    assert_eq!(history.len(), 2);
+    assert_eq!((history[0].previous_physical_id, history[0].physical_id), (2, 7));
+    assert_eq!((history[1].previous_physical_id, history[1].physical_id), (7, 2));
+}
+
+#[test]
+/// Control commands should be parsed from a single line
+fn test_parse_control_commands() {
+    assert_eq!(ControlCommand::parse("remap 1 8\n"), Ok(ControlCommand::Remap { logical_id: 1, physical_id: 8 }));
+    assert_eq!(ControlCommand::parse("unmap 1"), Ok(ControlCommand::Unmap { logical_id: 1 }));
+    assert_eq!(ControlCommand::parse("mappings"), Ok(ControlCommand::Mappings));
+    assert!(ControlCommand::parse("remap one 8").is_err());
+    assert!(ControlCommand::parse("launch").is_err());
+}
+
+#[test]
+/// Commands sent over TCP should be received and answered
+fn test_control_server() {
+    let mut control_server = ControlServer::new("127.0.0.1:0");
+    let mut client = TcpStream::connect(control_server.local_addr()).unwrap();
+    client.set_read_timeout(Some(Duration::from_millis(500))).unwrap();
+    client.write_all(b"remap 0 6\n").unwrap();
+
+    let mut requests = Vec::new();
+    for _ in 0..50 {
+        requests = control_server.poll();
+        if !requests.is_empty() {
+            break;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].command, Ok(ControlCommand::Remap { logical_id: 0, physical_id: 6 }));
+
+    control_server.respond(requests[0].client, "ok");
+    let mut response = String::new();
+    BufReader::new(client).read_line(&mut response).unwrap();
+    assert_eq!(response, "ok\n");
+}
@@ -0,0 +1,128 @@
+//!
+//! Test loading the robot table and encoding alive robots
+//!
+
+use robocup_base_station::alive_robots::{AliveRobots, AliveRobotsEncoding};
+use robocup_base_station::config::{max_protocol_robots, robot_id_representable, team_from_bit, AddressSet, ConfigError, RobotTable};
+
+use robojackets_robocup_rtp::{Team, BASE_STATION_ADDRESS, TEAM};
+
+#[test]
+/// Robots should be loaded from TOML with enabled defaulting to true
+fn test_parse_robot_table() {
+    let robot_table = RobotTable::parse(r#"
+        [[robots]]
+        id = 0
+        radio_address = [0xC3, 0xC3, 0xC3, 0xC3, 0xC1]
+
+        [[robots]]
+        id = 1
+        radio_address = [0xC3, 0xC3, 0xC3, 0xC3, 0xC2]
+        enabled = false
+    "#).unwrap();
+
+    assert_eq!(robot_table.robots.len(), 2);
+    assert_eq!(robot_table.radio_address(0), Some([0xC3, 0xC3, 0xC3, 0xC3, 0xC1]));
+    assert!(robot_table.is_enabled(0));
+    assert!(!robot_table.is_enabled(1));
+    assert_eq!(robot_table.radio_address(1), None);
+    assert!(robot_table.check_robots_in_play(2).is_ok());
+    assert!(matches!(robot_table.check_robots_in_play(3), Err(ConfigError::MissingRobot(2))));
+}
+
+#[test]
+/// Duplicate and unrepresentable robot ids should be rejected
+fn test_reject_invalid_robot_table() {
+    let duplicate = RobotTable::parse(r#"
+        [[robots]]
+        id = 0
+        radio_address = [0xC3, 0xC3, 0xC3, 0xC3, 0xC1]
+
+        [[robots]]
+        id = 0
+        radio_address = [0xC3, 0xC3, 0xC3, 0xC3, 0xC2]
+    "#);
+    assert!(matches!(duplicate, Err(ConfigError::DuplicateRobot(0))));
+
+    let max_robots = max_protocol_robots();
+    assert!(robot_id_representable(0));
+    if max_robots <= u8::MAX as usize {
+        let unrepresentable = RobotTable::parse(&format!(r#"
+            [[robots]]
+            id = {}
+            radio_address = [0xC3, 0xC3, 0xC3, 0xC3, 0xC1]
+        "#, max_robots));
+        assert!(matches!(unrepresentable, Err(ConfigError::UnrepresentableRobotId(_))));
+    }
+}
+
+#[test]
+/// rtp's robot id is 4 bits, so no more than 16 robots can be in play whatever the alive
+/// robots encoding
+fn test_max_protocol_robots() {
+    assert_eq!(max_protocol_robots(), 16);
+    assert!(!robot_id_representable(16));
+
+    let too_many = ConfigError::TooManyRobots { requested: 20, max: max_protocol_robots() };
+    assert!(too_many.to_string().contains("can only address 16 robots"));
+}
+
+#[test]
+/// The team bit of a message on the air should match rtp's team encoding
+fn test_team_from_bit() {
+    assert_eq!(team_from_bit(Team::Blue.into()), Team::Blue);
+    assert_eq!(team_from_bit(Team::Yellow.into()), Team::Yellow);
+}
+
+#[test]
+/// More than 16 robots should survive the variable length encoding
+fn test_variable_alive_robots_encoding() {
+    let mut alive_robots = AliveRobots::new(20);
+    alive_robots.set_alive(0, true);
+    alive_robots.set_alive(17, true);
+    alive_robots.set_alive(25, true);
+
+    let encoded = alive_robots.encode(AliveRobotsEncoding::Variable);
+    assert_eq!(encoded, vec![20, 0b0000_0001, 0b0000_0000, 0b0000_0010]);
+
+    let decoded = AliveRobots::decode(&encoded).unwrap();
+    assert_eq!(decoded.alive_ids(), vec![0, 17]);
+    assert!(AliveRobots::decode(&[20, 0]).is_none());
+}
+
+#[test]
+/// The legacy encoding should match the original u16 bitmask
+fn test_legacy_alive_robots_encoding() {
+    let mut alive_robots = AliveRobots::new(6);
+    alive_robots.set_alive(1, true);
+    alive_robots.set_alive(5, true);
+
+    let encoded = alive_robots.encode(AliveRobotsEncoding::Legacy);
+    assert_eq!(u16::from_le_bytes([encoded[0], encoded[1]]), 0b10_0010);
+    assert_eq!(AliveRobotsEncoding::Legacy.max_robots(), 16);
+}
+
+#[test]
+/// Every team and site should get its own radio addresses (site 0 being the defaults)
+fn test_address_sets() {
+    let default = AddressSet::default();
+    assert!(default.is_default());
+    assert_eq!(default.base_station_address(), BASE_STATION_ADDRESS);
+    assert_eq!(default.robot_table(), RobotTable::from_default_addresses());
+
+    let site = AddressSet::new(TEAM, 3);
+    assert!(!site.is_default());
+    assert_eq!(site.robot_table().robots.len(), RobotTable::from_default_addresses().robots.len());
+    assert!(site.robot_table().validate().is_ok());
+
+    let mut addresses = Vec::new();
+    for address_set in [site, AddressSet::new(TEAM, 4), AddressSet::new(Team::Blue, 5), AddressSet::new(Team::Yellow, 5)] {
+        addresses.push(address_set.base_station_address());
+        addresses.extend(address_set.robot_table().robots.iter().map(|robot| robot.radio_address));
+    }
+    assert!(!addresses.contains(&BASE_STATION_ADDRESS));
+    let mut unique = addresses.clone();
+    unique.sort();
+    unique.dedup();
+    assert_eq!(unique.len(), addresses.len());
+}
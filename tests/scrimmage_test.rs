@@ -0,0 +1,192 @@
+//!
+//! Test serving both teams from one base station (dual-team mode)
+//!
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream, UdpSocket};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use ncomm::node::Node;
+
+use packed_struct::PackedStruct;
+
+use robocup_base_station::config::{team_from_bit, AddressSet, RadioConfig, RobotTable};
+use robocup_base_station::one_radio::radio_node::RadioNode;
+use robocup_base_station::one_radio::scrimmage_node::ScrimmageNode;
+use robocup_base_station::publishers::radio_link::RadioLink;
+use robocup_base_station::publishers::radio_thread::RadioThread;
+use robocup_base_station::publishers::virtual_radio::{VirtualLink, VirtualRadio};
+use robocup_base_station::robot_sim::{RobotSimulator, SimulatedRobot};
+use robocup_base_station::validation::RejectionReason;
+
+use robojackets_robocup_rtp::control_message::ControlMessageBuilder;
+use robojackets_robocup_rtp::robot_status_message::RobotStatusMessageBuilder;
+use robojackets_robocup_rtp::Team;
+
+fn free_udp_address() -> String {
+    UdpSocket::bind("127.0.0.1:0").unwrap().local_addr().unwrap().to_string()
+}
+
+fn free_tcp_address() -> String {
+    TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().to_string()
+}
+
+fn team_node(team: Team, robot_table: &RobotTable, radio_link: RadioThread, control_message_address: &str, control_api_address: &str) -> RadioNode<'static, RadioThread> {
+    let control_message_address: &'static str = control_message_address.to_string().leak();
+    let control_api_address: &'static str = control_api_address.to_string().leak();
+    RadioNode::new(
+        team,
+        1,
+        robot_table.clone(),
+        RadioConfig::default(),
+        radio_link,
+        control_message_address,
+        "127.0.0.1:0",
+        vec!["127.0.0.1:9"],
+        "127.0.0.1:0",
+        control_api_address,
+    )
+}
+
+/// Send a command to a control API served by the scrimmage node and wait for the response
+fn control_command(scrimmage_node: &mut ScrimmageNode<'static, RadioThread>, control_api_address: &str, command: &str) -> String {
+    let mut client = TcpStream::connect(control_api_address).unwrap();
+    client.set_read_timeout(Some(Duration::from_millis(20))).unwrap();
+    client.write_all(format!("{}\n", command).as_bytes()).unwrap();
+
+    let mut reader = BufReader::new(client);
+    let mut response = String::new();
+    for _ in 0..50 {
+        scrimmage_node.update();
+        if reader.read_line(&mut response).is_ok_and(|size| size > 0) {
+            break;
+        }
+    }
+    response.trim_end().to_string()
+}
+
+/// A radio thread on a virtual radio nothing answers, initialized like the base station does
+fn unanswered_radio_link(robot_table: &RobotTable) -> RadioThread {
+    let bind_address = free_udp_address();
+    let peer_address = free_udp_address();
+    let robot_table = robot_table.clone();
+    let mut radio_link = RadioThread::spawn(move || {
+        let link = VirtualLink::open(&bind_address, &peer_address).map_err(|err| err.to_string())?;
+        Ok(Box::new(VirtualRadio::new(link, &robot_table)) as Box<dyn RadioLink>)
+    }).unwrap();
+    assert!(radio_link.initialize(&RadioConfig::default()));
+    radio_link
+}
+
+#[test]
+/// Robots of both teams should be commanded over the same radio, each team only through
+/// its own ports
+fn test_scrimmage_node() {
+    let blue_table = AddressSet::new(Team::Blue, 7).robot_table();
+    let yellow_table = AddressSet::new(Team::Yellow, 7).robot_table();
+    assert_ne!(blue_table.radio_address(0), yellow_table.radio_address(0));
+
+    // Simulated robot 0 of each team
+    let base_station_address = free_udp_address();
+    let simulator_address = free_udp_address();
+    let robots = vec![
+        SimulatedRobot::new(0, Team::Blue, blue_table.radio_address(0).unwrap()),
+        SimulatedRobot::new(0, Team::Yellow, yellow_table.radio_address(0).unwrap()),
+    ];
+    let mut simulator = RobotSimulator::new(VirtualLink::open(&simulator_address, &base_station_address).unwrap(), RadioConfig::default().channel, robots);
+    let stop = Arc::new(AtomicBool::new(false));
+    let stop_simulator = stop.clone();
+    let handle = thread::spawn(move || {
+        while !stop_simulator.load(Ordering::Relaxed) {
+            simulator.poll(Duration::from_millis(10)).unwrap();
+        }
+        simulator.robots().iter().map(|robot| (robot.team, robot.commands_received)).collect::<Vec<_>>()
+    });
+
+    let radio_blue_table = blue_table.clone();
+    let mut radio_link = RadioThread::spawn(move || {
+        let link = VirtualLink::open(&base_station_address, &simulator_address).map_err(|err| err.to_string())?;
+        Ok(Box::new(VirtualRadio::new(link, &radio_blue_table)) as Box<dyn RadioLink>)
+    }).unwrap();
+    assert!(radio_link.initialize(&RadioConfig::default()));
+
+    let blue_address = free_udp_address();
+    let yellow_address = free_udp_address();
+    let blue = team_node(Team::Blue, &blue_table, radio_link.clone(), &blue_address, "127.0.0.1:0");
+    let yellow = team_node(Team::Yellow, &yellow_table, radio_link, &yellow_address, "127.0.0.1:0");
+    let mut scrimmage_node = ScrimmageNode::new(blue, yellow);
+
+    let field_computer = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let blue_command = ControlMessageBuilder::new().team(Team::Blue).robot_id(0).body_x(1.0).build();
+    let yellow_command = ControlMessageBuilder::new().team(Team::Yellow).robot_id(0).body_x(1.0).build();
+    field_computer.send_to(&blue_command.pack().unwrap(), &blue_address).unwrap();
+    field_computer.send_to(&yellow_command.pack().unwrap(), &yellow_address).unwrap();
+    thread::sleep(Duration::from_millis(50));
+
+    for _ in 0..10 {
+        scrimmage_node.update();
+        if scrimmage_node.radio_nodes().iter().all(|radio_node| radio_node.status_cache().len() == 1) {
+            break;
+        }
+    }
+    for radio_node in scrimmage_node.radio_nodes() {
+        let status = radio_node.status_cache().get(0).expect("no status from robot 0");
+        assert_eq!(team_from_bit(status.status.team), radio_node.team());
+    }
+
+    // A Control Message for the yellow team sent to the blue team's port is rejected
+    field_computer.send_to(&yellow_command.pack().unwrap(), &blue_address).unwrap();
+    thread::sleep(Duration::from_millis(50));
+    scrimmage_node.update();
+    assert_eq!(scrimmage_node.radio_nodes()[0].rejections(RejectionReason::WrongTeam), 1);
+
+    stop.store(true, Ordering::Relaxed);
+    let commands = handle.join().unwrap();
+    assert!(commands.iter().all(|(_, commands_received)| *commands_received >= 1));
+}
+
+#[test]
+/// Only the first team's control API should change the shared radio and the second team's
+/// Radio Node should follow the change
+fn test_scrimmage_shares_radio_settings() {
+    let blue_table = AddressSet::new(Team::Blue, 7).robot_table();
+    let yellow_table = AddressSet::new(Team::Yellow, 7).robot_table();
+    let radio_link = unanswered_radio_link(&blue_table);
+
+    let blue_api_address = free_tcp_address();
+    let yellow_api_address = free_tcp_address();
+    let blue = team_node(Team::Blue, &blue_table, radio_link.clone(), &free_udp_address(), &blue_api_address);
+    let yellow = team_node(Team::Yellow, &yellow_table, radio_link, &free_udp_address(), &yellow_api_address);
+    let mut scrimmage_node = ScrimmageNode::new(blue, yellow);
+
+    assert!(control_command(&mut scrimmage_node, &yellow_api_address, "set channel 10").starts_with("error the radio is shared"));
+    assert!(control_command(&mut scrimmage_node, &yellow_api_address, "reinit").starts_with("error the radio is shared"));
+    assert!(scrimmage_node.radio_nodes().iter().all(|radio_node| radio_node.radio_config().channel == RadioConfig::default().channel));
+
+    assert_eq!(control_command(&mut scrimmage_node, &blue_api_address, "set channel 10"), "ok");
+    assert!(scrimmage_node.radio_nodes().iter().all(|radio_node| radio_node.radio_config().channel == 10));
+}
+
+#[test]
+/// Statuses from the other team's robots should be kept for the other team's Radio Node
+fn test_other_team_statuses() {
+    let blue_table = AddressSet::new(Team::Blue, 7).robot_table();
+    let mut blue = team_node(Team::Blue, &blue_table, unanswered_radio_link(&blue_table), &free_udp_address(), "127.0.0.1:0");
+
+    // Without a scrimmage the other team's statuses are dropped
+    let yellow_status = RobotStatusMessageBuilder::new().team(Team::Yellow).robot_id(0).build();
+    blue.handle_status(yellow_status, Instant::now());
+    assert!(blue.take_other_team_statuses().is_empty());
+
+    blue.keep_other_team_statuses();
+    blue.handle_status(yellow_status, Instant::now());
+    blue.handle_status(RobotStatusMessageBuilder::new().team(Team::Blue).robot_id(0).build(), Instant::now());
+    let statuses = blue.take_other_team_statuses();
+    assert_eq!(statuses.len(), 1);
+    assert_eq!(team_from_bit(statuses[0].0.team), Team::Yellow);
+    assert!(blue.take_other_team_statuses().is_empty());
+    assert!(blue.status_cache().get(0).is_some());
+}
@@ -0,0 +1,80 @@
+//!
+//! Test the latest robot status cache and its snapshot query protocol
+//!
+
+use std::net::UdpSocket;
+use std::time::{Duration, UNIX_EPOCH};
+
+use robocup_base_station::status_cache::{decode_snapshot, RobotStatusCache, StatusSnapshotServer};
+
+use robojackets_robocup_rtp::{RobotStatusMessageBuilder, Team};
+
+#[test]
+/// The cache should only keep the latest status for each robot
+fn test_cache_keeps_latest_status() {
+    let mut cache = RobotStatusCache::new();
+
+    cache.update_at(
+        RobotStatusMessageBuilder::new().team(Team::Blue).robot_id(1).battery_voltage(10).build(),
+        UNIX_EPOCH + Duration::from_millis(1_000),
+    );
+    cache.update_at(
+        RobotStatusMessageBuilder::new().team(Team::Blue).robot_id(0).battery_voltage(11).build(),
+        UNIX_EPOCH + Duration::from_millis(2_000),
+    );
+    cache.update_at(
+        RobotStatusMessageBuilder::new().team(Team::Blue).robot_id(1).battery_voltage(12).build(),
+        UNIX_EPOCH + Duration::from_millis(3_000),
+    );
+
+    assert_eq!(cache.len(), 2);
+    let latest = cache.get(1).unwrap();
+    assert_eq!(latest.received_at, UNIX_EPOCH + Duration::from_millis(3_000));
+
+    let snapshot = cache.snapshot();
+    assert_eq!(*snapshot[0].status.robot_id, 0);
+    assert_eq!(*snapshot[1].status.robot_id, 1);
+}
+
+#[test]
+/// A snapshot should survive being encoded and decoded
+fn test_snapshot_encoding_round_trip() {
+    let mut cache = RobotStatusCache::new();
+    for robot_id in 0..6 {
+        cache.update_at(
+            RobotStatusMessageBuilder::new().team(Team::Blue).robot_id(robot_id).build(),
+            UNIX_EPOCH + Duration::from_millis(1_000 * robot_id as u64),
+        );
+    }
+
+    let decoded = decode_snapshot(&cache.encode_snapshot()).unwrap();
+    assert_eq!(decoded.len(), 6);
+    for (robot_id, cached) in decoded.iter().enumerate() {
+        assert_eq!(*cached.status.robot_id as usize, robot_id);
+        assert_eq!(cached.received_at, UNIX_EPOCH + Duration::from_millis(1_000 * robot_id as u64));
+    }
+
+    assert!(decode_snapshot(&[]).is_none());
+    assert!(decode_snapshot(&[2, 0, 0]).is_none());
+}
+
+#[test]
+/// A client should receive the full snapshot after sending a request
+fn test_snapshot_server_answers_requests() {
+    let mut cache = RobotStatusCache::new();
+    cache.update(RobotStatusMessageBuilder::new().team(Team::Blue).robot_id(3).build());
+
+    let mut server = StatusSnapshotServer::new("127.0.0.1:0");
+    let client = UdpSocket::bind("127.0.0.1:0").unwrap();
+    client.set_read_timeout(Some(Duration::from_millis(500))).unwrap();
+    client.send_to(&[0], server.local_addr()).unwrap();
+
+    std::thread::sleep(Duration::from_millis(10));
+    server.poll(&cache);
+
+    let mut buffer = [0u8; 512];
+    let (size, _) = client.recv_from(&mut buffer).unwrap();
+    let snapshot = decode_snapshot(&buffer[..size]).unwrap();
+    assert_eq!(snapshot.len(), 1);
+    assert_eq!(*snapshot[0].status.robot_id, 3);
+}
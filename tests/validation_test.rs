@@ -0,0 +1,165 @@
+//!
+//! Test that malformed Control Messages are rejected or clamped before reaching the radio
+//!
+
+use robocup_base_station::config::{RobotConfig, RobotTable};
+use robocup_base_station::validation::{ControlMessageValidator, RejectionReason, ValidationLimits};
+
+use robojackets_robocup_rtp::control_message::ControlMessageBuilder;
+use robojackets_robocup_rtp::Team;
+
+#[test]
+/// Messages for robots that aren't in play should be rejected
+fn test_reject_invalid_robot_id() {
+    let mut validator = ControlMessageValidator::new(Team::Blue, 2, RobotTable::from_default_addresses(), ValidationLimits::default());
+
+    let control_message = ControlMessageBuilder::new()
+        .team(Team::Blue)
+        .robot_id(4)
+        .build();
+
+    assert_eq!(validator.validate(control_message).err(), Some(RejectionReason::InvalidRobotId));
+    assert_eq!(validator.rejections(RejectionReason::InvalidRobotId), 1);
+}
+
+#[test]
+/// Messages for robots disabled in the robot table should be rejected
+fn test_reject_disabled_robot() {
+    let robot_table = RobotTable {
+        robots: vec![RobotConfig { id: 0, radio_address: [0xC3, 0xC3, 0xC3, 0xC3, 0xC1], enabled: false }],
+    };
+    let mut validator = ControlMessageValidator::new(Team::Blue, 1, robot_table, ValidationLimits::default());
+
+    let control_message = ControlMessageBuilder::new()
+        .team(Team::Blue)
+        .robot_id(0)
+        .build();
+
+    assert_eq!(validator.validate(control_message).err(), Some(RejectionReason::RobotDisabled));
+}
+
+#[test]
+/// Messages for the other team should be rejected
+fn test_reject_wrong_team() {
+    let mut validator = ControlMessageValidator::new(Team::Blue, 6, RobotTable::from_default_addresses(), ValidationLimits::default());
+
+    let control_message = ControlMessageBuilder::new()
+        .team(Team::Yellow)
+        .robot_id(0)
+        .build();
+
+    assert_eq!(validator.validate(control_message).err(), Some(RejectionReason::WrongTeam));
+    assert_eq!(validator.rejections(RejectionReason::WrongTeam), 1);
+    assert_eq!(validator.rejections(RejectionReason::InvalidRobotId), 0);
+}
+
+#[test]
+/// Messages faster than the configured limits should be rejected
+fn test_reject_excessive_velocity() {
+    let limits = ValidationLimits {
+        max_linear_velocity: 10.0,
+        ..Default::default()
+    };
+    let mut validator = ControlMessageValidator::new(Team::Blue, 6, RobotTable::from_default_addresses(), limits);
+
+    let control_message = ControlMessageBuilder::new()
+        .team(Team::Blue)
+        .robot_id(0)
+        .body_x(100.0)
+        .build();
+
+    assert_eq!(validator.validate(control_message).err(), Some(RejectionReason::ExcessiveVelocity));
+}
+
+#[test]
+/// Out of range dribbler and kick values should be clamped instead of rejected
+fn test_clamp_dribbler_and_kick() {
+    let limits = ValidationLimits {
+        min_dribbler_speed: -2,
+        max_dribbler_speed: 2,
+        max_kick_strength: 3,
+        ..Default::default()
+    };
+    let mut validator = ControlMessageValidator::new(Team::Blue, 6, RobotTable::from_default_addresses(), limits);
+
+    let control_message = ControlMessageBuilder::new()
+        .team(Team::Blue)
+        .robot_id(1)
+        .dribbler_speed(-5)
+        .kick_strength(7)
+        .build();
+
+    let validated = validator.validate(control_message).unwrap();
+    assert_eq!(*validated.dribbler_speed, -2);
+    assert_eq!(*validated.kick_strength, 3);
+    assert_eq!(validator.rejections(RejectionReason::DribblerOutOfRange), 1);
+    assert_eq!(validator.rejections(RejectionReason::KickOutOfRange), 1);
+}
+
+#[test]
+/// The default limits should reject velocities no robot can reach
+fn test_default_velocity_limits() {
+    let mut validator = ControlMessageValidator::new(Team::Blue, 6, RobotTable::from_default_addresses(), ValidationLimits::default());
+
+    let control_message = ControlMessageBuilder::new()
+        .team(Team::Blue)
+        .robot_id(0)
+        .body_x(3.0)
+        .build();
+    assert!(validator.validate(control_message).is_ok());
+
+    let control_message = ControlMessageBuilder::new()
+        .team(Team::Blue)
+        .robot_id(0)
+        .body_y(-5.0)
+        .build();
+    assert_eq!(validator.validate(control_message).err(), Some(RejectionReason::ExcessiveVelocity));
+}
+
+#[test]
+/// The team of a Control Message is a single bit on the air (false is blue, true is
+/// yellow), so messages for blue robots should be rejected by the yellow team
+fn test_reject_wrong_team_on_the_air() {
+    let mut validator = ControlMessageValidator::new(Team::Yellow, 6, RobotTable::from_default_addresses(), ValidationLimits::default());
+
+    let control_message = ControlMessageBuilder::new()
+        .team(Team::Blue)
+        .robot_id(0)
+        .build();
+    assert!(!control_message.team);
+    assert_eq!(validator.validate(control_message).err(), Some(RejectionReason::WrongTeam));
+
+    let control_message = ControlMessageBuilder::new()
+        .team(Team::Yellow)
+        .robot_id(0)
+        .build();
+    assert!(validator.validate(control_message).is_ok());
+    assert_eq!(validator.rejections(RejectionReason::WrongTeam), 1);
+}
+
+#[test]
+/// By default the dribbler speed and kick strength may take any value of their fields
+fn test_default_dribbler_and_kick_limits() {
+    let mut validator = ControlMessageValidator::new(Team::Blue, 6, RobotTable::from_default_addresses(), ValidationLimits::default());
+
+    let control_message = ControlMessageBuilder::new()
+        .team(Team::Blue)
+        .robot_id(0)
+        .dribbler_speed(i8::MIN)
+        .kick_strength(u8::MAX)
+        .build();
+    let validated = validator.validate(control_message).unwrap();
+    assert_eq!(*validated.dribbler_speed, i8::MIN);
+    assert_eq!(*validated.kick_strength, u8::MAX);
+    assert_eq!(validator.rejections(RejectionReason::DribblerOutOfRange), 0);
+    assert_eq!(validator.rejections(RejectionReason::KickOutOfRange), 0);
+
+    // An empty dribbler range can't be applied
+    let limits = ValidationLimits {
+        min_dribbler_speed: 3,
+        max_dribbler_speed: -3,
+        ..Default::default()
+    };
+    assert!(limits.validate().is_err());
+    assert!(ValidationLimits::default().validate().is_ok());
+}
@@ -0,0 +1,188 @@
+//!
+//! Test the base station against simulated robots over the virtual radio link
+//!
+
+use std::net::UdpSocket;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use ncomm::node::Node;
+use ncomm::publisher_subscriber::{Publish, Receive};
+
+use packed_struct::PackedStruct;
+
+use robocup_base_station::config::{RadioConfig, RobotTable};
+use robocup_base_station::one_radio::radio_node::RadioNode;
+use robocup_base_station::publishers::radio_link::RadioLink;
+use robocup_base_station::publishers::virtual_radio::{VirtualFrame, VirtualLink, VirtualRadio};
+use robocup_base_station::radio_verify::verify_radio;
+use robocup_base_station::robot_sim::{RobotSimulator, SimulatedRobot};
+
+use robojackets_robocup_rtp::control_message::ControlMessageBuilder;
+use robojackets_robocup_rtp::Team;
+
+/// Run simulated robots in the background until the returned flag is set
+fn spawn_simulator(bind_address: String, base_station_address: String, robot_ids: Vec<u8>) -> (Arc<AtomicBool>, JoinHandle<u64>) {
+    let robot_table = RobotTable::from_default_addresses();
+    let robots = robot_ids.iter()
+        .map(|robot_id| SimulatedRobot::new(*robot_id, Team::Blue, robot_table.get(*robot_id).unwrap().radio_address))
+        .collect();
+    let link = VirtualLink::open(&bind_address, &base_station_address).unwrap();
+    let mut simulator = RobotSimulator::new(link, RadioConfig::default().channel, robots);
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let stop_simulator = stop.clone();
+    let handle = thread::spawn(move || {
+        while !stop_simulator.load(Ordering::Relaxed) {
+            simulator.poll(Duration::from_millis(10)).unwrap();
+        }
+        simulator.robots().iter().map(|robot| robot.commands_received).sum()
+    });
+    (stop, handle)
+}
+
+fn free_udp_address() -> String {
+    UdpSocket::bind("127.0.0.1:0").unwrap().local_addr().unwrap().to_string()
+}
+
+#[test]
+/// Frames should survive being encoded and decoded
+fn test_encode_decode_frame() {
+    let frame = VirtualFrame::new(104, [0xC3, 0xC3, 0xC3, 0xC3, 0xC1], vec![1, 2, 3]);
+    assert_eq!(VirtualFrame::decode(&frame.encode()), Some(frame));
+
+    let ack = VirtualFrame::ack(104, [0xE7; 5]);
+    assert!(VirtualFrame::decode(&ack.encode()).unwrap().is_ack());
+    assert_eq!(VirtualFrame::decode(&[104, 0xE7]), None);
+}
+
+/// Send a Control Message to every simulated robot and collect their statuses
+fn exchange_messages(mut virtual_radio: VirtualRadio, robot_ids: &[u8]) {
+    assert!(virtual_radio.initialize(&RadioConfig::default()));
+
+    for robot_id in robot_ids {
+        virtual_radio.send(ControlMessageBuilder::new().team(Team::Blue).robot_id(*robot_id).body_x(1.0).build());
+        assert!(virtual_radio.send_status(), "robot {} didn't acknowledge", robot_id);
+    }
+
+    let mut statuses = Vec::new();
+    for _ in 0..100 {
+        virtual_radio.update_data();
+        statuses.extend(virtual_radio.take_received());
+        if statuses.len() >= robot_ids.len() {
+            break;
+        }
+        thread::sleep(Duration::from_millis(5));
+    }
+    let mut status_ids: Vec<u8> = statuses.iter().map(|status| *status.robot_id).collect();
+    status_ids.sort();
+    assert_eq!(status_ids, robot_ids);
+}
+
+#[test]
+/// Simulated robots should acknowledge and respond to Control Messages over UDP
+fn test_virtual_radio_udp() {
+    let base_station_address = free_udp_address();
+    let simulator_address = free_udp_address();
+    let (stop, handle) = spawn_simulator(simulator_address.clone(), base_station_address.clone(), vec![0, 1]);
+
+    let link = VirtualLink::open(&base_station_address, &simulator_address).unwrap();
+    exchange_messages(VirtualRadio::new(link, &RobotTable::from_default_addresses()), &[0, 1]);
+
+    stop.store(true, Ordering::Relaxed);
+    assert_eq!(handle.join().unwrap(), 2);
+}
+
+#[cfg(unix)]
+#[test]
+/// Simulated robots should acknowledge and respond to Control Messages over Unix sockets
+fn test_virtual_radio_unix() {
+    let directory = std::env::temp_dir().join(format!("robot-sim-{}", std::process::id()));
+    std::fs::create_dir_all(&directory).unwrap();
+    let base_station_address = format!("unix:{}", directory.join("base-station.sock").display());
+    let simulator_address = format!("unix:{}", directory.join("robot-sim.sock").display());
+
+    let (stop, handle) = spawn_simulator(simulator_address.clone(), base_station_address.clone(), vec![2]);
+    let link = VirtualLink::open(&base_station_address, &simulator_address).unwrap();
+    exchange_messages(VirtualRadio::new(link, &RobotTable::from_default_addresses()), &[2]);
+
+    stop.store(true, Ordering::Relaxed);
+    assert_eq!(handle.join().unwrap(), 1);
+    let _ = std::fs::remove_dir_all(directory);
+}
+
+#[test]
+/// Control Messages to robots that aren't being simulated shouldn't be acknowledged
+fn test_virtual_radio_missing_robot() {
+    let base_station_address = free_udp_address();
+    let simulator_address = free_udp_address();
+    let (stop, handle) = spawn_simulator(simulator_address.clone(), base_station_address.clone(), vec![0]);
+
+    let link = VirtualLink::open(&base_station_address, &simulator_address).unwrap();
+    let mut virtual_radio = VirtualRadio::new(link, &RobotTable::from_default_addresses());
+    virtual_radio.set_ack_timeout(Duration::from_millis(20));
+    virtual_radio.send(ControlMessageBuilder::new().team(Team::Blue).robot_id(3).build());
+    assert!(!virtual_radio.send_status());
+
+    stop.store(true, Ordering::Relaxed);
+    assert_eq!(handle.join().unwrap(), 0);
+}
+
+#[test]
+/// The Radio Node should relay Control Messages from the Field Computer to simulated robots
+/// and their statuses back to the Field Computer
+fn test_radio_node_with_simulated_robots() {
+    let base_station_address = free_udp_address();
+    let simulator_address = free_udp_address();
+    let (stop, handle) = spawn_simulator(simulator_address.clone(), base_station_address.clone(), vec![0, 1]);
+
+    let field_computer = UdpSocket::bind("127.0.0.1:0").unwrap();
+    field_computer.set_read_timeout(Some(Duration::from_secs(1))).unwrap();
+    let field_computer_address = field_computer.local_addr().unwrap().to_string();
+    let control_message_address = free_udp_address();
+
+    let robot_table = RobotTable::from_default_addresses();
+    let link = VirtualLink::open(&base_station_address, &simulator_address).unwrap();
+    let mut virtual_radio = VirtualRadio::new(link, &robot_table);
+    assert!(virtual_radio.initialize(&RadioConfig::default()));
+    // The Virtual Radio has no registers to verify (so --strict-radio-config refuses it)
+    assert_eq!(verify_radio(&mut virtual_radio, &RadioConfig::default()), None);
+    let mut radio_node = RadioNode::new(
+        Team::Blue,
+        2,
+        robot_table.clone(),
+        RadioConfig::default(),
+        virtual_radio,
+        &control_message_address,
+        "127.0.0.1:0",
+        vec![&field_computer_address],
+        "127.0.0.1:0",
+        "127.0.0.1:0",
+    );
+    assert_eq!(radio_node.get_update_delay(), 50);
+    radio_node.set_update_delay(5);
+    assert_eq!(radio_node.get_update_delay(), 5);
+
+    for robot_id in 0..2 {
+        let control_message = ControlMessageBuilder::new().team(Team::Blue).robot_id(robot_id).body_x(1.0).build();
+        field_computer.send_to(&control_message.pack().unwrap(), &control_message_address).unwrap();
+    }
+    thread::sleep(Duration::from_millis(50));
+
+    for _ in 0..10 {
+        radio_node.update();
+        if radio_node.status_cache().len() == 2 {
+            break;
+        }
+    }
+    assert!(radio_node.status_cache().get(0).is_some());
+    assert!(radio_node.status_cache().get(1).is_some());
+
+    let mut buffer = [0u8; 64];
+    assert!(field_computer.recv(&mut buffer).is_ok());
+
+    stop.store(true, Ordering::Relaxed);
+    assert!(handle.join().unwrap() >= 2);
+}